@@ -2,6 +2,8 @@ import { inject, injectable, tagged } from "@mainsail/container";
 import { Contracts, Exceptions, Identifiers } from "@mainsail/contracts";
 import { ethers } from "ethers";
 
+import { getHistoryHeightFromBlockTag } from "../utils/resolve-block-tag.js";
+
 type TxData = {
 	from?: string;
 	to: string;
@@ -47,7 +49,7 @@ export class CallAction implements Contracts.Api.RPC.Action {
 	};
 
 	public async handle(parameters: [TxData, Contracts.Crypto.BlockTag]): Promise<any> {
-		const [data] = parameters;
+		const [data, blockTag] = parameters;
 
 		const {
 			block: { maxGasLimit },
@@ -61,6 +63,7 @@ export class CallAction implements Contracts.Api.RPC.Action {
 		}
 
 		const { success, output } = await this.evm.view({
+			blockNumber: await getHistoryHeightFromBlockTag(blockTag),
 			// default to zero address
 			data: Buffer.from(ethers.getBytes(data.data)),
 			from: data.from ?? "0x" + "0".repeat(40),
@@ -0,0 +1,175 @@
+import { Identifiers } from "@mainsail/contracts";
+import { schemas as keccak256Schemas } from "@mainsail/crypto-address-keccak256";
+import { schemas as cryptoBlockSchemas } from "@mainsail/crypto-block";
+import { schemas as validationSchemas } from "@mainsail/crypto-validation";
+import { Validator } from "@mainsail/validation";
+
+import { describe, Sandbox } from "../../../test-framework/source";
+import { schemas } from "../validation/index.js";
+import { EthGetLogs } from "./index.js";
+
+describe<{
+	sandbox: Sandbox;
+	action: EthGetLogs;
+	validator: Validator;
+	evm: any;
+	database: any;
+	store: any;
+}>("EthGetLogs", ({ beforeEach, it, assert, spy, stub }) => {
+	const address = "0x0000000000000000000000000000000000000001";
+	const blockHash = "0x0000000000000000000000000000000000000000000000000000000000000001";
+	const topic = "0x0000000000000000000000000000000000000000000000000000000000000002";
+
+	const log = {
+		address,
+		blockHash,
+		blockNumber: 16n,
+		data: "0x",
+		logIndex: 1n,
+		topics: [topic],
+		transactionHash: "0x0000000000000000000000000000000000000000000000000000000000000003",
+		transactionIndex: 2n,
+	};
+
+	beforeEach(async (context) => {
+		context.evm = {
+			getLogs: async () => ({ logs: [] }),
+		};
+
+		context.database = {
+			getBlockHeaderByHash: async () => undefined,
+		};
+
+		context.store = {
+			getBlockNumber: () => 20,
+		};
+
+		context.sandbox = new Sandbox();
+
+		context.sandbox.app.bind(Identifiers.Evm.Instance).toConstantValue(context.evm);
+		context.sandbox.app.bind(Identifiers.Database.Service).toConstantValue(context.database);
+		context.sandbox.app.bind(Identifiers.State.Store).toConstantValue(context.store);
+
+		context.action = context.sandbox.app.resolve(EthGetLogs);
+		context.validator = context.sandbox.app.resolve(Validator);
+	});
+
+	it("should have a name", ({ action }) => {
+		assert.equal(action.name, "eth_getLogs");
+	});
+
+	it("schema should be ok", ({ action, validator }) => {
+		validator.addSchema(keccak256Schemas.address);
+		validator.addSchema(validationSchemas.prefixedQuantityHex);
+		validator.addSchema(validationSchemas.prefixedDataHex);
+		validator.addSchema(cryptoBlockSchemas.prefixedBlockHash);
+		validator.addSchema(schemas.blockTag);
+		validator.addSchema(action.schema);
+
+		assert.undefined(validator.validate("jsonRpc_eth_getLogs", [{}]).errors);
+		assert.undefined(
+			validator.validate("jsonRpc_eth_getLogs", [
+				{ address: [address], fromBlock: "0x1", toBlock: "latest", topics: [null, topic, [topic]] },
+			]).errors,
+		);
+		assert.undefined(validator.validate("jsonRpc_eth_getLogs", [{ blockHash }]).errors);
+		assert.defined(validator.validate("jsonRpc_eth_getLogs", []).errors);
+		assert.defined(validator.validate("jsonRpc_eth_getLogs", [{}, {}]).errors);
+		assert.defined(validator.validate("jsonRpc_eth_getLogs", [{ fromBlock: "earliest" }]).errors);
+		assert.defined(validator.validate("jsonRpc_eth_getLogs", [{ address: "0x1" }]).errors);
+		assert.defined(validator.validate("jsonRpc_eth_getLogs", [{ topics: [null, null, null, null, null] }]).errors);
+		assert.defined(validator.validate("jsonRpc_eth_getLogs", [{ unknown: true }]).errors);
+	});
+
+	it("should query the latest block by default", async ({ action, evm }) => {
+		const spyGetLogs = spy(evm, "getLogs");
+
+		assert.equal(await action.handle([{}]), []);
+		spyGetLogs.calledOnce();
+		spyGetLogs.calledWith({ addresses: [], fromBlock: 20n, toBlock: 20n, topics: [] }, undefined, 10_000n);
+	});
+
+	it("should pass the block range", async ({ action, evm }) => {
+		const spyGetLogs = spy(evm, "getLogs");
+
+		await action.handle([{ fromBlock: "0x10", toBlock: "latest" }]);
+		spyGetLogs.calledOnce();
+		spyGetLogs.calledWith({ addresses: [], fromBlock: 16n, toBlock: 20n, topics: [] }, undefined, 10_000n);
+	});
+
+	it("should return no logs if the range is empty", async ({ action, evm }) => {
+		const spyGetLogs = spy(evm, "getLogs");
+
+		assert.equal(await action.handle([{ fromBlock: "0x11", toBlock: "0x10" }]), []);
+		spyGetLogs.neverCalled();
+	});
+
+	it("should pass the address and topic filters", async ({ action, evm }) => {
+		const spyGetLogs = spy(evm, "getLogs");
+
+		await action.handle([{ address, topics: [null, topic, [topic, blockHash]] }]);
+		spyGetLogs.calledWith(
+			{ addresses: [address], fromBlock: 20n, toBlock: 20n, topics: [[], [topic], [topic, blockHash]] },
+			undefined,
+			10_000n,
+		);
+
+		await action.handle([{ address: [address, address], topics: [] }]);
+		spyGetLogs.calledWith(
+			{ addresses: [address, address], fromBlock: 20n, toBlock: 20n, topics: [] },
+			undefined,
+			10_000n,
+		);
+	});
+
+	it("should query the block of the block hash", async ({ action, evm, database }) => {
+		const spyGetBlockHeaderByHash = stub(database, "getBlockHeaderByHash").resolvedValue({ number: 5 });
+		const spyGetLogs = spy(evm, "getLogs");
+
+		await action.handle([{ blockHash }]);
+		spyGetBlockHeaderByHash.calledWith(blockHash.slice(2));
+		spyGetLogs.calledWith({ addresses: [], fromBlock: 5n, toBlock: 5n, topics: [] }, undefined, 10_000n);
+	});
+
+	it("should throw if the block hash is unknown", async ({ action }) => {
+		await assert.rejects(() => action.handle([{ blockHash }]), "unknown block");
+	});
+
+	it("should throw if the block hash is combined with a block range", async ({ action, evm }) => {
+		const spyGetLogs = spy(evm, "getLogs");
+
+		await assert.rejects(
+			() => action.handle([{ blockHash, fromBlock: "0x1" }]),
+			"blockHash cannot be combined with fromBlock or toBlock",
+		);
+		await assert.rejects(
+			() => action.handle([{ blockHash, toBlock: "latest" }]),
+			"blockHash cannot be combined with fromBlock or toBlock",
+		);
+		spyGetLogs.neverCalled();
+	});
+
+	it("should throw if the limit is exceeded", async ({ action, evm }) => {
+		stub(evm, "getLogs").resolvedValue({ logs: [log], nextCursor: { blockNumber: 16n, logIndex: 2n } });
+
+		await assert.rejects(() => action.handle([{}]), "query returned more than 10000 results");
+	});
+
+	it("should return the logs", async ({ action, evm }) => {
+		stub(evm, "getLogs").resolvedValue({ logs: [log] });
+
+		assert.equal(await action.handle([{}]), [
+			{
+				address,
+				blockHash,
+				blockNumber: "0x10",
+				data: "0x",
+				logIndex: "0x1",
+				removed: false,
+				topics: [topic],
+				transactionHash: log.transactionHash,
+				transactionIndex: "0x2",
+			},
+		]);
+	});
+});
@@ -0,0 +1,122 @@
+import { inject, injectable, tagged } from "@mainsail/container";
+import { Contracts, Identifiers } from "@mainsail/contracts";
+
+import { resolveBlockTag } from "../utils/resolve-block-tag.js";
+
+type Topic = string | string[] | null;
+
+interface Filter {
+	fromBlock?: string;
+	toBlock?: string;
+	address?: string | string[];
+	topics?: Topic[];
+	blockHash?: string;
+}
+
+// Upper bound of logs returned by a single request, larger results have to be split into smaller block ranges
+const MAX_LOGS = 10_000n;
+
+@injectable()
+export class EthGetLogs implements Contracts.Api.RPC.Action {
+	@inject(Identifiers.Database.Service)
+	private readonly databaseService!: Contracts.Database.DatabaseService;
+
+	@inject(Identifiers.State.Store)
+	private readonly stateStore!: Contracts.State.Store;
+
+	@inject(Identifiers.Evm.Instance)
+	@tagged("instance", "rpc")
+	private readonly evm!: Contracts.Evm.Instance;
+
+	public readonly name: string = "eth_getLogs";
+
+	public readonly schema = {
+		$id: `jsonRpc_${this.name}`,
+
+		maxItems: 1,
+		minItems: 1,
+
+		prefixItems: [
+			{
+				additionalProperties: false,
+				properties: {
+					address: {
+						anyOf: [{ $ref: "address" }, { items: { $ref: "address" }, type: "array" }],
+					},
+					blockHash: { $ref: "prefixedBlockHash" },
+					fromBlock: { $ref: "blockTag" },
+					toBlock: { $ref: "blockTag" },
+					topics: {
+						items: {
+							anyOf: [
+								{ type: "null" },
+								{ $ref: "prefixedDataHex" },
+								{ items: { $ref: "prefixedDataHex" }, type: "array" },
+							],
+						},
+						maxItems: 4,
+						type: "array",
+					},
+				},
+				type: "object",
+			},
+		],
+		type: "array",
+	};
+
+	// https://ethereum.org/en/developers/docs/apis/json-rpc/#eth_getlogs
+	public async handle(parameters: [Filter]): Promise<object[]> {
+		const [filter] = parameters;
+
+		let fromBlock: number;
+		let toBlock: number;
+
+		if (filter.blockHash) {
+			if (filter.fromBlock !== undefined || filter.toBlock !== undefined) {
+				throw new Error("blockHash cannot be combined with fromBlock or toBlock");
+			}
+
+			const header = await this.databaseService.getBlockHeaderByHash(filter.blockHash.slice(2));
+			if (!header) {
+				throw new Error("unknown block");
+			}
+
+			fromBlock = header.number;
+			toBlock = header.number;
+		} else {
+			fromBlock = await resolveBlockTag(this.stateStore, filter.fromBlock ?? "latest");
+			toBlock = await resolveBlockTag(this.stateStore, filter.toBlock ?? "latest");
+		}
+
+		if (fromBlock > toBlock) {
+			return [];
+		}
+
+		const { logs, nextCursor } = await this.evm.getLogs(
+			{
+				addresses: filter.address === undefined ? [] : [filter.address].flat(),
+				fromBlock: BigInt(fromBlock),
+				toBlock: BigInt(toBlock),
+				topics: (filter.topics ?? []).map((topic) => (topic === null ? [] : [topic].flat())),
+			},
+			undefined,
+			MAX_LOGS,
+		);
+
+		if (nextCursor) {
+			throw new Error(`query returned more than ${MAX_LOGS} results`);
+		}
+
+		return logs.map((log) => ({
+			address: log.address,
+			blockHash: log.blockHash,
+			blockNumber: `0x${log.blockNumber.toString(16)}`,
+			data: log.data,
+			logIndex: `0x${log.logIndex.toString(16)}`,
+			removed: false,
+			topics: log.topics,
+			transactionHash: log.transactionHash,
+			transactionIndex: `0x${log.transactionIndex.toString(16)}`,
+		}));
+	}
+}
@@ -60,6 +60,14 @@ describe<{
 
 		assert.equal(await action.handle(["0x0000000000", "0x0", "latest"]), "0x0");
 		spyStorageAt.calledOnce();
-		spyStorageAt.calledWith("0x0000000000", BigInt(0));
+		spyStorageAt.calledWith("0x0000000000", BigInt(0), undefined);
+	});
+
+	it("should return storage at height", async ({ action, evm }) => {
+		const spyStorageAt = spy(evm, "storageAt");
+
+		assert.equal(await action.handle(["0x0000000000", "0x0", "0x10"]), "0x0");
+		spyStorageAt.calledOnce();
+		spyStorageAt.calledWith("0x0000000000", BigInt(0), BigInt(16));
 	});
 });
@@ -1,6 +1,8 @@
 import { inject, injectable, tagged } from "@mainsail/container";
 import { Contracts, Identifiers } from "@mainsail/contracts";
 
+import { getHistoryHeightFromBlockTag } from "../utils/resolve-block-tag.js";
+
 @injectable()
 export class EthGetStorageAtAction implements Contracts.Api.RPC.Action {
 	@inject(Identifiers.Evm.Instance)
@@ -24,8 +26,9 @@ export class EthGetStorageAtAction implements Contracts.Api.RPC.Action {
 	};
 
 	public async handle(parameters: [string, string, Contracts.Crypto.BlockTag]): Promise<any> {
-		const [address, slot] = parameters;
+		const [address, slot, blockTag] = parameters;
+		const height = await getHistoryHeightFromBlockTag(blockTag);
 
-		return await this.evm.storageAt(address, BigInt(slot));
+		return await this.evm.storageAt(address, BigInt(slot), height);
 	}
 }
@@ -30,23 +30,22 @@ export class EthGetTransactionReceipt implements Contracts.Api.RPC.Action {
 
 	// https://ethereum.org/en/developers/docs/apis/json-rpc/#eth_gettransactionreceipt
 	public async handle(parameters: [string, boolean]): Promise<object | null> {
-		const transaction = await this.databaseService.getTransactionByHash(parameters[0].slice(2));
-
-		if (!transaction) {
+		const { receipt } = await this.evm.getReceiptByHash(parameters[0]);
+		if (!receipt) {
 			// eslint-disable-next-line unicorn/no-null
 			return null;
 		}
 
-		assert.defined(transaction.data.blockNumber);
+		assert.defined(receipt.blockNumber);
 
-		const header = await this.databaseService.getBlockHeader(transaction.data.blockNumber);
-		if (!header) {
+		const transaction = await this.databaseService.getTransactionByHash(parameters[0].slice(2));
+		if (!transaction) {
 			// eslint-disable-next-line unicorn/no-null
 			return null;
 		}
 
-		const { receipt } = await this.evm.getReceipt(BigInt(transaction.data.blockNumber), transaction.hash);
-		if (!receipt) {
+		const header = await this.databaseService.getBlockHeader(Number(receipt.blockNumber));
+		if (!header) {
 			// eslint-disable-next-line unicorn/no-null
 			return null;
 		}
@@ -9,6 +9,7 @@ export * from "./eth-get-block-by-number.js";
 export * from "./eth-get-block-transaction-count-by-hash.js";
 export * from "./eth-get-block-transaction-count-by-number.js";
 export * from "./eth-get-code.js";
+export * from "./eth-get-logs.js";
 export * from "./eth-get-storage-at.js";
 export * from "./eth-get-transaction-by-block-hash-and-index.js";
 export * from "./eth-get-transaction-by-block-number-and-index.js";
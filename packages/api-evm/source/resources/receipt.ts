@@ -8,20 +8,32 @@ export class ReceiptResource {
 		header: Contracts.Crypto.BlockHeader,
 		receipt: Contracts.Evm.TransactionReceipt,
 	): Promise<object> {
+		const transactionHash = `0x${transaction.hash}`;
+		const transactionIndex = `0x${receipt.transactionIndex.toString(16)}`;
+		const blockHash = `0x${transaction.blockHash}`;
+		const blockNumber = `0x${transaction.blockNumber?.toString(16)}`;
+
 		/* eslint-disable sort-keys-fix/sort-keys-fix */
 		return {
-			transactionHash: `0x${transaction.hash}`,
-			transactionIndex: `0x${transaction.transactionIndex?.toString(16)}`,
-			blockHash: `0x${transaction.blockHash}`,
-			blockNumber: `0x${transaction.blockNumber?.toString(16)}`,
+			transactionHash,
+			transactionIndex,
+			blockHash,
+			blockNumber,
 			from: transaction.from,
 			to: transaction.to,
-			// TODO: Calculate
-			cumulativeGasUsed: "0x0", // The sum of the base fee and tip paid per unit of gas.
-			effectiveGasUsed: "0x0", // The total amount of gas used when this transaction was executed in the block.
+			cumulativeGasUsed: `0x${receipt.cumulativeGasUsed.toString(16)}`,
+			effectiveGasPrice: `0x${(receipt.effectiveGasPrice ?? BigInt(transaction.gasPrice)).toString(16)}`,
 			gasUsed: `0x${receipt.gasUsed.toString(16)}`,
 			contractAddress: receipt.contractAddress,
-			logs: receipt.logs,
+			logs: (receipt.logs ?? []).map((log: { logIndex: number }) => ({
+				...log,
+				logIndex: `0x${log.logIndex.toString(16)}`,
+				transactionHash,
+				transactionIndex,
+				blockHash,
+				blockNumber,
+				removed: false,
+			})),
 			logsBloom: `0x${header.logsBloom}`,
 			type: "0x2",
 			status: receipt.status ? "0x1" : "0x0",
@@ -15,6 +15,7 @@ import {
 	EthGetBlockTransactionCountByHash,
 	EthGetBlockTransactionCountByNumber,
 	EthGetCodeAction,
+	EthGetLogs,
 	EthGetStorageAtAction,
 	EthGetTransactionByBlockHashAndIndex,
 	EthGetTransactionByBlockNumberAndIndex,
@@ -107,6 +108,7 @@ export class ServiceProvider extends AbstractServiceProvider<Server> {
 			this.app.resolve(EthGetBlockTransactionCountByHash),
 			this.app.resolve(EthGetBlockTransactionCountByNumber),
 			this.app.resolve(EthGetCodeAction),
+			this.app.resolve(EthGetLogs),
 			this.app.resolve(EthGetStorageAtAction),
 			this.app.resolve(EthGetTransactionByBlockHashAndIndex),
 			this.app.resolve(EthGetTransactionByBlockNumberAndIndex),
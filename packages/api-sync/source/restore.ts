@@ -376,7 +376,7 @@ export class Restore {
 
 		const BATCH_SIZE = 1000n;
 		const CHUNK_SIZE = 250;
-		let offset: bigint | undefined = 0n;
+		let cursor: string | undefined;
 
 		if (this.snapshotImporter) {
 			for (const wallet of this.snapshotImporter.wallets) {
@@ -392,7 +392,7 @@ export class Restore {
 		let totalAccounts = 0;
 
 		do {
-			const result = await this.evm.getAccounts(offset ?? 0n, BATCH_SIZE);
+			const result = await this.evm.getAccounts(cursor, BATCH_SIZE);
 			const accounts: Models.Wallet[] = [];
 
 			for (const account of result.accounts) {
@@ -472,8 +472,8 @@ export class Restore {
 				await context.walletRepository.createQueryBuilder().insert().orIgnore().values(batch).execute();
 			}
 
-			offset = result.nextOffset;
-		} while (offset);
+			cursor = result.nextCursor;
+		} while (cursor);
 
 		context.totalSupply = context.totalSupply.plus(totalAccountBalance);
 
@@ -486,13 +486,13 @@ export class Restore {
 
 		const BATCH_SIZE = 1000n;
 		const CHUNK_SIZE = 250;
-		let offset: bigint | undefined = 0n;
+		let cursor: string | undefined;
 
 		let totalLegacyAccountBalance = 0n;
 		let totalLegacyAccounts = 0;
 
 		do {
-			const result = await this.evm.getLegacyColdWallets(offset ?? 0n, BATCH_SIZE);
+			const result = await this.evm.getLegacyColdWallets(cursor, BATCH_SIZE);
 
 			const legacyColdWallets: Models.LegacyColdWallet[] = [];
 
@@ -532,8 +532,8 @@ export class Restore {
 					.execute();
 			}
 
-			offset = result.nextOffset;
-		} while (offset);
+			cursor = result.nextCursor;
+		} while (cursor);
 
 		context.totalSupply = context.totalSupply.plus(totalLegacyAccountBalance);
 
@@ -545,13 +545,13 @@ export class Restore {
 		const t0 = performance.now();
 
 		const BATCH_SIZE = 1000n;
-		let offset: bigint | undefined = 0n;
+		let cursor: string | undefined;
 
 		let totalReceipts = 0;
 
 		do {
 			const receipts: Models.Receipt[] = [];
-			const result = await this.evm.getReceipts(offset ?? 0n, BATCH_SIZE);
+			const result = await this.evm.getReceipts(cursor, BATCH_SIZE);
 
 			for (const receipt of result.receipts) {
 				assert.defined(receipt.txHash);
@@ -578,9 +578,9 @@ export class Restore {
 				await context.receiptRepository.createQueryBuilder().insert().orIgnore().values(batch).execute();
 			}
 
-			offset = result.nextOffset;
+			cursor = result.nextCursor;
 			totalReceipts += receipts.length;
-		} while (offset);
+		} while (cursor);
 
 		const t1 = performance.now();
 		this.logger.info(`Restored ${totalReceipts.toLocaleString()} receipts in ${t1 - t0}ms`);
@@ -131,6 +131,8 @@ export interface TransactionSimulateContext {
 	readonly data: Buffer;
 	readonly blockContext: BlockContext;
 	readonly specId: SpecId;
+	/** Execute against the state at a past block */
+	readonly blockNumber?: bigint;
 }
 
 export interface TransactionViewContext {
@@ -139,25 +141,118 @@ export interface TransactionViewContext {
 	readonly data: Buffer;
 	readonly specId: SpecId;
 	readonly gasLimit?: bigint;
+	/** Execute against the state at a past block */
+	readonly blockNumber?: bigint;
 }
 
 export interface GetAccountsResult {
-	readonly nextOffset?: bigint;
+	/** Opaque cursor to resume reading after the last returned item */
+	readonly nextCursor?: string;
 	readonly accounts: AccountInfoExtended[];
 }
 
 export interface GetLegacyColdWalletsResult {
-	readonly nextOffset?: bigint;
+	/** Opaque cursor to resume reading after the last returned item */
+	readonly nextCursor?: string;
 	readonly wallets: LegacyColdWallet[];
 }
 
 export interface GetReceiptsResult {
-	readonly nextOffset?: bigint;
+	/** Opaque cursor to resume reading after the last returned item */
+	readonly nextCursor?: string;
 	readonly receipts: TransactionReceipt[];
 }
 
 export interface GetReceiptResult {
 	readonly receipt?: TransactionReceipt;
+	readonly pruned: boolean;
+}
+
+export interface GetReceiptByHashResult {
+	readonly receipt?: TransactionReceipt;
+	readonly blockHash?: string;
+	readonly transactionIndex?: number;
+}
+
+export interface BalanceFilter {
+	readonly minBalance?: bigint;
+	/** Omit to match both externally owned accounts and contracts */
+	readonly kind?: "Eoa" | "Contract";
+	readonly withLegacyAttributes?: boolean;
+}
+
+export interface LogFilter {
+	readonly fromBlock: bigint;
+	readonly toBlock: bigint;
+	/** Matches logs emitted by any of the addresses, omit to match all logs */
+	readonly addresses?: string[];
+	/** Topics by position, empty positions match any topic */
+	readonly topics?: string[][];
+}
+
+export interface LogCursor {
+	readonly blockNumber: bigint;
+	readonly logIndex: bigint;
+}
+
+export interface Log {
+	readonly address: string;
+	readonly topics: string[];
+	readonly data: string;
+	readonly blockNumber: bigint;
+	readonly blockHash: string;
+	readonly transactionHash: string;
+	readonly transactionIndex: number;
+	readonly logIndex: number;
+}
+
+export interface GetLogsResult {
+	readonly logs: Log[];
+	/** Only set if there are more matching logs */
+	readonly nextCursor?: LogCursor;
+}
+
+export interface StorageSlot {
+	readonly slot: bigint;
+	readonly value: string;
+}
+
+export interface GetStorageRangeResult {
+	/** Slots are ordered by their little endian bytes */
+	readonly slots: StorageSlot[];
+	/** Only set if there are more slots */
+	readonly nextSlot?: bigint;
+}
+
+export interface ContractDeployment {
+	readonly address: string;
+	/** Unknown for deployments committed before the sender got stored */
+	readonly deployer?: string;
+	readonly transactionHash: string;
+	readonly blockNumber: bigint;
+	readonly codeHash: string;
+}
+
+export interface GetContractDeploymentsResult {
+	readonly deployments: ContractDeployment[];
+	/** Only set if there are more deployments */
+	readonly nextCursor?: string;
+}
+
+export interface StorageProof {
+	readonly key: string;
+	readonly value: bigint;
+	readonly proof: string[];
+}
+
+export interface AccountProof {
+	readonly address: string;
+	readonly balance: bigint;
+	readonly nonce: bigint;
+	readonly codeHash: string;
+	readonly storageHash: string;
+	readonly accountProof: string[];
+	readonly storageProof: StorageProof[];
 }
 
 export interface BlockContext {
@@ -188,6 +283,13 @@ export interface CommitKey {
 	readonly blockHash?: string;
 }
 
+export interface RevertReason {
+	// One of `Error`, `Panic`, `Custom` or `Unknown`
+	readonly kind: string;
+	// The message of `Error`, the hex encoded code of `Panic` or the signature of `Custom`
+	readonly value?: string;
+}
+
 export interface TransactionReceipt {
 	readonly gasUsed: bigint;
 	readonly gasRefunded: bigint;
@@ -196,6 +298,18 @@ export interface TransactionReceipt {
 	readonly logs: any;
 	readonly output?: Buffer;
 
+	// Position within the block and block-wide totals, only set for committed receipts
+	readonly transactionIndex: number;
+	readonly cumulativeGasUsed: bigint;
+	// Unknown for receipts migrated from older databases
+	readonly from?: string;
+	readonly to?: string;
+	readonly effectiveGasPrice?: bigint;
+
+	// Set if the transaction failed
+	readonly revertReason?: RevertReason;
+	readonly haltReason?: string;
+
 	// Only present when reading receipts explicitly via `get_receipts`
 	readonly blockNumber?: bigint;
 	readonly txHash?: string;
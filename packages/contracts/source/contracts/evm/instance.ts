@@ -2,15 +2,24 @@ import { CommitHandler } from "../crypto/commit-handler.js";
 import {
 	AccountInfo,
 	AccountInfoExtended,
+	AccountProof,
+	BalanceFilter,
 	CalculateRoundValidatorsContext,
 	CommitKey,
+	ContractDeployment,
 	GenesisInfo,
 	GetAccountsResult,
+	GetContractDeploymentsResult,
 	GetLegacyColdWalletsResult,
+	GetLogsResult,
+	GetReceiptByHashResult,
 	GetReceiptResult,
 	GetReceiptsResult,
+	GetStorageRangeResult,
 	ImportLegacyColdWallet,
 	LegacyAttributes,
+	LogCursor,
+	LogFilter,
 	PrepareNextCommitContext,
 	PreverifyTransactionContext,
 	PreverifyTransactionResult,
@@ -34,17 +43,30 @@ export interface Instance extends CommitHandler {
 	getAccountInfoExtended(address: string, legacyAddress?: string): Promise<AccountInfoExtended>;
 	importAccountInfos(infos: AccountInfoExtended[]): Promise<void>;
 	importLegacyColdWallets(wallets: ImportLegacyColdWallet[]): Promise<void>;
-	getAccounts(offset: bigint, limit: bigint): Promise<GetAccountsResult>;
+	getAccounts(cursor: string | undefined, limit: bigint): Promise<GetAccountsResult>;
+	getAccountsByBalance(
+		filter: BalanceFilter,
+		cursor: string | undefined,
+		limit: bigint,
+	): Promise<GetAccountsResult>;
 	getLegacyAttributes(address: string, legacyAddress?: string): Promise<LegacyAttributes | null>;
-	getLegacyColdWallets(offset: bigint, limit: bigint): Promise<GetLegacyColdWalletsResult>;
-	getReceipts(offset: bigint, limit: bigint): Promise<GetReceiptsResult>;
+	getLegacyColdWallets(cursor: string | undefined, limit: bigint): Promise<GetLegacyColdWalletsResult>;
+	getReceipts(cursor: string | undefined, limit: bigint): Promise<GetReceiptsResult>;
 	getReceipt(height: bigint, txHash: string): Promise<GetReceiptResult>;
+	getReceiptByHash(txHash: string): Promise<GetReceiptByHashResult>;
+	getLogs(filter: LogFilter, cursor: LogCursor | undefined, limit: bigint): Promise<GetLogsResult>;
 	calculateRoundValidators(context: CalculateRoundValidatorsContext): Promise<void>;
 	updateRewardsAndVotes(context: UpdateRewardsAndVotesContext): Promise<void>;
 	logsBloom(commitKey: CommitKey): Promise<string>;
 	stateRoot(commitKey: CommitKey, currentHash: string): Promise<string>;
+	getCommittedStateRoot(blockNumber: bigint): Promise<string | undefined>;
+	getCommittedLogsBloom(blockNumber: bigint): Promise<string | undefined>;
 	codeAt(address: string, height?: bigint): Promise<string>;
-	storageAt(address: string, slot: bigint): Promise<string>;
+	storageAt(address: string, slot: bigint, height?: bigint): Promise<string>;
+	getStorageRange(address: string, start: bigint | undefined, limit: bigint): Promise<GetStorageRangeResult>;
+	getContractDeployment(address: string): Promise<ContractDeployment | undefined>;
+	getContractDeployments(cursor: string | undefined, limit: bigint): Promise<GetContractDeploymentsResult>;
+	getProof(address: string, slots: bigint[], blockNumber: bigint): Promise<AccountProof>;
 	snapshot(commitKey: CommitKey): Promise<void>;
 	rollback(commitKey: CommitKey): Promise<void>;
 	dispose(): Promise<void>;
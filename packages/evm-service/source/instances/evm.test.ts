@@ -262,7 +262,7 @@ describe<{
 		]);
 		await commit(commitKey);
 
-		assert.undefined((await instance.getLegacyColdWallets(0n, 100n)).wallets[0].mergeInfo);
+		assert.undefined((await instance.getLegacyColdWallets(undefined, 100n)).wallets[0].mergeInfo);
 
 		extendedInfo = await instance.getAccountInfoExtended(sender.address, legacyAddress);
 		assert.equal(extendedInfo.balance, 10n);
@@ -294,7 +294,7 @@ describe<{
 		await commit(commitKey);
 
 		// Legacy cold balance moved to native balance
-		assert.equal((await instance.getLegacyColdWallets(0n, 100n)).wallets[0].mergeInfo, {
+		assert.equal((await instance.getLegacyColdWallets(undefined, 100n)).wallets[0].mergeInfo, {
 			address: sender.address,
 			txHash: `0x${txHash}`,
 		});
@@ -323,7 +323,7 @@ describe<{
 		assert.equal(receipt.receipt.status, 1);
 		await commit(commitKey);
 
-		assert.equal((await instance.getLegacyColdWallets(0n, 100n)).wallets[0].mergeInfo, {
+		assert.equal((await instance.getLegacyColdWallets(undefined, 100n)).wallets[0].mergeInfo, {
 			address: sender.address,
 			txHash: `0x${txHash}`,
 		});
@@ -369,7 +369,7 @@ describe<{
 		]);
 		await commit(commitKey);
 
-		let { wallets } = await instance.getLegacyColdWallets(0n, 100n);
+		let { wallets, nextCursor } = await instance.getLegacyColdWallets(undefined, 1n);
 
 		assert.equal(wallets, [
 			{
@@ -379,7 +379,7 @@ describe<{
 			},
 		]);
 
-		({ wallets } = await instance.getLegacyColdWallets(1n, 100n));
+		({ wallets } = await instance.getLegacyColdWallets(nextCursor, 100n));
 		assert.empty(wallets);
 	});
 
@@ -120,8 +120,16 @@ export class EvmInstance implements Contracts.Evm.Instance, Contracts.Evm.Storag
 		return this.#evm.importLegacyColdWallets(wallets);
 	}
 
-	public async getAccounts(offset: bigint, limit: bigint): Promise<Contracts.Evm.GetAccountsResult> {
-		return this.#evm.getAccounts(offset, limit);
+	public async getAccounts(cursor: string | undefined, limit: bigint): Promise<Contracts.Evm.GetAccountsResult> {
+		return this.#evm.getAccounts(cursor, limit);
+	}
+
+	public async getAccountsByBalance(
+		filter: Contracts.Evm.BalanceFilter,
+		cursor: string | undefined,
+		limit: bigint,
+	): Promise<Contracts.Evm.GetAccountsResult> {
+		return this.#evm.getAccountsByBalance(filter, cursor, limit);
 	}
 
 	public async getLegacyAttributes(
@@ -132,20 +140,32 @@ export class EvmInstance implements Contracts.Evm.Instance, Contracts.Evm.Storag
 	}
 
 	public async getLegacyColdWallets(
-		offset: bigint,
+		cursor: string | undefined,
 		limit: bigint,
 	): Promise<Contracts.Evm.GetLegacyColdWalletsResult> {
-		return this.#evm.getLegacyColdWallets(offset, limit);
+		return this.#evm.getLegacyColdWallets(cursor, limit);
 	}
 
-	public async getReceipts(offset: bigint, limit: bigint): Promise<Contracts.Evm.GetReceiptsResult> {
-		return this.#evm.getReceipts(offset, limit);
+	public async getReceipts(cursor: string | undefined, limit: bigint): Promise<Contracts.Evm.GetReceiptsResult> {
+		return this.#evm.getReceipts(cursor, limit);
 	}
 
 	public async getReceipt(blockNumber: bigint, txHash: string): Promise<Contracts.Evm.GetReceiptResult> {
 		return this.#evm.getReceipt(blockNumber, txHash);
 	}
 
+	public async getReceiptByHash(txHash: string): Promise<Contracts.Evm.GetReceiptByHashResult> {
+		return this.#evm.getReceiptByHash(txHash);
+	}
+
+	public async getLogs(
+		filter: Contracts.Evm.LogFilter,
+		cursor: Contracts.Evm.LogCursor | undefined,
+		limit: bigint,
+	): Promise<Contracts.Evm.GetLogsResult> {
+		return this.#evm.getLogs(filter, cursor, limit);
+	}
+
 	public async updateRewardsAndVotes(context: Contracts.Evm.UpdateRewardsAndVotesContext): Promise<void> {
 		return this.#evm.updateRewardsAndVotes(context);
 	}
@@ -169,8 +189,31 @@ export class EvmInstance implements Contracts.Evm.Instance, Contracts.Evm.Storag
 		return this.#evm.codeAt(address, blockNumber);
 	}
 
-	public async storageAt(address: string, slot: bigint): Promise<string> {
-		return this.#evm.storageAt(address, slot);
+	public async storageAt(address: string, slot: bigint, blockNumber?: bigint): Promise<string> {
+		return this.#evm.storageAt(address, slot, blockNumber);
+	}
+
+	public async getStorageRange(
+		address: string,
+		start: bigint | undefined,
+		limit: bigint,
+	): Promise<Contracts.Evm.GetStorageRangeResult> {
+		return this.#evm.getStorageRange(address, start, limit);
+	}
+
+	public async getContractDeployment(address: string): Promise<Contracts.Evm.ContractDeployment | undefined> {
+		return this.#evm.getContractDeployment(address);
+	}
+
+	public async getContractDeployments(
+		cursor: string | undefined,
+		limit: bigint,
+	): Promise<Contracts.Evm.GetContractDeploymentsResult> {
+		return this.#evm.getContractDeployments(cursor, limit);
+	}
+
+	public async getProof(address: string, slots: bigint[], blockNumber: bigint): Promise<Contracts.Evm.AccountProof> {
+		return this.#evm.getProof(address, slots, blockNumber);
 	}
 
 	public async stateRoot(commitKey: Contracts.Evm.CommitKey, currentHash: string): Promise<string> {
@@ -181,6 +224,14 @@ export class EvmInstance implements Contracts.Evm.Instance, Contracts.Evm.Storag
 		return this.#evm.logsBloom(commitKey);
 	}
 
+	public async getCommittedStateRoot(blockNumber: bigint): Promise<string | undefined> {
+		return this.#evm.getCommittedStateRoot(blockNumber);
+	}
+
+	public async getCommittedLogsBloom(blockNumber: bigint): Promise<string | undefined> {
+		return this.#evm.getCommittedLogsBloom(blockNumber);
+	}
+
 	public async getState(): Promise<{ blockNumber: number; totalRound: number }> {
 		const state = await this.#evm.getState();
 		return { blockNumber: Number(state.blockNumber), totalRound: Number(state.totalRound) };
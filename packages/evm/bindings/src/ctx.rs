@@ -1,8 +1,12 @@
 use std::{path::PathBuf, str::FromStr};
 
 use mainsail_evm_core::{
+    balance_index::{AccountKind, BalanceFilter},
     db::{CommitData, CommitKey},
     legacy::LegacyAddress,
+    log_index::{LogFilter, LogPosition},
+    pruning::RetentionPolicy,
+    state_root::StateRootMode,
 };
 use napi::{JsBigInt, JsBuffer, JsFunction, JsNumber, JsString};
 use napi_derive::napi;
@@ -15,6 +19,23 @@ pub struct JsEvmOptions {
     pub path: JsString,
     pub logger: Option<JsFunction>,
     pub history_size: Option<JsBigInt>,
+    /// Number of most recent blocks that can be unwound, defaults to 1000 or the history size if larger
+    pub unwind_depth: Option<JsBigInt>,
+    /// Either "Legacy" (default) or "MerklePatriciaTrie", cannot be changed after the first commit
+    pub state_root_mode: Option<JsString>,
+    /// Number of most recent blocks for which the block data is kept, defaults to all blocks
+    pub block_retention: Option<JsBigInt>,
+    /// Number of most recent blocks for which the proofs are kept, defaults to all blocks
+    pub proof_retention: Option<JsBigInt>,
+    /// Number of most recent blocks for which the transactions are kept, defaults to all blocks
+    pub transaction_retention: Option<JsBigInt>,
+    /// Number of most recent blocks for which the receipts are kept, defaults to all blocks
+    pub receipt_retention: Option<JsBigInt>,
+    /// Opens an existing database without writing to it, e.g. from a secondary process
+    pub read_only: Option<bool>,
+    /// Keeps the state in a temporary database on tmpfs which is removed once the EVM is dropped,
+    /// the path is ignored. Fails if no tmpfs is available
+    pub in_memory: Option<bool>,
 }
 
 #[napi(object)]
@@ -46,6 +67,8 @@ pub struct JsTransactionSimulateContext {
     pub data: JsBuffer,
     pub block_context: JsBlockContext,
     pub spec_id: JsString,
+    /// Execute against the state at a past block
+    pub block_number: Option<JsBigInt>,
 }
 
 #[napi(object)]
@@ -71,6 +94,8 @@ pub struct JsTransactionViewContext {
     pub data: JsBuffer,
     pub spec_id: JsString,
     pub gas_limit: Option<JsBigInt>,
+    /// Execute against the state at a past block
+    pub block_number: Option<JsBigInt>,
 }
 
 #[napi(object)]
@@ -116,6 +141,32 @@ pub struct JsCommitKey {
     pub block_hash: Option<JsString>,
 }
 
+#[napi(object)]
+pub struct JsLogFilter {
+    pub from_block: JsBigInt,
+    pub to_block: JsBigInt,
+    /// Matches logs emitted by any of the addresses, omit to match all logs
+    pub addresses: Option<Vec<JsString>>,
+    /// Topics by position, a log matches if its topic equals any of the topics at that position.
+    /// Empty positions match any topic.
+    pub topics: Option<Vec<Vec<JsString>>>,
+}
+
+#[napi(object)]
+pub struct JsBalanceFilter {
+    pub min_balance: Option<JsBigInt>,
+    /// Either "Eoa" or "Contract", omit to match both
+    pub kind: Option<JsString>,
+    /// Only accounts with legacy attributes
+    pub with_legacy_attributes: Option<bool>,
+}
+
+#[napi(object)]
+pub struct JsLogCursor {
+    pub block_number: JsBigInt,
+    pub log_index: JsBigInt,
+}
+
 #[napi(object)]
 pub struct JsCommitData {
     pub commit_round: JsBigInt,
@@ -176,6 +227,7 @@ pub struct TxViewContext {
     pub data: Bytes,
     pub spec_id: SpecId,
     pub gas_limit: Option<u64>,
+    pub block_number: Option<u64>,
 }
 
 #[derive(Debug)]
@@ -189,6 +241,7 @@ pub struct TxSimulateContext {
     pub data: Bytes,
     pub block_context: BlockContext,
     pub spec_id: SpecId,
+    pub block_number: Option<u64>,
 }
 
 #[derive(Debug)]
@@ -231,6 +284,11 @@ pub struct EvmOptions {
     pub path: PathBuf,
     pub logger_callback: Option<JsFunction>,
     pub history_size: Option<u64>,
+    pub unwind_depth: Option<u64>,
+    pub state_root_mode: StateRootMode,
+    pub retention: RetentionPolicy,
+    pub read_only: bool,
+    pub in_memory: bool,
 }
 
 #[derive(Debug)]
@@ -243,9 +301,13 @@ pub struct ExecutionContext {
     pub nonce: Option<u64>,
     pub data: Bytes,
     pub tx_hash: Option<B256>,
+    // Position within the block, defaults to the execution order
+    pub tx_index: Option<u32>,
     pub block_context: Option<BlockContext>,
     pub spec_id: SpecId,
     pub stateful: bool,
+    // Executes against the state at the given block instead of the latest state
+    pub state_block_number: Option<u64>,
 }
 
 impl From<TxViewContext> for ExecutionContext {
@@ -259,9 +321,11 @@ impl From<TxViewContext> for ExecutionContext {
             nonce: None,
             data: value.data,
             tx_hash: None,
+            tx_index: None,
             block_context: None,
             spec_id: value.spec_id,
             stateful: false,
+            state_block_number: value.block_number,
         }
     }
 }
@@ -277,9 +341,11 @@ impl From<TxContext> for ExecutionContext {
             nonce: Some(value.nonce),
             data: value.data,
             tx_hash: Some(value.tx_hash),
+            tx_index: value.index,
             block_context: Some(value.block_context),
             spec_id: value.spec_id,
             stateful: true,
+            state_block_number: None,
         }
     }
 }
@@ -295,9 +361,11 @@ impl From<TxSimulateContext> for ExecutionContext {
             nonce: Some(value.nonce),
             data: value.data,
             tx_hash: None,
+            tx_index: None,
             block_context: Some(value.block_context),
             spec_id: value.spec_id,
             stateful: false,
+            state_block_number: value.block_number,
         }
     }
 }
@@ -320,6 +388,67 @@ impl TryFrom<JsCommitKey> for CommitKey {
     }
 }
 
+impl TryFrom<JsBalanceFilter> for BalanceFilter {
+    type Error = anyhow::Error;
+
+    fn try_from(value: JsBalanceFilter) -> Result<Self, Self::Error> {
+        let min_balance = match value.min_balance {
+            Some(min_balance) => utils::convert_bigint_to_u256(min_balance)?,
+            None => U256::ZERO,
+        };
+
+        let kind = match value.kind {
+            Some(kind) => Some(parse_account_kind(kind)?),
+            None => None,
+        };
+
+        Ok(BalanceFilter {
+            min_balance,
+            kind,
+            with_legacy_attributes: value.with_legacy_attributes.unwrap_or_default(),
+        })
+    }
+}
+
+impl TryFrom<JsLogFilter> for LogFilter {
+    type Error = anyhow::Error;
+
+    fn try_from(value: JsLogFilter) -> Result<Self, Self::Error> {
+        let mut addresses = Vec::new();
+        for address in value.addresses.unwrap_or_default() {
+            addresses.push(utils::create_address_from_js_string(address)?);
+        }
+
+        let mut topics = Vec::new();
+        for position in value.topics.unwrap_or_default() {
+            let mut position_topics = Vec::with_capacity(position.len());
+            for topic in position {
+                position_topics.push(utils::convert_string_to_b256(topic)?);
+            }
+
+            topics.push(position_topics);
+        }
+
+        Ok(LogFilter {
+            from_block: value.from_block.get_u64()?.0,
+            to_block: value.to_block.get_u64()?.0,
+            addresses,
+            topics,
+        })
+    }
+}
+
+impl TryFrom<JsLogCursor> for LogPosition {
+    type Error = anyhow::Error;
+
+    fn try_from(value: JsLogCursor) -> Result<Self, Self::Error> {
+        Ok(LogPosition {
+            block_number: value.block_number.get_u64()?.0,
+            log_index: value.log_index.get_u64()?.0,
+        })
+    }
+}
+
 impl TryFrom<JsCommitData> for CommitData {
     type Error = anyhow::Error;
 
@@ -430,6 +559,12 @@ impl TryFrom<JsTransactionSimulateContext> for TxSimulateContext {
             None
         };
 
+        let block_number = if let Some(block_number) = value.block_number {
+            Some(block_number.get_u64()?.0)
+        } else {
+            None
+        };
+
         Ok(TxSimulateContext {
             to,
             gas_limit: value.gas_limit.try_into()?,
@@ -440,6 +575,7 @@ impl TryFrom<JsTransactionSimulateContext> for TxSimulateContext {
             data: Bytes::from(buf.as_ref().to_owned()),
             block_context: value.block_context.try_into()?,
             spec_id: parse_spec_id(value.spec_id)?,
+            block_number,
         })
     }
 }
@@ -494,12 +630,19 @@ impl TryFrom<JsTransactionViewContext> for TxViewContext {
             None
         };
 
+        let block_number = if let Some(block_number) = value.block_number {
+            Some(block_number.get_u64()?.0)
+        } else {
+            None
+        };
+
         let tx_ctx = TxViewContext {
             from: utils::create_address_from_js_string(value.from)?,
             to: utils::create_address_from_js_string(value.to)?,
             data: Bytes::from(buf.as_ref().to_owned()),
             spec_id: parse_spec_id(value.spec_id)?,
             gas_limit,
+            block_number,
         };
 
         Ok(tx_ctx)
@@ -531,10 +674,34 @@ impl TryFrom<JsEvmOptions> for EvmOptions {
             None
         };
 
+        let unwind_depth = if let Some(unwind_depth) = value.unwind_depth {
+            Some(unwind_depth.get_u64()?.0)
+        } else {
+            None
+        };
+
+        let state_root_mode = if let Some(state_root_mode) = value.state_root_mode {
+            parse_state_root_mode(state_root_mode)?
+        } else {
+            StateRootMode::default()
+        };
+
+        let retention = RetentionPolicy {
+            blocks: parse_retention(value.block_retention)?,
+            proofs: parse_retention(value.proof_retention)?,
+            transactions: parse_retention(value.transaction_retention)?,
+            receipts: parse_retention(value.receipt_retention)?,
+        };
+
         Ok(EvmOptions {
             path: value.path.into_utf8()?.into_owned()?.into(),
             logger_callback: value.logger,
             history_size,
+            unwind_depth,
+            state_root_mode,
+            retention,
+            read_only: value.read_only.unwrap_or_default(),
+            in_memory: value.in_memory.unwrap_or_default(),
         })
     }
 }
@@ -570,6 +737,29 @@ impl TryFrom<JsUpdateRewardsAndVotesContext> for UpdateRewardsAndVotesContext {
     }
 }
 
+fn parse_retention(retention: Option<JsBigInt>) -> Result<Option<u64>, anyhow::Error> {
+    match retention {
+        Some(retention) => Ok(Some(retention.get_u64()?.0)),
+        None => Ok(None),
+    }
+}
+
+fn parse_state_root_mode(state_root_mode: JsString) -> Result<StateRootMode, anyhow::Error> {
+    match state_root_mode.into_utf8()?.into_owned()?.as_str() {
+        "Legacy" => Ok(StateRootMode::Legacy),
+        "MerklePatriciaTrie" => Ok(StateRootMode::MerklePatriciaTrie),
+        _ => Err(anyhow::anyhow!("invalid state_root_mode")),
+    }
+}
+
+fn parse_account_kind(kind: JsString) -> Result<AccountKind, anyhow::Error> {
+    match kind.into_utf8()?.into_owned()?.as_str() {
+        "Eoa" => Ok(AccountKind::Eoa),
+        "Contract" => Ok(AccountKind::Contract),
+        _ => Err(anyhow::anyhow!("invalid account kind")),
+    }
+}
+
 fn parse_spec_id(spec_id: JsString) -> Result<SpecId, anyhow::Error> {
     let spec_id = spec_id.into_utf8()?.into_owned()?;
 
@@ -1,32 +1,52 @@
-use std::{collections::HashMap, sync::Arc, u64};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::Arc,
+    u64,
+};
 
 use ctx::{
     BlockContext, CalculateRoundValidatorsContext, EvmOptions, ExecutionContext, GenesisContext,
-    JsCalculateRoundValidatorsContext, JsCommitData, JsCommitKey, JsEvmOptions, JsGenesisContext,
-    JsPrepareNextCommitContext, JsPreverifyTransactionContext, JsTransactionContext,
-    JsTransactionSimulateContext, JsTransactionViewContext, JsUpdateRewardsAndVotesContext,
-    PrepareNextCommitContext, PreverifyTxContext, TxContext, TxSimulateContext, TxViewContext,
-    UpdateRewardsAndVotesContext,
+    JsBalanceFilter, JsCalculateRoundValidatorsContext, JsCommitData, JsCommitKey, JsEvmOptions,
+    JsGenesisContext, JsLogCursor, JsLogFilter, JsPrepareNextCommitContext,
+    JsPreverifyTransactionContext, JsTransactionContext, JsTransactionSimulateContext,
+    JsTransactionViewContext, JsUpdateRewardsAndVotesContext, PrepareNextCommitContext,
+    PreverifyTxContext, TxContext, TxSimulateContext, TxViewContext, UpdateRewardsAndVotesContext,
 };
 use logger::JsLogger;
 use mainsail_evm_core::{
     account::AccountInfoExtended,
+    backup::{BackupHandle, BackupInfo},
+    balance_index::BalanceFilter,
+    code_references::GarbageCollection,
     db::{CommitData, CommitKey, GenesisInfo, PendingCommit, PersistentDB, PersistentDBOptions},
+    deployment::ContractDeployment,
     legacy::{LegacyAccountAttributes, LegacyAddress, LegacyColdWallet},
+    log_index::{IndexedLog, LogFilter, LogPosition},
     logger::LogLevel,
     logs_bloom,
-    receipt::{TxReceipt, map_execution_result},
+    pruning::{PruneState, Retained},
+    read_handle::{ReadHandle, ReadHandles},
+    receipt::{ExecutedTransaction, IndexedReceipt, TxReceipt, map_execution_result},
+    snapshot::SnapshotInfo,
     state_changes::AccountUpdate,
     state_commit, state_root,
+    stats::StorageStats,
+    trie::AccountProof,
+    verify::VerifyReport,
+};
+use napi::{
+    JsBigInt, JsObject, JsString,
+    bindgen_prelude::*,
+    threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode},
 };
-use napi::{JsBigInt, JsObject, JsString, bindgen_prelude::*};
 use napi_derive::napi;
 use result::{
     CommitResult, JsAccountInfoExtended, JsLegacyAttributes, JsLegacyColdWallet, PreverifyTxResult,
     TxViewResult,
 };
 use revm::{
-    Database, DatabaseCommit, ExecuteEvm, MainBuilder, MainContext,
+    Database, DatabaseCommit, DatabaseRef, ExecuteEvm, MainBuilder, MainContext,
     context::{
         BlockEnv, Cfg, ContextTr, TxEnv,
         result::{EVMError, ExecutionResult, ResultAndState},
@@ -70,7 +90,23 @@ impl EvmInner {
             }
         }
 
-        let persistent_db = PersistentDB::new(db_opts).expect("path ok");
+        if let Some(unwind_depth) = opts.unwind_depth {
+            db_opts = db_opts.with_unwind_depth(unwind_depth)
+        }
+
+        db_opts = db_opts
+            .with_state_root_mode(opts.state_root_mode)
+            .with_retention(opts.retention);
+
+        if opts.read_only {
+            db_opts = db_opts.with_read_only()
+        }
+
+        let persistent_db = match opts.in_memory {
+            true => PersistentDB::new_ephemeral(db_opts),
+            false => PersistentDB::new(db_opts),
+        }
+        .expect("path ok");
 
         EvmInner {
             persistent_db,
@@ -112,6 +148,12 @@ impl EvmInner {
         let result = self.transact_evm(tx_ctx.into());
 
         Ok(match result {
+            Err(EVMError::Database(
+                err @ (mainsail_evm_core::db::Error::HistoryUnavailable(_)
+                | mainsail_evm_core::db::Error::MissingRevert(_)),
+            )) => {
+                return Err(serde::de::Error::custom(err));
+            }
             Ok(r) => {
                 if !r.is_success() {
                     self.logger
@@ -137,57 +179,56 @@ impl EvmInner {
         })
     }
 
-    pub fn code_at(
+    pub fn get_storage_range(
         &mut self,
         address: Address,
-        block_number: Option<u64>,
-    ) -> std::result::Result<Bytes, EVMError<String>> {
-        let account = match block_number {
-            None => self.persistent_db.basic(address),
-            Some(block_number) => {
-                let result = self
-                    .persistent_db
-                    .get_historical_account_info(block_number, address);
-
-                match result {
-                    Ok((historical, _)) if historical.is_some() => Ok(historical),
-                    Ok((_, missing_fallback)) if missing_fallback => {
-                        self.persistent_db.basic(address)
-                    } // fallback
-                    Ok(_) => Ok(None),
-                    Err(err) => Err(err),
-                }
-            }
+        start: Option<U256>,
+        limit: u64,
+    ) -> std::result::Result<(Option<U256>, Vec<(U256, U256)>), EVMError<String>> {
+        match self.persistent_db.get_storage_range(address, start, limit) {
+            Ok(result) => Ok(result),
+            Err(err) => Err(EVMError::Database(
+                format!("failed reading storage range: {}", err).into(),
+            )),
         }
-        .map_err(|err| EVMError::Database(format!("account lookup failed: {}", err).into()))?;
+    }
 
-        match account {
-            Some(account) => {
-                let code = self
-                    .persistent_db
-                    .code_by_hash(account.code_hash)
-                    .map_err(|err| {
-                        EVMError::Database(format!("code lookup failed: {}", err).into())
-                    })?;
+    pub fn get_contract_deployment(
+        &mut self,
+        address: Address,
+    ) -> std::result::Result<Option<ContractDeployment>, EVMError<String>> {
+        match self.persistent_db.get_contract_deployment(address) {
+            Ok(result) => Ok(result),
+            Err(err) => Err(EVMError::Database(
+                format!("failed reading contract deployment: {}", err).into(),
+            )),
+        }
+    }
 
-                Ok(match code {
-                    Bytecode::LegacyAnalyzed(code) => code.original_bytes(),
-                    Bytecode::Eip7702(code) => code.raw.clone(),
-                })
-            }
-            None => Ok(Default::default()),
+    pub fn get_contract_deployments(
+        &mut self,
+        cursor: Option<Bytes>,
+        limit: u64,
+    ) -> std::result::Result<(Option<Bytes>, Vec<(Address, ContractDeployment)>), EVMError<String>>
+    {
+        match self.persistent_db.get_contract_deployments(cursor, limit) {
+            Ok(result) => Ok(result),
+            Err(err) => Err(EVMError::Database(
+                format!("failed reading contract deployments: {}", err).into(),
+            )),
         }
     }
 
-    pub fn storage_at(
+    pub fn get_proof(
         &mut self,
         address: Address,
-        slot: U256,
-    ) -> std::result::Result<U256, EVMError<String>> {
-        match self.persistent_db.storage(address, slot) {
-            Ok(slot) => Ok(slot),
+        slots: Vec<U256>,
+        block_number: u64,
+    ) -> std::result::Result<AccountProof, EVMError<String>> {
+        match self.persistent_db.get_proof(address, &slots, block_number) {
+            Ok(proof) => Ok(proof),
             Err(err) => Err(EVMError::Database(
-                format!("storage lookup failed: {}", err).into(),
+                format!("proof generation failed: {}", err).into(),
             )),
         }
     }
@@ -218,12 +259,7 @@ impl EvmInner {
             ctx.commit_key
         );
 
-        let genesis_info = self
-            .persistent_db
-            .genesis_info
-            .as_ref()
-            .expect("genesis info")
-            .clone();
+        let genesis_info = self.persistent_db.genesis_info().expect("genesis info");
 
         let abi = ethers_contract::BaseContract::from(
             ethers_core::abi::parse_abi(&["function calculateRoundValidators(uint8 n) external"])
@@ -255,7 +291,9 @@ impl EvmInner {
             gas_price: 0,
             spec_id: ctx.spec_id,
             tx_hash: None,
+            tx_index: None,
             stateful: true,
+            state_block_number: None,
         }) {
             Ok(receipt) => {
                 self.logger.log(
@@ -288,12 +326,7 @@ impl EvmInner {
             ctx.commit_key
         );
 
-        let genesis_info = self
-            .persistent_db
-            .genesis_info
-            .as_ref()
-            .expect("genesis info")
-            .clone();
+        let genesis_info = self.persistent_db.genesis_info().expect("genesis info");
 
         let nonce = self
             .get_account_nonce(&ctx.commit_key, genesis_info.deployer_account)
@@ -341,7 +374,9 @@ impl EvmInner {
                     gas_price: 0,
                     spec_id: ctx.spec_id,
                     tx_hash: None,
+                    tx_index: None,
                     stateful: true,
+                    state_block_number: None,
                 }) {
                     Ok(receipt) => {
                         self.logger.log(
@@ -368,37 +403,6 @@ impl EvmInner {
         }
     }
 
-    pub fn get_account_info(
-        &mut self,
-        address: Address,
-        block_number: Option<u64>,
-    ) -> std::result::Result<AccountInfo, EVMError<String>> {
-        let result = match block_number {
-            None => self.persistent_db.basic(address),
-            Some(block_number) => {
-                let result = self
-                    .persistent_db
-                    .get_historical_account_info(block_number, address);
-
-                match result {
-                    Ok((historical, _)) if historical.is_some() => Ok(historical),
-                    Ok((_, missing_fallback)) if missing_fallback => {
-                        self.persistent_db.basic(address)
-                    } // fallback
-                    Ok(_) => Ok(None),
-                    Err(err) => Err(err),
-                }
-            }
-        };
-
-        match result {
-            Ok(account) => Ok(account.unwrap_or_default()),
-            Err(err) => Err(EVMError::Database(
-                format!("account lookup failed: {}", err).into(),
-            )),
-        }
-    }
-
     pub fn get_account_info_extended(
         &mut self,
         address: Address,
@@ -496,17 +500,34 @@ impl EvmInner {
 
     pub fn get_accounts(
         &mut self,
-        offset: u64,
+        cursor: Option<Bytes>,
         limit: u64,
-    ) -> std::result::Result<(Option<u64>, Vec<AccountInfoExtended>), EVMError<String>> {
-        match self.persistent_db.get_accounts(offset, limit) {
-            Ok((next_offset, accounts)) => Ok((next_offset, accounts)),
+    ) -> std::result::Result<(Option<Bytes>, Vec<AccountInfoExtended>), EVMError<String>> {
+        match self.persistent_db.get_accounts(cursor, limit) {
+            Ok((next_cursor, accounts)) => Ok((next_cursor, accounts)),
             Err(err) => Err(EVMError::Database(
                 format!("failed reading accounts: {}", err).into(),
             )),
         }
     }
 
+    pub fn get_accounts_by_balance(
+        &mut self,
+        filter: BalanceFilter,
+        cursor: Option<Bytes>,
+        limit: u64,
+    ) -> std::result::Result<(Option<Bytes>, Vec<AccountInfoExtended>), EVMError<String>> {
+        match self
+            .persistent_db
+            .get_accounts_by_balance(&filter, cursor, limit)
+        {
+            Ok(result) => Ok(result),
+            Err(err) => Err(EVMError::Database(
+                format!("failed reading accounts by balance: {}", err).into(),
+            )),
+        }
+    }
+
     pub fn get_legacy_attributes(
         &mut self,
         address: Address,
@@ -545,11 +566,11 @@ impl EvmInner {
 
     pub fn get_legacy_cold_wallets(
         &mut self,
-        offset: u64,
+        cursor: Option<Bytes>,
         limit: u64,
-    ) -> std::result::Result<(Option<u64>, Vec<LegacyColdWallet>), EVMError<String>> {
-        match self.persistent_db.get_legacy_cold_wallets(offset, limit) {
-            Ok((next_offset, accounts)) => Ok((next_offset, accounts)),
+    ) -> std::result::Result<(Option<Bytes>, Vec<LegacyColdWallet>), EVMError<String>> {
+        match self.persistent_db.get_legacy_cold_wallets(cursor, limit) {
+            Ok((next_cursor, accounts)) => Ok((next_cursor, accounts)),
             Err(err) => Err(EVMError::Database(
                 format!("failed reading legacy cold wallets: {}", err).into(),
             )),
@@ -558,12 +579,12 @@ impl EvmInner {
 
     pub fn get_receipts(
         &mut self,
-        offset: u64,
+        cursor: Option<Bytes>,
         limit: u64,
-    ) -> std::result::Result<(Option<u64>, Vec<(u64, Vec<(B256, TxReceipt)>)>), EVMError<String>>
+    ) -> std::result::Result<(Option<Bytes>, Vec<(u64, Vec<(B256, TxReceipt)>)>), EVMError<String>>
     {
-        match self.persistent_db.get_receipts(offset, limit) {
-            Ok((next_offset, receipts)) => Ok((next_offset, receipts)),
+        match self.persistent_db.get_receipts(cursor, limit) {
+            Ok((next_cursor, receipts)) => Ok((next_cursor, receipts)),
             Err(err) => Err(EVMError::Database(
                 format!("failed reading receipts: {}", err).into(),
             )),
@@ -652,15 +673,16 @@ impl EvmInner {
         })
     }
 
-    pub fn get_receipt(
+    pub fn get_logs(
         &mut self,
-        block_number: u64,
-        tx_hash: B256,
-    ) -> std::result::Result<Option<TxReceipt>, EVMError<String>> {
-        match self.persistent_db.get_receipt(block_number, tx_hash) {
-            Ok(receipt) => Ok(receipt),
+        filter: LogFilter,
+        cursor: Option<LogPosition>,
+        limit: u64,
+    ) -> std::result::Result<(Option<LogPosition>, Vec<IndexedLog>), EVMError<String>> {
+        match self.persistent_db.get_logs(&filter, cursor, limit) {
+            Ok(result) => Ok(result),
             Err(err) => Err(EVMError::Database(
-                format!("failed reading receipt: {}", err).into(),
+                format!("failed reading logs: {}", err).into(),
             )),
         }
     }
@@ -797,6 +819,34 @@ impl EvmInner {
         }
     }
 
+    pub fn get_committed_state_root(
+        &mut self,
+        block_number: u64,
+    ) -> std::result::Result<Option<String>, EVMError<String>> {
+        let result = self.persistent_db.get_state_root(block_number);
+
+        match result {
+            Ok(result) => Ok(result.map(|state_root| state_root.encode_hex())),
+            Err(err) => Err(EVMError::Database(
+                format!("get_committed_state_root failed: {}", err).into(),
+            )),
+        }
+    }
+
+    pub fn get_committed_logs_bloom(
+        &mut self,
+        block_number: u64,
+    ) -> std::result::Result<Option<String>, EVMError<String>> {
+        let result = self.persistent_db.get_logs_bloom(block_number);
+
+        match result {
+            Ok(result) => Ok(result.map(|logs_bloom| logs_bloom.encode_hex())),
+            Err(err) => Err(EVMError::Database(
+                format!("get_committed_logs_bloom failed: {}", err).into(),
+            )),
+        }
+    }
+
     pub fn is_empty(&mut self) -> std::result::Result<bool, EVMError<String>> {
         let result = self.persistent_db.is_empty();
 
@@ -819,6 +869,28 @@ impl EvmInner {
         }
     }
 
+    pub fn get_prune_state(&mut self) -> std::result::Result<PruneState, EVMError<String>> {
+        let result = self.persistent_db.prune_state();
+
+        match result {
+            Ok(result) => Ok(result),
+            Err(err) => Err(EVMError::Database(
+                format!("get_prune_state failed: {}", err).into(),
+            )),
+        }
+    }
+
+    pub fn get_storage_stats(&mut self) -> std::result::Result<StorageStats, EVMError<String>> {
+        let result = self.persistent_db.storage_stats();
+
+        match result {
+            Ok(result) => Ok(result),
+            Err(err) => Err(EVMError::Database(
+                format!("get_storage_stats failed: {}", err).into(),
+            )),
+        }
+    }
+
     pub fn get_block_header_bytes(
         &mut self,
         block_number: u64,
@@ -826,7 +898,7 @@ impl EvmInner {
         let result = self.persistent_db.get_block_header_bytes(block_number);
 
         match result {
-            Ok(result) => Ok(result),
+            Ok(result) => Ok(result.present()),
             Err(err) => Err(EVMError::Database(
                 format!("get_block_header_bytes failed: {}", err).into(),
             )),
@@ -854,7 +926,7 @@ impl EvmInner {
         let result = self.persistent_db.get_proof_bytes(block_number);
 
         match result {
-            Ok(result) => Ok(result),
+            Ok(result) => Ok(result.present()),
             Err(err) => Err(EVMError::Database(
                 format!("get_proof_bytes failed: {}", err).into(),
             )),
@@ -868,7 +940,7 @@ impl EvmInner {
         let result = self.persistent_db.get_transaction_bytes(key);
 
         match result {
-            Ok(result) => Ok(result),
+            Ok(result) => Ok(result.present()),
             Err(err) => Err(EVMError::Database(
                 format!("get_transaction_bytes failed: {}", err).into(),
             )),
@@ -929,6 +1001,101 @@ impl EvmInner {
         }
     }
 
+    pub fn unwind_to(&mut self, block_number: u64) -> std::result::Result<(), EVMError<String>> {
+        self.logger.inner().log(
+            LogLevel::Info,
+            format!("unwinding to block {}", block_number),
+        );
+
+        let result = self.persistent_db.unwind_to(block_number);
+
+        match result {
+            Ok(result) => {
+                // any pending state builds on top of the unwound blocks
+                self.pending_commits.clear();
+                self.snapshot = None;
+
+                Ok(result)
+            }
+            Err(err) => Err(EVMError::Database(
+                format!("unwind_to failed: {}", err).into(),
+            )),
+        }
+    }
+
+    pub fn export_snapshot(
+        &mut self,
+        path: PathBuf,
+        include_blocks: bool,
+    ) -> std::result::Result<SnapshotInfo, EVMError<String>> {
+        match self.persistent_db.export_snapshot(&path, include_blocks) {
+            Ok(info) => Ok(info),
+            Err(err) => Err(EVMError::Database(
+                format!("export_snapshot failed: {}", err).into(),
+            )),
+        }
+    }
+
+    pub fn import_snapshot(
+        &mut self,
+        path: PathBuf,
+    ) -> std::result::Result<SnapshotInfo, EVMError<String>> {
+        self.logger.inner().log(
+            LogLevel::Info,
+            format!("importing snapshot {}", path.display()),
+        );
+
+        match self.persistent_db.import_snapshot(&path) {
+            Ok(info) => {
+                self.pending_commits.clear();
+                self.snapshot = None;
+
+                Ok(info)
+            }
+            Err(err) => Err(EVMError::Database(
+                format!("import_snapshot failed: {}", err).into(),
+            )),
+        }
+    }
+
+    pub fn backup_handle(&mut self) -> BackupHandle {
+        self.persistent_db.backup_handle()
+    }
+
+    pub fn verify(&mut self) -> std::result::Result<VerifyReport, EVMError<String>> {
+        match self.persistent_db.verify() {
+            Ok(report) => {
+                for inconsistency in report.inconsistencies.iter() {
+                    self.logger
+                        .inner()
+                        .log(LogLevel::Warning, format!("database {}", inconsistency));
+                }
+
+                Ok(report)
+            }
+            Err(err) => Err(EVMError::Database(format!("verify failed: {}", err).into())),
+        }
+    }
+
+    pub fn collect_garbage(&mut self) -> std::result::Result<GarbageCollection, EVMError<String>> {
+        match self.persistent_db.collect_garbage() {
+            Ok(collection) => {
+                self.logger.inner().log(
+                    LogLevel::Info,
+                    format!(
+                        "collected {} unreferenced contracts ({} bytes), retained {}",
+                        collection.deleted, collection.reclaimed_bytes, collection.retained
+                    ),
+                );
+
+                Ok(collection)
+            }
+            Err(err) => Err(EVMError::Database(
+                format!("collect_garbage failed: {}", err).into(),
+            )),
+        }
+    }
+
     pub fn dispose(&mut self) -> std::result::Result<(), EVMError<String>> {
         // replace to drop any reference to logging hook
         self.logger = JsLogger::new(None)
@@ -951,6 +1118,14 @@ impl EvmInner {
                     EVMError::Transaction(err) => {
                         return Err(EVMError::Transaction(err));
                     }
+                    EVMError::Database(
+                        err @ (mainsail_evm_core::db::Error::HistoryUnavailable(_)
+                        | mainsail_evm_core::db::Error::MissingRevert(_)),
+                    ) => {
+                        return Err(EVMError::Database(
+                            format!("historical state lookup failed: {}", err).into(),
+                        ));
+                    }
                     // EVMError::Header(_) => todo!(),
                     // EVMError::Database(_) => todo!(),
                     // EVMError::Custom(_) => todo!(),
@@ -966,6 +1141,22 @@ impl EvmInner {
         &mut self,
         ctx: ExecutionContext,
     ) -> std::result::Result<ExecutionResult, EVMError<mainsail_evm_core::db::Error>> {
+        // Read-only execution against the state of a past block
+        if let Some(block_number) = ctx.state_block_number {
+            assert!(!ctx.stateful);
+
+            let historical_state = self
+                .persistent_db
+                .historical_state(block_number)
+                .map_err(EVMError::Database)?;
+
+            let mut state_db = State::builder()
+                .with_database(WrapDatabaseRef(historical_state))
+                .build();
+
+            return Self::replay_evm(&ctx, &mut state_db).map(|result| result.result);
+        }
+
         let mut state_builder = State::builder().with_bundle_update();
 
         if let Some(commit_key) = ctx.block_context.as_ref().map(|b| &b.commit_key)
@@ -977,44 +1168,16 @@ impl EvmInner {
             }
         }
 
-        let state_db = state_builder
+        if let Some(block_ctx) = ctx.block_context.as_ref() {
+            state_builder =
+                state_builder.with_block_hashes(self.pending_block_hashes(block_ctx.commit_key.0));
+        }
+
+        let mut state_db = state_builder
             .with_database(WrapDatabaseRef(&self.persistent_db))
             .build();
 
-        let mut evm = revm::Context::mainnet()
-            .with_db(state_db)
-            .modify_cfg_chained(|cfg| {
-                cfg.spec = ctx.spec_id;
-                cfg.disable_nonce_check = ctx.nonce.is_none();
-            })
-            .modify_block_chained(|block_env: &mut BlockEnv| {
-                let Some(block_ctx) = ctx.block_context.as_ref() else {
-                    return;
-                };
-
-                block_env.number = U256::from(block_ctx.commit_key.0);
-                block_env.beneficiary = block_ctx.validator_address;
-                block_env.timestamp = U256::from(block_ctx.timestamp);
-                block_env.gas_limit = block_ctx.gas_limit;
-                block_env.difficulty = U256::ZERO;
-            })
-            .modify_tx_chained(|tx_env: &mut TxEnv| {
-                tx_env.gas_limit = ctx.gas_limit.unwrap_or_else(|| u64::MAX);
-                tx_env.gas_price = ctx.gas_price;
-                tx_env.gas_priority_fee = None;
-                tx_env.caller = ctx.from;
-                tx_env.value = ctx.value;
-                tx_env.nonce = ctx.nonce.unwrap_or_default();
-                tx_env.kind = match ctx.to {
-                    Some(recipient) => TxKind::Call(recipient),
-                    None => TxKind::Create,
-                };
-
-                tx_env.data = ctx.data;
-            })
-            .build_mainnet();
-
-        let result = evm.replay();
+        let result = Self::replay_evm(&ctx, &mut state_db);
 
         match result {
             Ok(result) => {
@@ -1024,14 +1187,27 @@ impl EvmInner {
                 if let Some(commit_key) = ctx.block_context.as_ref().map(|b| &b.commit_key)
                     && ctx.stateful
                 {
-                    let state_db = evm.db_mut();
                     state_db.commit(state);
 
                     if let Some(pending_commit) = self.pending_commits.get_mut(commit_key) {
                         pending_commit.cache = std::mem::take(&mut state_db.cache);
 
                         if let Some(tx_hash) = ctx.tx_hash {
-                            pending_commit.results.insert(tx_hash, result.clone());
+                            let index = match ctx.tx_index {
+                                Some(index) => index as u64,
+                                None => pending_commit.results.len() as u64,
+                            };
+
+                            pending_commit.results.insert(
+                                tx_hash,
+                                ExecutedTransaction {
+                                    index,
+                                    from: ctx.from,
+                                    to: ctx.to,
+                                    effective_gas_price: ctx.gas_price,
+                                    result: result.clone(),
+                                },
+                            );
                         }
 
                         pending_commit.transitions.add_transitions(
@@ -1052,6 +1228,55 @@ impl EvmInner {
         }
     }
 
+    fn replay_evm<DB: Database>(
+        ctx: &ExecutionContext,
+        db: DB,
+    ) -> std::result::Result<ResultAndState, EVMError<DB::Error>> {
+        let mut evm = revm::Context::mainnet()
+            .with_db(db)
+            .modify_cfg_chained(|cfg| {
+                cfg.spec = ctx.spec_id;
+                cfg.disable_nonce_check = ctx.nonce.is_none();
+            })
+            .modify_block_chained(|block_env: &mut BlockEnv| {
+                let Some(block_ctx) = ctx.block_context.as_ref() else {
+                    if let Some(block_number) = ctx.state_block_number {
+                        block_env.number = U256::from(block_number);
+                    }
+
+                    return;
+                };
+
+                block_env.number = U256::from(block_ctx.commit_key.0);
+                block_env.beneficiary = block_ctx.validator_address;
+                block_env.timestamp = U256::from(block_ctx.timestamp);
+                block_env.gas_limit = block_ctx.gas_limit;
+                block_env.difficulty = U256::ZERO;
+            })
+            .modify_tx_chained(|tx_env: &mut TxEnv| {
+                tx_env.gas_limit = ctx.gas_limit.unwrap_or_else(|| u64::MAX);
+                tx_env.gas_price = ctx.gas_price;
+                tx_env.gas_priority_fee = None;
+                tx_env.caller = ctx.from;
+                tx_env.value = ctx.value;
+                tx_env.nonce = ctx.nonce.unwrap_or_default();
+                tx_env.kind = match ctx.to {
+                    Some(recipient) => TxKind::Call(recipient),
+                    None => TxKind::Create,
+                };
+
+                tx_env.data = ctx.data.clone();
+            })
+            .build_mainnet();
+
+        evm.replay()
+    }
+
+    // Hashes of processed blocks which are not persisted yet, the persistent db only knows about committed blocks.
+    fn pending_block_hashes(&self, block_number: u64) -> BTreeMap<u64, B256> {
+        state_commit::pending_block_hashes(self.pending_commits.keys(), block_number)
+    }
+
     fn get_account_nonce(
         &mut self,
         commit_key: &CommitKey,
@@ -1099,33 +1324,151 @@ impl EvmInner {
     #[inline]
     fn genesis_block_number(&mut self) -> u64 {
         self.persistent_db
-            .genesis_info
-            .as_ref()
-            .cloned()
+            .genesis_info()
             .unwrap_or_default()
             .initial_block_number
     }
 }
 
-// The EVM wrapper is exposed to JavaScript.
-
-#[napi(js_name = "Evm")]
-pub struct JsEvmWrapper {
-    evm: Arc<tokio::sync::Mutex<EvmInner>>,
+// API reads are served from read handles and thus do not wait for blocks being processed or
+// committed.
+fn open_read_handle(
+    read_handles: &ReadHandles,
+) -> std::result::Result<ReadHandle, EVMError<String>> {
+    read_handles
+        .open()
+        .map_err(|err| EVMError::Database(format!("failed opening read handle: {}", err).into()))
 }
 
-#[napi]
-impl JsEvmWrapper {
-    #[napi(constructor)]
-    pub fn new(opts: JsEvmOptions) -> Result<Self> {
-        let opts = EvmOptions::try_from(opts)?;
-        Ok(JsEvmWrapper {
-            evm: Arc::new(tokio::sync::Mutex::new(EvmInner::new(opts))),
-        })
+fn get_account_info(
+    read_handles: &ReadHandles,
+    address: Address,
+    block_number: Option<u64>,
+) -> std::result::Result<AccountInfo, EVMError<String>> {
+    let db = open_read_handle(read_handles)?;
+
+    // Blocks outside of the history window are an error rather than the current state
+    let result = match block_number {
+        None => db.basic_ref(address),
+        Some(block_number) => db
+            .historical_state(block_number)
+            .and_then(|state| state.basic_ref(address)),
+    };
+
+    match result {
+        Ok(account) => Ok(account.unwrap_or_default()),
+        Err(err) => Err(EVMError::Database(
+            format!("account lookup failed: {}", err).into(),
+        )),
     }
+}
 
-    #[napi(ts_return_type = "Promise<JsPreverifyTransactionResult>")]
-    pub fn preverify_transaction(
+fn code_at(
+    read_handles: &ReadHandles,
+    address: Address,
+    block_number: Option<u64>,
+) -> std::result::Result<Bytes, EVMError<String>> {
+    let db = open_read_handle(read_handles)?;
+
+    let account = match block_number {
+        None => db.basic_ref(address),
+        Some(block_number) => db
+            .historical_state(block_number)
+            .and_then(|state| state.basic_ref(address)),
+    }
+    .map_err(|err| EVMError::Database(format!("account lookup failed: {}", err).into()))?;
+
+    match account {
+        Some(account) => {
+            let code = db
+                .code_by_hash_ref(account.code_hash)
+                .map_err(|err| EVMError::Database(format!("code lookup failed: {}", err).into()))?;
+
+            Ok(match code {
+                Bytecode::LegacyAnalyzed(code) => code.original_bytes(),
+                Bytecode::Eip7702(code) => code.raw.clone(),
+            })
+        }
+        None => Ok(Default::default()),
+    }
+}
+
+fn storage_at(
+    read_handles: &ReadHandles,
+    address: Address,
+    slot: U256,
+    block_number: Option<u64>,
+) -> std::result::Result<U256, EVMError<String>> {
+    let db = open_read_handle(read_handles)?;
+
+    let result = match block_number {
+        None => db.storage_ref(address, slot),
+        Some(block_number) => db
+            .historical_state(block_number)
+            .and_then(|state| state.storage_ref(address, slot)),
+    };
+
+    match result {
+        Ok(slot) => Ok(slot),
+        Err(err) => Err(EVMError::Database(
+            format!("storage lookup failed: {}", err).into(),
+        )),
+    }
+}
+
+fn get_receipt(
+    read_handles: &ReadHandles,
+    block_number: u64,
+    tx_hash: B256,
+) -> std::result::Result<Retained<TxReceipt>, EVMError<String>> {
+    let db = open_read_handle(read_handles)?;
+
+    match db.get_receipt(block_number, tx_hash) {
+        Ok(receipt) => Ok(receipt),
+        Err(err) => Err(EVMError::Database(
+            format!("failed reading receipt: {}", err).into(),
+        )),
+    }
+}
+
+fn get_receipt_by_hash(
+    read_handles: &ReadHandles,
+    tx_hash: B256,
+) -> std::result::Result<Option<IndexedReceipt>, EVMError<String>> {
+    let db = open_read_handle(read_handles)?;
+
+    match db.get_receipt_by_hash(tx_hash) {
+        Ok(receipt) => Ok(receipt),
+        Err(err) => Err(EVMError::Database(
+            format!("failed reading receipt: {}", err).into(),
+        )),
+    }
+}
+
+// The EVM wrapper is exposed to JavaScript.
+
+#[napi(js_name = "Evm")]
+pub struct JsEvmWrapper {
+    evm: Arc<tokio::sync::Mutex<EvmInner>>,
+    read_handles: ReadHandles,
+}
+
+#[napi]
+impl JsEvmWrapper {
+    #[napi(constructor)]
+    pub fn new(opts: JsEvmOptions) -> Result<Self> {
+        let opts = EvmOptions::try_from(opts)?;
+        let evm = EvmInner::new(opts);
+        let read_handles = evm.persistent_db.read_handles();
+
+        Ok(JsEvmWrapper {
+            evm: Arc::new(tokio::sync::Mutex::new(evm)),
+            read_handles,
+        })
+    }
+
+    #[napi(ts_return_type = "Promise<JsPreverifyTransactionResult>")]
+    pub fn preverify_transaction(
         &mut self,
         node_env: Env,
         tx_ctx: JsPreverifyTransactionContext,
@@ -1239,7 +1582,7 @@ impl JsEvmWrapper {
         };
 
         node_env.execute_tokio_future(
-            Self::get_account_info_async(self.evm.clone(), address, block_number),
+            Self::get_account_info_async(self.read_handles.clone(), address, block_number),
             |&mut node_env, result| Ok(result::JsAccountInfo::new(&node_env, result)?),
         )
     }
@@ -1302,14 +1645,38 @@ impl JsEvmWrapper {
     pub fn get_accounts(
         &mut self,
         node_env: Env,
-        offset: JsBigInt,
+        cursor: Option<JsString>,
+        limit: JsBigInt,
+    ) -> Result<JsObject> {
+        let cursor = match cursor {
+            Some(cursor) => Some(utils::convert_string_to_bytes(cursor)?),
+            None => None,
+        };
+        let limit = limit.get_u64()?.0;
+
+        node_env.execute_tokio_future(
+            Self::get_accounts_async(self.evm.clone(), cursor, limit),
+            |&mut node_env, result| Ok(result::JsGetAccounts::new(&node_env, result.0, result.1)?),
+        )
+    }
+
+    #[napi(ts_return_type = "Promise<JsGetAccounts>")]
+    pub fn get_accounts_by_balance(
+        &mut self,
+        node_env: Env,
+        filter: JsBalanceFilter,
+        cursor: Option<JsString>,
         limit: JsBigInt,
     ) -> Result<JsObject> {
-        let offset = offset.get_u64()?.0;
+        let filter = BalanceFilter::try_from(filter)?;
+        let cursor = match cursor {
+            Some(cursor) => Some(utils::convert_string_to_bytes(cursor)?),
+            None => None,
+        };
         let limit = limit.get_u64()?.0;
 
         node_env.execute_tokio_future(
-            Self::get_accounts_async(self.evm.clone(), offset, limit),
+            Self::get_accounts_by_balance_async(self.evm.clone(), filter, cursor, limit),
             |&mut node_env, result| Ok(result::JsGetAccounts::new(&node_env, result.0, result.1)?),
         )
     }
@@ -1343,14 +1710,17 @@ impl JsEvmWrapper {
     pub fn get_legacy_cold_wallets(
         &mut self,
         node_env: Env,
-        offset: JsBigInt,
+        cursor: Option<JsString>,
         limit: JsBigInt,
     ) -> Result<JsObject> {
-        let offset = offset.get_u64()?.0;
+        let cursor = match cursor {
+            Some(cursor) => Some(utils::convert_string_to_bytes(cursor)?),
+            None => None,
+        };
         let limit = limit.get_u64()?.0;
 
         node_env.execute_tokio_future(
-            Self::get_legacy_cold_wallets_async(self.evm.clone(), offset, limit),
+            Self::get_legacy_cold_wallets_async(self.evm.clone(), cursor, limit),
             |&mut node_env, result| {
                 Ok(result::JsGetLegacyColdWallets::new(
                     &node_env, result.0, result.1,
@@ -1363,14 +1733,17 @@ impl JsEvmWrapper {
     pub fn get_receipts(
         &mut self,
         node_env: Env,
-        offset: JsBigInt,
+        cursor: Option<JsString>,
         limit: JsBigInt,
     ) -> Result<JsObject> {
-        let offset = offset.get_u64()?.0;
+        let cursor = match cursor {
+            Some(cursor) => Some(utils::convert_string_to_bytes(cursor)?),
+            None => None,
+        };
         let limit = limit.get_u64()?.0;
 
         node_env.execute_tokio_future(
-            Self::get_receipts_async(self.evm.clone(), offset, limit),
+            Self::get_receipts_async(self.evm.clone(), cursor, limit),
             |&mut node_env, result| Ok(result::JsGetReceipts::new(&node_env, result.0, result.1)?),
         )
     }
@@ -1386,7 +1759,7 @@ impl JsEvmWrapper {
         let tx_hash = utils::convert_string_to_b256(tx_hash)?;
 
         node_env.execute_tokio_future(
-            Self::get_receipt_async(self.evm.clone(), block_number, tx_hash),
+            Self::get_receipt_async(self.read_handles.clone(), block_number, tx_hash),
             move |&mut node_env, result| {
                 Ok(result::JsGetReceipt::new(
                     &node_env,
@@ -1398,6 +1771,39 @@ impl JsEvmWrapper {
         )
     }
 
+    #[napi(ts_return_type = "Promise<JsGetReceiptByHash>")]
+    pub fn get_receipt_by_hash(&mut self, node_env: Env, tx_hash: JsString) -> Result<JsObject> {
+        let tx_hash = utils::convert_string_to_b256(tx_hash)?;
+
+        node_env.execute_tokio_future(
+            Self::get_receipt_by_hash_async(self.read_handles.clone(), tx_hash),
+            move |&mut node_env, result| {
+                Ok(result::JsGetReceiptByHash::new(&node_env, result, tx_hash)?)
+            },
+        )
+    }
+
+    #[napi(ts_return_type = "Promise<JsGetLogs>")]
+    pub fn get_logs(
+        &mut self,
+        node_env: Env,
+        filter: JsLogFilter,
+        cursor: Option<JsLogCursor>,
+        limit: JsBigInt,
+    ) -> Result<JsObject> {
+        let filter = LogFilter::try_from(filter)?;
+        let cursor = match cursor {
+            Some(cursor) => Some(LogPosition::try_from(cursor)?),
+            None => None,
+        };
+        let limit = limit.get_u64()?.0;
+
+        node_env.execute_tokio_future(
+            Self::get_logs_async(self.evm.clone(), filter, cursor, limit),
+            |&mut node_env, result| Ok(result::JsGetLogs::new(&node_env, result.0, result.1)?),
+        )
+    }
+
     #[napi(ts_return_type = "Promise<string>")]
     pub fn code_at(
         &mut self,
@@ -1412,7 +1818,7 @@ impl JsEvmWrapper {
         };
 
         node_env.execute_tokio_future(
-            Self::code_at_async(self.evm.clone(), address, block_number),
+            Self::code_at_async(self.read_handles.clone(), address, block_number),
             |&mut node_env, result| Ok(node_env.create_string_from_std(result)?),
         )
     }
@@ -1423,15 +1829,114 @@ impl JsEvmWrapper {
         node_env: Env,
         address: JsString,
         slot: JsBigInt,
+        block_number: Option<JsBigInt>,
     ) -> Result<JsObject> {
         let address = utils::create_address_from_js_string(address)?;
         let slot = utils::convert_bigint_to_u256(slot)?;
+        let block_number = match block_number {
+            Some(block_number) => Some(block_number.get_u64()?.0),
+            None => None,
+        };
+
         node_env.execute_tokio_future(
-            Self::storage_at_async(self.evm.clone(), address, slot),
+            Self::storage_at_async(self.read_handles.clone(), address, slot, block_number),
             |&mut node_env, result| Ok(node_env.create_string_from_std(result)?),
         )
     }
 
+    /// Lists the non-zero storage slots of a contract in the latest state, beginning at `start`.
+    #[napi(ts_return_type = "Promise<JsGetStorageRange>")]
+    pub fn get_storage_range(
+        &mut self,
+        node_env: Env,
+        address: JsString,
+        start: Option<JsBigInt>,
+        limit: JsBigInt,
+    ) -> Result<JsObject> {
+        let address = utils::create_address_from_js_string(address)?;
+        let start = match start {
+            Some(start) => Some(utils::convert_bigint_to_u256(start)?),
+            None => None,
+        };
+        let limit = limit.get_u64()?.0;
+
+        node_env.execute_tokio_future(
+            Self::get_storage_range_async(self.evm.clone(), address, start, limit),
+            |&mut node_env, result| {
+                Ok(result::JsGetStorageRange::new(
+                    &node_env, result.0, result.1,
+                )?)
+            },
+        )
+    }
+
+    /// Looks up the creation info of a contract deployed by a transaction.
+    #[napi(ts_return_type = "Promise<JsContractDeployment | undefined>")]
+    pub fn get_contract_deployment(
+        &mut self,
+        node_env: Env,
+        address: JsString,
+    ) -> Result<JsObject> {
+        let address = utils::create_address_from_js_string(address)?;
+
+        node_env.execute_tokio_future(
+            Self::get_contract_deployment_async(self.evm.clone(), address),
+            move |&mut node_env, result| {
+                Ok(match result {
+                    Some(deployment) => Some(result::JsContractDeployment::new(
+                        &node_env, address, deployment,
+                    )?),
+                    None => None,
+                })
+            },
+        )
+    }
+
+    #[napi(ts_return_type = "Promise<JsGetContractDeployments>")]
+    pub fn get_contract_deployments(
+        &mut self,
+        node_env: Env,
+        cursor: Option<JsString>,
+        limit: JsBigInt,
+    ) -> Result<JsObject> {
+        let cursor = match cursor {
+            Some(cursor) => Some(utils::convert_string_to_bytes(cursor)?),
+            None => None,
+        };
+        let limit = limit.get_u64()?.0;
+
+        node_env.execute_tokio_future(
+            Self::get_contract_deployments_async(self.evm.clone(), cursor, limit),
+            |&mut node_env, result| {
+                Ok(result::JsGetContractDeployments::new(
+                    &node_env, result.0, result.1,
+                )?)
+            },
+        )
+    }
+
+    #[napi(ts_return_type = "Promise<JsAccountProof>")]
+    pub fn get_proof(
+        &mut self,
+        node_env: Env,
+        address: JsString,
+        slots: Vec<JsBigInt>,
+        block_number: JsBigInt,
+    ) -> Result<JsObject> {
+        let address = utils::create_address_from_js_string(address)?;
+        let block_number = block_number.get_u64()?.0;
+
+        let mut storage_slots = Vec::with_capacity(slots.len());
+        for slot in slots {
+            storage_slots.push(utils::convert_bigint_to_u256(slot)?);
+        }
+
+        node_env.execute_tokio_future(
+            Self::get_proof_async(self.evm.clone(), address, storage_slots, block_number),
+            |&mut node_env, result| Ok(result::JsAccountProof::new(&node_env, result)?),
+        )
+    }
+
     #[napi(ts_return_type = "Promise<JsCommitResult>")]
     pub fn commit(
         &mut self,
@@ -1476,6 +1981,42 @@ impl JsEvmWrapper {
         )
     }
 
+    #[napi(ts_return_type = "Promise<string | undefined>")]
+    pub fn get_committed_state_root(
+        &mut self,
+        node_env: Env,
+        block_number: JsBigInt,
+    ) -> Result<JsObject> {
+        let block_number = block_number.get_u64()?.0;
+        node_env.execute_tokio_future(
+            Self::get_committed_state_root_async(self.evm.clone(), block_number),
+            |&mut node_env, result| {
+                Ok(match result {
+                    Some(result) => Some(node_env.create_string(&result)?),
+                    None => None,
+                })
+            },
+        )
+    }
+
+    #[napi(ts_return_type = "Promise<string | undefined>")]
+    pub fn get_committed_logs_bloom(
+        &mut self,
+        node_env: Env,
+        block_number: JsBigInt,
+    ) -> Result<JsObject> {
+        let block_number = block_number.get_u64()?.0;
+        node_env.execute_tokio_future(
+            Self::get_committed_logs_bloom_async(self.evm.clone(), block_number),
+            |&mut node_env, result| {
+                Ok(match result {
+                    Some(result) => Some(node_env.create_string(&result)?),
+                    None => None,
+                })
+            },
+        )
+    }
+
     #[napi(ts_return_type = "Promise<boolean>")]
     pub fn is_empty(&mut self, node_env: Env) -> Result<JsObject> {
         node_env.execute_tokio_future(
@@ -1492,6 +2033,23 @@ impl JsEvmWrapper {
         )
     }
 
+    #[napi(ts_return_type = "Promise<JsPruneState>")]
+    pub fn get_prune_state(&mut self, node_env: Env) -> Result<JsObject> {
+        node_env.execute_tokio_future(
+            Self::get_prune_state_async(self.evm.clone()),
+            |&mut node_env, result| Ok(result::JsPruneState::new(&node_env, result)?),
+        )
+    }
+
+    /// Reports the size of each table and of the database file to monitor its growth.
+    #[napi(ts_return_type = "Promise<JsStorageStats>")]
+    pub fn get_storage_stats(&mut self, node_env: Env) -> Result<JsObject> {
+        node_env.execute_tokio_future(
+            Self::get_storage_stats_async(self.evm.clone()),
+            |&mut node_env, result| Ok(result::JsStorageStats::new(&node_env, result)?),
+        )
+    }
+
     #[napi(ts_return_type = "Promise<Buffer | undefined>")]
     pub fn get_block_header_bytes(
         &mut self,
@@ -1594,6 +2152,85 @@ impl JsEvmWrapper {
         )
     }
 
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn unwind_to(&mut self, node_env: Env, block_number: JsBigInt) -> Result<JsObject> {
+        let block_number = block_number.get_u64()?.0;
+        node_env.execute_tokio_future(
+            Self::unwind_to_async(self.evm.clone(), block_number),
+            |_, _| Ok(()),
+        )
+    }
+
+    #[napi(ts_return_type = "Promise<JsSnapshotInfo>")]
+    pub fn export_snapshot(
+        &mut self,
+        node_env: Env,
+        path: JsString,
+        include_blocks: bool,
+    ) -> Result<JsObject> {
+        let path = PathBuf::from(path.into_utf8()?.into_owned()?);
+        node_env.execute_tokio_future(
+            Self::export_snapshot_async(self.evm.clone(), path, include_blocks),
+            |&mut node_env, result| Ok(result::JsSnapshotInfo::new(&node_env, result)?),
+        )
+    }
+
+    #[napi(ts_return_type = "Promise<JsSnapshotInfo>")]
+    pub fn import_snapshot(&mut self, node_env: Env, path: JsString) -> Result<JsObject> {
+        let path = PathBuf::from(path.into_utf8()?.into_owned()?);
+        node_env.execute_tokio_future(
+            Self::import_snapshot_async(self.evm.clone(), path),
+            |&mut node_env, result| Ok(result::JsSnapshotInfo::new(&node_env, result)?),
+        )
+    }
+
+    #[napi(ts_return_type = "Promise<JsBackupInfo>")]
+    pub fn backup(
+        &mut self,
+        node_env: Env,
+        path: JsString,
+        compact: bool,
+        #[napi(ts_arg_type = "(written: number, total: number) => void")] on_progress: Option<
+            JsFunction,
+        >,
+    ) -> Result<JsObject> {
+        let path = PathBuf::from(path.into_utf8()?.into_owned()?);
+
+        let on_progress: Option<ThreadsafeFunction<(u64, u64), ErrorStrategy::Fatal>> =
+            match on_progress {
+                Some(on_progress) => Some(on_progress.create_threadsafe_function(0, |ctx| {
+                    let (written, total): (u64, u64) = ctx.value;
+                    let js_written = ctx.env.create_double(written as f64)?;
+                    let js_total = ctx.env.create_double(total as f64)?;
+                    Ok(vec![js_written.into_unknown(), js_total.into_unknown()])
+                })?),
+                None => None,
+            };
+
+        node_env.execute_tokio_future(
+            Self::backup_async(self.evm.clone(), path, compact, on_progress),
+            |&mut node_env, result| Ok(result::JsBackupInfo::new(&node_env, result)?),
+        )
+    }
+
+    #[napi(ts_return_type = "Promise<JsVerifyReport>")]
+    pub fn verify(&mut self, node_env: Env) -> Result<JsObject> {
+        node_env.execute_tokio_future(
+            Self::verify_async(self.evm.clone()),
+            |&mut node_env, result| Ok(result::JsVerifyReport::new(&node_env, result)?),
+        )
+    }
+
+    /// Deletes contract bytecode which is no longer referenced by any account and can no longer
+    /// be restored by unwinding or read through the account history.
+    #[napi(ts_return_type = "Promise<JsGarbageCollection>")]
+    pub fn collect_garbage(&mut self, node_env: Env) -> Result<JsObject> {
+        node_env.execute_tokio_future(
+            Self::collect_garbage_async(self.evm.clone()),
+            |&mut node_env, result| Ok(result::JsGarbageCollection::new(&node_env, result)?),
+        )
+    }
+
     #[napi(ts_return_type = "Promise<void>")]
     pub fn dispose(&mut self, node_env: Env) -> Result<JsObject> {
         node_env.execute_tokio_future(Self::dispose_async(self.evm.clone()), |_, _| Ok(()))
@@ -1647,12 +2284,11 @@ impl JsEvmWrapper {
     }
 
     async fn get_account_info_async(
-        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        read_handles: ReadHandles,
         address: Address,
         block_number: Option<u64>,
     ) -> Result<AccountInfo> {
-        let mut lock = evm.lock().await;
-        let result = lock.get_account_info(address, block_number);
+        let result = get_account_info(&read_handles, address, block_number);
 
         match result {
             Ok(account) => Result::Ok(account),
@@ -1753,12 +2389,11 @@ impl JsEvmWrapper {
     }
 
     async fn code_at_async(
-        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        read_handles: ReadHandles,
         address: Address,
         block_number: Option<u64>,
     ) -> Result<String> {
-        let mut lock = evm.lock().await;
-        let result = lock.code_at(address, block_number);
+        let result = code_at(&read_handles, address, block_number);
 
         match result {
             Ok(code) => Result::Ok(revm::primitives::hex::encode_prefixed(code.as_ref())),
@@ -1766,13 +2401,55 @@ impl JsEvmWrapper {
         }
     }
 
-    async fn storage_at_async(
+    async fn get_storage_range_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        address: Address,
+        start: Option<U256>,
+        limit: u64,
+    ) -> Result<(Option<U256>, Vec<(U256, U256)>)> {
+        let mut lock = evm.lock().await;
+        let result = lock.get_storage_range(address, start, limit);
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
+    async fn get_contract_deployment_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        address: Address,
+    ) -> Result<Option<ContractDeployment>> {
+        let mut lock = evm.lock().await;
+        let result = lock.get_contract_deployment(address);
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
+    async fn get_contract_deployments_async(
         evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        cursor: Option<Bytes>,
+        limit: u64,
+    ) -> Result<(Option<Bytes>, Vec<(Address, ContractDeployment)>)> {
+        let mut lock = evm.lock().await;
+        let result = lock.get_contract_deployments(cursor, limit);
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
+    async fn storage_at_async(
+        read_handles: ReadHandles,
         address: Address,
         slot: U256,
+        block_number: Option<u64>,
     ) -> Result<String> {
-        let mut lock = evm.lock().await;
-        let result = lock.storage_at(address, slot);
+        let result = storage_at(&read_handles, address, slot, block_number);
 
         match result {
             Ok(slot) => Result::Ok(revm::primitives::hex::encode_prefixed(
@@ -1782,6 +2459,21 @@ impl JsEvmWrapper {
         }
     }
 
+    async fn get_proof_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        address: Address,
+        slots: Vec<U256>,
+        block_number: u64,
+    ) -> Result<AccountProof> {
+        let mut lock = evm.lock().await;
+        let result = lock.get_proof(address, slots, block_number);
+
+        match result {
+            Ok(proof) => Result::Ok(proof),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
     async fn commit_async(
         evm: Arc<tokio::sync::Mutex<EvmInner>>,
         commit_key: CommitKey,
@@ -1825,13 +2517,54 @@ impl JsEvmWrapper {
         }
     }
 
+    async fn get_committed_state_root_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        block_number: u64,
+    ) -> Result<Option<String>> {
+        let mut lock = evm.lock().await;
+        let result = lock.get_committed_state_root(block_number);
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
+    async fn get_committed_logs_bloom_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        block_number: u64,
+    ) -> Result<Option<String>> {
+        let mut lock = evm.lock().await;
+        let result = lock.get_committed_logs_bloom(block_number);
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
     async fn get_accounts_async(
         evm: Arc<tokio::sync::Mutex<EvmInner>>,
-        offset: u64,
+        cursor: Option<Bytes>,
         limit: u64,
-    ) -> Result<(Option<u64>, Vec<AccountInfoExtended>)> {
+    ) -> Result<(Option<Bytes>, Vec<AccountInfoExtended>)> {
         let mut lock = evm.lock().await;
-        let result = lock.get_accounts(offset, limit);
+        let result = lock.get_accounts(cursor, limit);
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
+    async fn get_accounts_by_balance_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        filter: BalanceFilter,
+        cursor: Option<Bytes>,
+        limit: u64,
+    ) -> Result<(Option<Bytes>, Vec<AccountInfoExtended>)> {
+        let mut lock = evm.lock().await;
+        let result = lock.get_accounts_by_balance(filter, cursor, limit);
 
         match result {
             Ok(result) => Result::Ok(result),
@@ -1855,11 +2588,11 @@ impl JsEvmWrapper {
 
     async fn get_legacy_cold_wallets_async(
         evm: Arc<tokio::sync::Mutex<EvmInner>>,
-        offset: u64,
+        cursor: Option<Bytes>,
         limit: u64,
-    ) -> Result<(Option<u64>, Vec<LegacyColdWallet>)> {
+    ) -> Result<(Option<Bytes>, Vec<LegacyColdWallet>)> {
         let mut lock = evm.lock().await;
-        let result = lock.get_legacy_cold_wallets(offset, limit);
+        let result = lock.get_legacy_cold_wallets(cursor, limit);
 
         match result {
             Ok(result) => Result::Ok(result),
@@ -1869,11 +2602,11 @@ impl JsEvmWrapper {
 
     async fn get_receipts_async(
         evm: Arc<tokio::sync::Mutex<EvmInner>>,
-        offset: u64,
+        cursor: Option<Bytes>,
         limit: u64,
-    ) -> Result<(Option<u64>, Vec<(u64, Vec<(B256, TxReceipt)>)>)> {
+    ) -> Result<(Option<Bytes>, Vec<(u64, Vec<(B256, TxReceipt)>)>)> {
         let mut lock = evm.lock().await;
-        let result = lock.get_receipts(offset, limit);
+        let result = lock.get_receipts(cursor, limit);
 
         match result {
             Ok(result) => Result::Ok(result),
@@ -1882,12 +2615,38 @@ impl JsEvmWrapper {
     }
 
     async fn get_receipt_async(
-        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        read_handles: ReadHandles,
         block_number: u64,
         tx_hash: B256,
-    ) -> Result<Option<TxReceipt>> {
+    ) -> Result<Retained<TxReceipt>> {
+        let result = get_receipt(&read_handles, block_number, tx_hash);
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
+    async fn get_receipt_by_hash_async(
+        read_handles: ReadHandles,
+        tx_hash: B256,
+    ) -> Result<Option<IndexedReceipt>> {
+        let result = get_receipt_by_hash(&read_handles, tx_hash);
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
+    async fn get_logs_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        filter: LogFilter,
+        cursor: Option<LogPosition>,
+        limit: u64,
+    ) -> Result<(Option<LogPosition>, Vec<IndexedLog>)> {
         let mut lock = evm.lock().await;
-        let result = lock.get_receipt(block_number, tx_hash);
+        let result = lock.get_logs(filter, cursor, limit);
 
         match result {
             Ok(result) => Result::Ok(result),
@@ -1915,6 +2674,28 @@ impl JsEvmWrapper {
         }
     }
 
+    async fn get_prune_state_async(evm: Arc<tokio::sync::Mutex<EvmInner>>) -> Result<PruneState> {
+        let mut lock = evm.lock().await;
+        let result = lock.get_prune_state();
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
+    async fn get_storage_stats_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+    ) -> Result<StorageStats> {
+        let mut lock = evm.lock().await;
+        let result = lock.get_storage_stats();
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
     async fn get_block_header_bytes_async(
         evm: Arc<tokio::sync::Mutex<EvmInner>>,
         block_number: u64,
@@ -2006,6 +2787,99 @@ impl JsEvmWrapper {
         }
     }
 
+    async fn unwind_to_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        block_number: u64,
+    ) -> Result<()> {
+        let mut lock = evm.lock().await;
+        let result = lock.unwind_to(block_number);
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
+    async fn export_snapshot_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        path: PathBuf,
+        include_blocks: bool,
+    ) -> Result<SnapshotInfo> {
+        let mut lock = evm.lock().await;
+        let result = lock.export_snapshot(path, include_blocks);
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
+    async fn import_snapshot_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        path: PathBuf,
+    ) -> Result<SnapshotInfo> {
+        let mut lock = evm.lock().await;
+        let result = lock.import_snapshot(path);
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
+    async fn backup_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+        path: PathBuf,
+        compact: bool,
+        on_progress: Option<ThreadsafeFunction<(u64, u64), ErrorStrategy::Fatal>>,
+    ) -> Result<BackupInfo> {
+        // The lock is only held to obtain the handle, commits continue while the backup is written.
+        let handle = {
+            let mut lock = evm.lock().await;
+            lock.backup_handle()
+        };
+
+        // The copy blocks for a long time, thus it must not occupy a runtime worker.
+        let result = tokio::task::spawn_blocking(move || {
+            handle
+                .copy_to(&path, compact, |written, total| {
+                    if let Some(on_progress) = &on_progress {
+                        on_progress.call((written, total), ThreadsafeFunctionCallMode::NonBlocking);
+                    }
+                })
+                .map_err(|err| err.to_string())
+        })
+        .await
+        .unwrap_or_else(|err| Err(err.to_string()));
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(format!("backup failed: {}", err))),
+        }
+    }
+
+    async fn verify_async(evm: Arc<tokio::sync::Mutex<EvmInner>>) -> Result<VerifyReport> {
+        let mut lock = evm.lock().await;
+        let result = lock.verify();
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
+    async fn collect_garbage_async(
+        evm: Arc<tokio::sync::Mutex<EvmInner>>,
+    ) -> Result<GarbageCollection> {
+        let mut lock = evm.lock().await;
+        let result = lock.collect_garbage();
+
+        match result {
+            Ok(result) => Result::Ok(result),
+            Err(err) => Result::Err(serde::de::Error::custom(err)),
+        }
+    }
+
     async fn dispose_async(evm: Arc<tokio::sync::Mutex<EvmInner>>) -> Result<()> {
         let mut lock = evm.lock().await;
         let result = lock.dispose();
@@ -1,17 +1,26 @@
 use mainsail_evm_core::{
     account::AccountInfoExtended,
+    backup::BackupInfo,
+    code_references::GarbageCollection,
+    deployment::ContractDeployment,
     legacy::{LegacyAccountAttributes, LegacyColdWallet, LegacyMultiSignatureAttribute},
-    receipt::TxReceipt,
+    log_index::{IndexedLog, LogPosition},
+    pruning::{PruneState, PrunedRange, Retained},
+    receipt::{IndexedReceipt, RevertReason, TxReceipt},
+    snapshot::SnapshotInfo,
     state_changes::AccountUpdate,
+    stats::{StorageStats, TableStats},
+    trie::AccountProof,
+    verify::VerifyReport,
 };
 use napi::{JsBigInt, JsBoolean, JsBuffer, JsNumber, JsString};
 use napi_derive::napi;
 use revm::{
-    primitives::{B256, Bytes},
+    primitives::{Address, B256, Bytes, U256, hex},
     state::AccountInfo,
 };
 
-use crate::utils;
+use crate::{ctx::JsLogCursor, utils};
 
 #[napi(object)]
 pub struct JsProcessResult {
@@ -107,6 +116,46 @@ pub struct JsTransactionReceipt {
 
     pub logs: serde_json::Value,
     pub output: Option<JsBuffer>,
+
+    /// Position within the block and block-wide totals, only set for committed receipts
+    pub transaction_index: JsNumber,
+    pub cumulative_gas_used: JsBigInt,
+    /// Unknown for receipts migrated from older databases
+    pub from: Option<JsString>,
+    pub to: Option<JsString>,
+    pub effective_gas_price: Option<JsBigInt>,
+
+    /// Set if the transaction reverted
+    pub revert_reason: Option<JsRevertReason>,
+    /// Set if the transaction halted, e.g. `OutOfGas(Basic)` or `OpcodeNotFound`
+    pub halt_reason: Option<JsString>,
+}
+
+#[napi(object)]
+pub struct JsRevertReason {
+    /// One of `Error`, `Panic`, `Custom` or `Unknown`
+    pub kind: JsString,
+    /// The message of `Error`, the hex encoded code of `Panic` or the signature of `Custom`
+    pub value: Option<JsString>,
+}
+
+impl JsRevertReason {
+    pub fn new(node_env: &napi::Env, reason: RevertReason) -> anyhow::Result<Self> {
+        let (kind, value) = match reason {
+            RevertReason::Error(message) => ("Error", Some(message)),
+            RevertReason::Panic(code) => ("Panic", Some(format!("{:#x}", code))),
+            RevertReason::Custom(signature) => ("Custom", Some(signature)),
+            RevertReason::Unknown => ("Unknown", None),
+        };
+
+        Ok(JsRevertReason {
+            kind: node_env.create_string(kind)?,
+            value: match value {
+                Some(value) => Some(node_env.create_string_from_std(value)?),
+                None => None,
+            },
+        })
+    }
 }
 
 #[derive(Default)]
@@ -134,14 +183,51 @@ impl JsTransactionReceipt {
             None
         };
 
+        // logs are numbered consecutively within the block
+        let logs = receipt.logs.map(|logs| {
+            logs.into_iter()
+                .zip(receipt.first_log_index..)
+                .map(|(log, log_index)| {
+                    let mut log = serde_json::to_value(log).unwrap();
+                    if let Some(log) = log.as_object_mut() {
+                        log.insert("logIndex".into(), log_index.into());
+                    }
+                    log
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let address = |address: Option<Address>| match address {
+            Some(address) => node_env
+                .create_string_from_std(address.to_checksum(None))
+                .map(Some),
+            None => Ok(None),
+        };
+
+        let effective_gas_price = match receipt.effective_gas_price {
+            Some(effective_gas_price) => {
+                Some(node_env.create_bigint_from_u128(effective_gas_price)?)
+            }
+            None => None,
+        };
+
+        let revert_reason = match receipt.revert_reason {
+            Some(revert_reason) => Some(JsRevertReason::new(node_env, revert_reason)?),
+            None => None,
+        };
+
+        let halt_reason = match receipt.halt_reason {
+            Some(halt_reason) => Some(node_env.create_string_from_std(halt_reason)?),
+            None => None,
+        };
+
         Ok(JsTransactionReceipt {
             gas_used: node_env.create_bigint_from_u64(receipt.gas_used)?,
             gas_refunded: node_env.create_bigint_from_u64(receipt.gas_refunded)?,
             status: node_env.create_uint32(receipt.success as u32)?,
             contract_address,
-            logs: receipt
-                .logs
-                .map(|l| serde_json::to_value(l).unwrap())
+            logs: logs
+                .map(serde_json::Value::Array)
                 .unwrap_or_else(|| serde_json::Value::Null), // TODO: check if null is correct
             output: receipt.output.map(|o| {
                 node_env
@@ -151,6 +237,13 @@ impl JsTransactionReceipt {
             }),
             block_number: None,
             tx_hash: None,
+            transaction_index: node_env.create_uint32(u32::try_from(receipt.transaction_index)?)?,
+            cumulative_gas_used: node_env.create_bigint_from_u64(receipt.cumulative_gas_used)?,
+            from: address(receipt.from)?,
+            to: address(receipt.to)?,
+            effective_gas_price,
+            revert_reason,
+            halt_reason,
         })
     }
 }
@@ -182,6 +275,91 @@ impl TryInto<AccountInfo> for JsAccountInfo {
     }
 }
 
+#[napi(object)]
+pub struct JsSnapshotInfo {
+    pub block_number: JsBigInt,
+    pub records: JsBigInt,
+}
+
+impl JsSnapshotInfo {
+    pub fn new(node_env: &napi::Env, info: SnapshotInfo) -> anyhow::Result<Self> {
+        Ok(JsSnapshotInfo {
+            block_number: node_env.create_bigint_from_u64(info.block_number)?,
+            records: node_env.create_bigint_from_u64(info.records)?,
+        })
+    }
+}
+
+#[napi(object)]
+pub struct JsBackupInfo {
+    pub path: JsString,
+    pub size: JsBigInt,
+    pub compacted: bool,
+}
+
+impl JsBackupInfo {
+    pub fn new(node_env: &napi::Env, info: BackupInfo) -> anyhow::Result<Self> {
+        Ok(JsBackupInfo {
+            path: node_env.create_string_from_std(info.path.display().to_string())?,
+            size: node_env.create_bigint_from_u64(info.size)?,
+            compacted: info.compacted,
+        })
+    }
+}
+
+#[napi(object)]
+pub struct JsAccountProof {
+    pub address: JsString,
+    pub balance: JsBigInt,
+    pub nonce: JsBigInt,
+    pub code_hash: JsString,
+    pub storage_hash: JsString,
+    pub account_proof: Vec<JsString>,
+    pub storage_proof: Vec<JsStorageProof>,
+}
+
+#[napi(object)]
+pub struct JsStorageProof {
+    pub key: JsString,
+    pub value: JsBigInt,
+    pub proof: Vec<JsString>,
+}
+
+impl JsAccountProof {
+    pub fn new(node_env: &napi::Env, account_proof: AccountProof) -> anyhow::Result<Self> {
+        let mut proof = Vec::with_capacity(account_proof.account_proof.len());
+        for node in account_proof.account_proof {
+            proof.push(node_env.create_string_from_std(hex::encode_prefixed(node))?);
+        }
+
+        let mut storage_proof = Vec::with_capacity(account_proof.storage_proofs.len());
+        for item in account_proof.storage_proofs {
+            let mut proof = Vec::with_capacity(item.proof.len());
+            for node in item.proof {
+                proof.push(node_env.create_string_from_std(hex::encode_prefixed(node))?);
+            }
+
+            storage_proof.push(JsStorageProof {
+                key: node_env
+                    .create_string_from_std(hex::encode_prefixed(item.key.to_be_bytes::<32>()))?,
+                value: utils::convert_u256_to_bigint(node_env, item.value)?,
+                proof,
+            });
+        }
+
+        Ok(JsAccountProof {
+            address: node_env.create_string_from_std(account_proof.address.to_checksum(None))?,
+            balance: utils::convert_u256_to_bigint(node_env, account_proof.balance)?,
+            nonce: node_env.create_bigint_from_u64(account_proof.nonce)?,
+            code_hash: node_env.create_string_from_std(account_proof.code_hash.to_string())?,
+            storage_hash: node_env
+                .create_string_from_std(account_proof.storage_root.to_string())?,
+            account_proof: proof,
+            storage_proof,
+        })
+    }
+}
+
 #[napi(object)]
 pub struct JsAccountUpdate {
     pub address: JsString,
@@ -421,28 +599,23 @@ impl TryInto<LegacyMultiSignatureAttribute> for JsLegacyMultiSignatureAttribute
 
 #[napi(object)]
 pub struct JsGetAccounts {
-    pub next_offset: Option<JsBigInt>,
+    pub next_cursor: Option<String>,
     pub accounts: Vec<JsAccountInfoExtended>,
 }
 
 impl JsGetAccounts {
     pub fn new(
         node_env: &napi::Env,
-        next_offset: Option<u64>,
+        next_cursor: Option<Bytes>,
         accounts: Vec<AccountInfoExtended>,
     ) -> anyhow::Result<Self> {
-        let next_offset = match next_offset {
-            Some(next_offset) => Some(node_env.create_bigint_from_u64(next_offset)?),
-            None => None,
-        };
-
         let mut mapped = Vec::with_capacity(accounts.len());
         for account in accounts {
             mapped.push(JsAccountInfoExtended::new(node_env, account)?);
         }
 
         Ok(JsGetAccounts {
-            next_offset,
+            next_cursor: next_cursor.map(hex::encode_prefixed),
             accounts: mapped,
         })
     }
@@ -450,28 +623,23 @@ impl JsGetAccounts {
 
 #[napi(object)]
 pub struct JsGetLegacyColdWallets {
-    pub next_offset: Option<JsBigInt>,
+    pub next_cursor: Option<String>,
     pub wallets: Vec<JsLegacyColdWallet>,
 }
 
 impl JsGetLegacyColdWallets {
     pub fn new(
         node_env: &napi::Env,
-        next_offset: Option<u64>,
+        next_cursor: Option<Bytes>,
         wallets: Vec<LegacyColdWallet>,
     ) -> anyhow::Result<Self> {
-        let next_offset = match next_offset {
-            Some(next_offset) => Some(node_env.create_bigint_from_u64(next_offset)?),
-            None => None,
-        };
-
         let mut mapped = Vec::with_capacity(wallets.len());
         for wallet in wallets {
             mapped.push(JsLegacyColdWallet::new(node_env, wallet)?);
         }
 
         Ok(JsGetLegacyColdWallets {
-            next_offset,
+            next_cursor: next_cursor.map(hex::encode_prefixed),
             wallets: mapped,
         })
     }
@@ -479,21 +647,16 @@ impl JsGetLegacyColdWallets {
 
 #[napi(object)]
 pub struct JsGetReceipts {
-    pub next_offset: Option<JsBigInt>,
+    pub next_cursor: Option<String>,
     pub receipts: Vec<JsTransactionReceipt>,
 }
 
 impl JsGetReceipts {
     pub fn new(
         node_env: &napi::Env,
-        next_offset: Option<u64>,
+        next_cursor: Option<Bytes>,
         receipts_by_block_number: Vec<(u64, Vec<(B256, TxReceipt)>)>,
     ) -> anyhow::Result<Self> {
-        let next_offset = match next_offset {
-            Some(next_offset) => Some(node_env.create_bigint_from_u64(next_offset)?),
-            None => None,
-        };
-
         let mut mapped = vec![];
         for (block_number, tx_receipts) in receipts_by_block_number {
             for (hash, tx_receipt) in tx_receipts {
@@ -507,7 +670,7 @@ impl JsGetReceipts {
         }
 
         Ok(JsGetReceipts {
-            next_offset,
+            next_cursor: next_cursor.map(hex::encode_prefixed),
             receipts: mapped,
         })
     }
@@ -516,16 +679,19 @@ impl JsGetReceipts {
 #[napi(object)]
 pub struct JsGetReceipt {
     pub receipt: Option<JsTransactionReceipt>,
+    /// Set if the receipt is missing because it has been pruned
+    pub pruned: bool,
 }
 
 impl JsGetReceipt {
     pub fn new(
         node_env: &napi::Env,
-        receipt: Option<TxReceipt>,
+        receipt: Retained<TxReceipt>,
         block_number: u64,
         tx_hash: B256,
     ) -> anyhow::Result<Self> {
-        let receipt = match receipt {
+        let pruned = receipt.is_pruned();
+        let receipt = match receipt.present() {
             Some(receipt) => {
                 let mut receipt = JsTransactionReceipt::new(node_env, receipt)?;
                 receipt.block_number = Some(node_env.create_bigint_from_u64(block_number)?);
@@ -536,7 +702,112 @@ impl JsGetReceipt {
             None => None,
         };
 
-        Ok(JsGetReceipt { receipt })
+        Ok(JsGetReceipt { receipt, pruned })
+    }
+}
+
+#[napi(object)]
+pub struct JsGetReceiptByHash {
+    pub receipt: Option<JsTransactionReceipt>,
+    pub block_hash: Option<JsString>,
+    /// Only known for blocks committed together with their transactions
+    pub transaction_index: Option<JsNumber>,
+}
+
+impl JsGetReceiptByHash {
+    pub fn new(
+        node_env: &napi::Env,
+        receipt: Option<IndexedReceipt>,
+        tx_hash: B256,
+    ) -> anyhow::Result<Self> {
+        let Some(IndexedReceipt { receipt, location }) = receipt else {
+            return Ok(JsGetReceiptByHash {
+                receipt: None,
+                block_hash: None,
+                transaction_index: None,
+            });
+        };
+
+        let mut receipt = JsTransactionReceipt::new(node_env, receipt)?;
+        receipt.block_number = Some(node_env.create_bigint_from_u64(location.block_number)?);
+        receipt.tx_hash = Some(node_env.create_string_from_std(tx_hash.to_string())?);
+
+        let transaction_index = match location.transaction_index {
+            Some(index) => Some(node_env.create_uint32(u32::try_from(index)?)?),
+            None => None,
+        };
+
+        Ok(JsGetReceiptByHash {
+            receipt: Some(receipt),
+            block_hash: Some(node_env.create_string_from_std(location.block_hash.to_string())?),
+            transaction_index,
+        })
+    }
+}
+
+#[napi(object)]
+pub struct JsLog {
+    pub address: JsString,
+    pub topics: Vec<JsString>,
+    pub data: JsString,
+    pub block_number: JsBigInt,
+    pub block_hash: JsString,
+    pub transaction_hash: JsString,
+    pub transaction_index: JsNumber,
+    /// Position of the log within the block
+    pub log_index: JsNumber,
+}
+
+impl JsLog {
+    pub fn new(node_env: &napi::Env, log: IndexedLog) -> anyhow::Result<Self> {
+        let mut topics = Vec::with_capacity(log.log.topics().len());
+        for topic in log.log.topics() {
+            topics.push(node_env.create_string_from_std(topic.to_string())?);
+        }
+
+        Ok(JsLog {
+            address: node_env.create_string_from_std(log.log.address.to_checksum(None))?,
+            topics,
+            data: node_env.create_string_from_std(log.log.data.data.to_string())?,
+            block_number: node_env.create_bigint_from_u64(log.block_number)?,
+            block_hash: node_env.create_string_from_std(log.block_hash.to_string())?,
+            transaction_hash: node_env.create_string_from_std(log.transaction_hash.to_string())?,
+            transaction_index: node_env.create_uint32(u32::try_from(log.transaction_index)?)?,
+            log_index: node_env.create_uint32(u32::try_from(log.log_index)?)?,
+        })
+    }
+}
+
+#[napi(object)]
+pub struct JsGetLogs {
+    pub logs: Vec<JsLog>,
+    /// Where to continue reading, only set if there are more matching logs
+    pub next_cursor: Option<JsLogCursor>,
+}
+
+impl JsGetLogs {
+    pub fn new(
+        node_env: &napi::Env,
+        next_cursor: Option<LogPosition>,
+        logs: Vec<IndexedLog>,
+    ) -> anyhow::Result<Self> {
+        let next_cursor = match next_cursor {
+            Some(next_cursor) => Some(JsLogCursor {
+                block_number: node_env.create_bigint_from_u64(next_cursor.block_number)?,
+                log_index: node_env.create_bigint_from_u64(next_cursor.log_index)?,
+            }),
+            None => None,
+        };
+
+        let mut mapped = Vec::with_capacity(logs.len());
+        for log in logs {
+            mapped.push(JsLog::new(node_env, log)?);
+        }
+
+        Ok(JsGetLogs {
+            logs: mapped,
+            next_cursor,
+        })
     }
 }
 
@@ -554,3 +825,229 @@ impl JsGetState {
         })
     }
 }
+
+#[napi(object)]
+pub struct JsPruneState {
+    pub blocks: Option<JsPrunedRange>,
+    pub proofs: Option<JsPrunedRange>,
+    pub transactions: Option<JsPrunedRange>,
+    pub receipts: Option<JsPrunedRange>,
+}
+
+#[napi(object)]
+pub struct JsPrunedRange {
+    pub first: JsBigInt,
+    pub last: JsBigInt,
+}
+
+impl JsPruneState {
+    pub fn new(node_env: &napi::Env, state: PruneState) -> anyhow::Result<Self> {
+        let range = |range: Option<PrunedRange>| -> anyhow::Result<Option<JsPrunedRange>> {
+            Ok(match range {
+                Some(range) => Some(JsPrunedRange {
+                    first: node_env.create_bigint_from_u64(range.first)?,
+                    last: node_env.create_bigint_from_u64(range.last)?,
+                }),
+                None => None,
+            })
+        };
+
+        Ok(JsPruneState {
+            blocks: range(state.blocks)?,
+            proofs: range(state.proofs)?,
+            transactions: range(state.transactions)?,
+            receipts: range(state.receipts)?,
+        })
+    }
+}
+
+#[napi(object)]
+pub struct JsVerifyReport {
+    pub blocks: JsBigInt,
+    pub transactions: JsBigInt,
+    pub storage_entries: JsBigInt,
+    pub inconsistencies: Vec<JsInconsistency>,
+}
+
+#[napi(object)]
+pub struct JsInconsistency {
+    pub kind: String,
+    pub message: String,
+}
+
+impl JsVerifyReport {
+    pub fn new(node_env: &napi::Env, report: VerifyReport) -> anyhow::Result<Self> {
+        Ok(JsVerifyReport {
+            blocks: node_env.create_bigint_from_u64(report.blocks)?,
+            transactions: node_env.create_bigint_from_u64(report.transactions)?,
+            storage_entries: node_env.create_bigint_from_u64(report.storage_entries)?,
+            inconsistencies: report
+                .inconsistencies
+                .iter()
+                .map(|inconsistency| JsInconsistency {
+                    kind: inconsistency.kind().to_owned(),
+                    message: inconsistency.to_string(),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[napi(object)]
+pub struct JsGarbageCollection {
+    pub deleted: JsBigInt,
+    pub reclaimed_bytes: JsBigInt,
+    pub retained: JsBigInt,
+}
+
+impl JsGarbageCollection {
+    pub fn new(node_env: &napi::Env, collection: GarbageCollection) -> anyhow::Result<Self> {
+        Ok(JsGarbageCollection {
+            deleted: node_env.create_bigint_from_u64(collection.deleted)?,
+            reclaimed_bytes: node_env.create_bigint_from_u64(collection.reclaimed_bytes)?,
+            retained: node_env.create_bigint_from_u64(collection.retained)?,
+        })
+    }
+}
+
+#[napi(object)]
+pub struct JsStorageStats {
+    pub map_size: JsBigInt,
+    pub used_size: JsBigInt,
+    pub real_disk_size: JsBigInt,
+    /// Number of map resizes since the database got opened
+    pub resize_count: JsBigInt,
+    pub tables: Vec<JsTableStats>,
+}
+
+#[napi(object)]
+pub struct JsTableStats {
+    pub name: String,
+    pub entries: JsBigInt,
+    pub depth: u32,
+    pub branch_pages: JsBigInt,
+    pub leaf_pages: JsBigInt,
+    pub overflow_pages: JsBigInt,
+}
+
+impl JsStorageStats {
+    pub fn new(node_env: &napi::Env, stats: StorageStats) -> anyhow::Result<Self> {
+        let table = |table: TableStats| -> anyhow::Result<JsTableStats> {
+            Ok(JsTableStats {
+                name: table.name.to_owned(),
+                entries: node_env.create_bigint_from_u64(table.entries)?,
+                depth: table.depth,
+                branch_pages: node_env.create_bigint_from_u64(table.branch_pages)?,
+                leaf_pages: node_env.create_bigint_from_u64(table.leaf_pages)?,
+                overflow_pages: node_env.create_bigint_from_u64(table.overflow_pages)?,
+            })
+        };
+
+        Ok(JsStorageStats {
+            map_size: node_env.create_bigint_from_u64(stats.map_size)?,
+            used_size: node_env.create_bigint_from_u64(stats.used_size)?,
+            real_disk_size: node_env.create_bigint_from_u64(stats.real_disk_size)?,
+            resize_count: node_env.create_bigint_from_u64(stats.resize_count)?,
+            tables: stats
+                .tables
+                .into_iter()
+                .map(table)
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+}
+
+#[napi(object)]
+pub struct JsStorageSlot {
+    pub slot: JsBigInt,
+    pub value: String,
+}
+
+#[napi(object)]
+pub struct JsGetStorageRange {
+    pub slots: Vec<JsStorageSlot>,
+    /// Slot to continue reading from, only set if there are more slots
+    pub next_slot: Option<JsBigInt>,
+}
+
+impl JsGetStorageRange {
+    pub fn new(
+        node_env: &napi::Env,
+        next_slot: Option<U256>,
+        slots: Vec<(U256, U256)>,
+    ) -> anyhow::Result<Self> {
+        let next_slot = match next_slot {
+            Some(next_slot) => Some(utils::convert_u256_to_bigint(node_env, next_slot)?),
+            None => None,
+        };
+
+        let mut mapped = Vec::with_capacity(slots.len());
+        for (slot, value) in slots {
+            mapped.push(JsStorageSlot {
+                slot: utils::convert_u256_to_bigint(node_env, slot)?,
+                value: hex::encode_prefixed(value.to_be_bytes::<32>()),
+            });
+        }
+
+        Ok(JsGetStorageRange {
+            slots: mapped,
+            next_slot,
+        })
+    }
+}
+
+#[napi(object)]
+pub struct JsContractDeployment {
+    pub address: JsString,
+    /// Unknown for deployments committed before the sender got stored
+    pub deployer: Option<JsString>,
+    pub transaction_hash: JsString,
+    pub block_number: JsBigInt,
+    pub code_hash: JsString,
+}
+
+impl JsContractDeployment {
+    pub fn new(
+        node_env: &napi::Env,
+        address: Address,
+        deployment: ContractDeployment,
+    ) -> anyhow::Result<Self> {
+        let deployer = match deployment.deployer {
+            Some(deployer) => Some(node_env.create_string_from_std(deployer.to_checksum(None))?),
+            None => None,
+        };
+
+        Ok(JsContractDeployment {
+            address: node_env.create_string_from_std(address.to_checksum(None))?,
+            deployer,
+            transaction_hash: node_env
+                .create_string_from_std(deployment.transaction_hash.to_string())?,
+            block_number: node_env.create_bigint_from_u64(deployment.block_number)?,
+            code_hash: node_env.create_string_from_std(deployment.code_hash.to_string())?,
+        })
+    }
+}
+
+#[napi(object)]
+pub struct JsGetContractDeployments {
+    pub next_cursor: Option<String>,
+    pub deployments: Vec<JsContractDeployment>,
+}
+
+impl JsGetContractDeployments {
+    pub fn new(
+        node_env: &napi::Env,
+        next_cursor: Option<Bytes>,
+        deployments: Vec<(Address, ContractDeployment)>,
+    ) -> anyhow::Result<Self> {
+        let mut mapped = Vec::with_capacity(deployments.len());
+        for (address, deployment) in deployments {
+            mapped.push(JsContractDeployment::new(node_env, address, deployment)?);
+        }
+
+        Ok(JsGetContractDeployments {
+            next_cursor: next_cursor.map(hex::encode_prefixed),
+            deployments: mapped,
+        })
+    }
+}
@@ -27,6 +27,10 @@ pub(crate) fn convert_js_buffer_to_bytes(js_buffer: JsBuffer) -> anyhow::Result<
     Ok(bytes)
 }
 
+pub(crate) fn convert_string_to_bytes(js_str: JsString) -> anyhow::Result<Bytes> {
+    Ok(Bytes::from_str(js_str.into_utf8()?.as_str()?)?)
+}
+
 pub(crate) fn convert_string_to_b256(js_str: JsString) -> anyhow::Result<B256> {
     Ok(B256::try_from(
         &Bytes::from_str(js_str.into_utf8()?.as_str()?)?.as_ref()[..],
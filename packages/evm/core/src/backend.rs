@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+
+use revm::{
+    DatabaseRef,
+    primitives::{Address, B256, Bytes, U256},
+    state::{AccountInfo, Bytecode},
+};
+
+use crate::{
+    db::{CommitData, CommitKey, CommitReceipts, Error, GenesisInfo, PersistentDB, PersistentTxn},
+    legacy::{LegacyAccountAttributes, LegacyAddress, LegacyColdWallet},
+    logger::Logger,
+    pruning::Retained,
+    receipt::TxReceipt,
+    state_commit::StateCommit,
+    state_root::StateRootMode,
+};
+
+// Storage the state is committed to and read from. `PersistentDB` stores it in LMDB, `MemoryDB`
+// keeps it in maps for simulations and tests. Commits are applied by `state_commit::commit_to_db`
+// through the table operations of `StorageTxn`.
+pub trait StorageBackend: DatabaseRef<Error = Error> {
+    type Txn<'a>: StorageTxn
+    where
+        Self: 'a;
+
+    fn state_root_mode(&self) -> StateRootMode;
+
+    fn genesis_info(&self) -> Option<GenesisInfo>;
+
+    fn set_genesis_info(&mut self, genesis_info: GenesisInfo);
+
+    fn logger(&self) -> &Logger;
+
+    // Runs `apply` in a write transaction. Its writes become visible at once if it succeeds and
+    // are discarded otherwise.
+    fn write<T>(
+        &self,
+        apply: impl FnOnce(&mut Self::Txn<'_>) -> Result<T, Error>,
+    ) -> Result<T, Error>;
+
+    // Calculates the Merkle Patricia Trie state root the commit results in without applying it.
+    fn calculate_trie_root(&self, state_commit: &mut StateCommit) -> Result<B256, Error>;
+
+    // Grows the storage after a commit failed with `Error::DbFull`.
+    fn resize(&self) -> Result<(), Error>;
+
+    fn is_block_committed(&self, block_number: u64) -> bool;
+
+    fn get_committed_receipt(
+        &self,
+        block_number: u64,
+        tx_hash: B256,
+    ) -> Result<(bool, Option<TxReceipt>), Error>;
+
+    fn get_committed_hashes(&self, block_number: u64) -> Result<Option<(B256, B256, B256)>, Error>;
+
+    fn get_state_root(&self, block_number: u64) -> Result<Option<B256>, Error>;
+
+    // Returns the account as of `block_number` and whether the current state has to be read
+    // instead, see `AccountHistory`.
+    fn get_historical_account_info(
+        &self,
+        block_number: u64,
+        address: Address,
+    ) -> Result<(Option<AccountInfo>, bool), Error>;
+
+    fn get_historical_storage(
+        &self,
+        block_number: u64,
+        address: Address,
+        slot: U256,
+    ) -> Result<(Option<U256>, bool), Error>;
+
+    fn get_legacy_attributes(
+        &self,
+        address: Address,
+    ) -> Result<Option<LegacyAccountAttributes>, Error>;
+
+    fn get_legacy_cold_wallet(
+        &self,
+        address: LegacyAddress,
+    ) -> Result<Option<LegacyColdWallet>, Error>;
+
+    // Returns the last committed block number and the total round.
+    fn get_state(&self) -> Result<(u64, u64), Error>;
+
+    fn get_block_header_bytes(&self, block_number: u64) -> Result<Retained<Bytes>, Error>;
+
+    fn get_block_hash(&self, block_number: u64) -> Result<Option<B256>, Error>;
+
+    fn get_block_number_by_hash(&self, block_hash: B256) -> Result<Option<u64>, Error>;
+
+    fn get_proof_bytes(&self, block_number: u64) -> Result<Retained<Bytes>, Error>;
+
+    fn get_transaction_bytes(&self, key: String) -> Result<Retained<Bytes>, Error>;
+
+    fn get_transaction_key_by_hash(&self, tx_hash: B256) -> Result<Option<String>, Error>;
+}
+
+// Table operations of a write transaction, see `state_commit::apply_commit` for the order in which
+// a commit applies them.
+pub trait StorageTxn {
+    // Writes the account, `None` removes it.
+    fn put_account(
+        &mut self,
+        block_number: u64,
+        address: Address,
+        account: Option<&AccountInfo>,
+    ) -> Result<(), Error>;
+
+    fn put_contract(
+        &mut self,
+        block_number: u64,
+        code_hash: B256,
+        bytecode: &Bytecode,
+    ) -> Result<(), Error>;
+
+    // Writes the slots of the address, a zero value removes the slot. Wiping removes all existing
+    // slots beforehand, which are returned.
+    fn write_storage(
+        &mut self,
+        address: Address,
+        wipe: bool,
+        slots: &[(U256, U256)],
+    ) -> Result<Vec<U256>, Error>;
+
+    fn get_legacy_attributes(
+        &self,
+        address: Address,
+    ) -> Result<Option<LegacyAccountAttributes>, Error>;
+
+    fn put_legacy_attributes(
+        &mut self,
+        address: Address,
+        legacy_attributes: &LegacyAccountAttributes,
+    ) -> Result<(), Error>;
+
+    fn get_legacy_cold_wallet(
+        &self,
+        address: LegacyAddress,
+    ) -> Result<Option<LegacyColdWallet>, Error>;
+
+    fn put_legacy_cold_wallet(
+        &mut self,
+        address: LegacyAddress,
+        legacy_cold_wallet: &LegacyColdWallet,
+    ) -> Result<(), Error>;
+
+    // Whether the account and storage history is kept, see `AccountHistory`.
+    fn keeps_history(&self) -> bool;
+
+    fn insert_account_history(
+        &mut self,
+        block_number: u64,
+        accounts: Vec<(Address, AccountInfo)>,
+    ) -> Result<(), Error>;
+
+    fn insert_storage_history(
+        &mut self,
+        block_number: u64,
+        storage: BTreeMap<Address, BTreeMap<U256, U256>>,
+    ) -> Result<(), Error>;
+
+    // Writes the block, its proof and transactions and adds its rounds to the total round.
+    fn put_block(&mut self, block_number: u64, commit_data: &CommitData) -> Result<(), Error>;
+
+    // Applies the change set to the state trie once the state is written. Returns the new root,
+    // `None` for `StateRootMode::Legacy`.
+    fn update_trie(&mut self, state_commit: &StateCommit) -> Result<Option<B256>, Error>;
+
+    // Stores the receipts and hashes of the commit, which completes it.
+    fn put_commit(
+        &mut self,
+        key: &CommitKey,
+        commit_data: &Option<CommitData>,
+        receipts: CommitReceipts,
+    ) -> Result<(), Error>;
+}
+
+// Inherent methods take precedence, thus all calls below forward to `PersistentDB`.
+impl StorageBackend for PersistentDB {
+    type Txn<'a> = PersistentTxn<'a>;
+
+    fn state_root_mode(&self) -> StateRootMode {
+        self.state_root_mode()
+    }
+
+    fn genesis_info(&self) -> Option<GenesisInfo> {
+        self.genesis_info()
+    }
+
+    fn set_genesis_info(&mut self, genesis_info: GenesisInfo) {
+        self.set_genesis_info(genesis_info)
+    }
+
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+
+    fn write<T>(
+        &self,
+        apply: impl FnOnce(&mut PersistentTxn<'_>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        self.write(apply)
+    }
+
+    fn calculate_trie_root(&self, state_commit: &mut StateCommit) -> Result<B256, Error> {
+        self.calculate_trie_root(state_commit)
+    }
+
+    fn resize(&self) -> Result<(), Error> {
+        self.resize()
+    }
+
+    fn is_block_committed(&self, block_number: u64) -> bool {
+        self.is_block_committed(block_number)
+    }
+
+    fn get_committed_receipt(
+        &self,
+        block_number: u64,
+        tx_hash: B256,
+    ) -> Result<(bool, Option<TxReceipt>), Error> {
+        self.get_committed_receipt(block_number, tx_hash)
+    }
+
+    fn get_committed_hashes(&self, block_number: u64) -> Result<Option<(B256, B256, B256)>, Error> {
+        self.get_committed_hashes(block_number)
+    }
+
+    fn get_state_root(&self, block_number: u64) -> Result<Option<B256>, Error> {
+        self.get_state_root(block_number)
+    }
+
+    fn get_historical_account_info(
+        &self,
+        block_number: u64,
+        address: Address,
+    ) -> Result<(Option<AccountInfo>, bool), Error> {
+        self.get_historical_account_info(block_number, address)
+    }
+
+    fn get_historical_storage(
+        &self,
+        block_number: u64,
+        address: Address,
+        slot: U256,
+    ) -> Result<(Option<U256>, bool), Error> {
+        self.get_historical_storage(block_number, address, slot)
+    }
+
+    fn get_legacy_attributes(
+        &self,
+        address: Address,
+    ) -> Result<Option<LegacyAccountAttributes>, Error> {
+        self.get_legacy_attributes(address)
+    }
+
+    fn get_legacy_cold_wallet(
+        &self,
+        address: LegacyAddress,
+    ) -> Result<Option<LegacyColdWallet>, Error> {
+        self.get_legacy_cold_wallet(address)
+    }
+
+    fn get_state(&self) -> Result<(u64, u64), Error> {
+        self.get_state()
+    }
+
+    fn get_block_header_bytes(&self, block_number: u64) -> Result<Retained<Bytes>, Error> {
+        self.get_block_header_bytes(block_number)
+    }
+
+    fn get_block_hash(&self, block_number: u64) -> Result<Option<B256>, Error> {
+        self.get_block_hash(block_number)
+    }
+
+    fn get_block_number_by_hash(&self, block_hash: B256) -> Result<Option<u64>, Error> {
+        self.get_block_number_by_hash(block_hash)
+    }
+
+    fn get_proof_bytes(&self, block_number: u64) -> Result<Retained<Bytes>, Error> {
+        self.get_proof_bytes(block_number)
+    }
+
+    fn get_transaction_bytes(&self, key: String) -> Result<Retained<Bytes>, Error> {
+        self.get_transaction_bytes(key)
+    }
+
+    fn get_transaction_key_by_hash(&self, tx_hash: B256) -> Result<Option<String>, Error> {
+        self.get_transaction_key_by_hash(tx_hash)
+    }
+}
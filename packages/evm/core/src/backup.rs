@@ -0,0 +1,288 @@
+use std::{
+    fs::File,
+    io::{PipeReader, Read, Write},
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use heed::CompactionOption;
+
+use crate::db::{Error, MAP_SIZE_UNIT, PersistentDB};
+
+// Minimum number of bytes between two progress reports.
+const PROGRESS_INTERVAL: u64 = 16 * 1024 * 1024;
+
+// Free map space reserved before copying, commits made during the backup which need more than
+// that wait for the backup to finish.
+const RESERVED_MAP_SIZE: usize = MAP_SIZE_UNIT;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub size: u64,
+    pub compacted: bool,
+}
+
+// Creates copies of the database without borrowing the `PersistentDB`, which allows commits to
+// continue while a backup is running. LMDB copies from a read transaction, thus the copy
+// reflects the state at the time the backup started.
+#[derive(Clone)]
+pub struct BackupHandle {
+    db: Arc<PersistentDB>,
+}
+
+impl PersistentDB {
+    pub fn backup_handle(&self) -> BackupHandle {
+        // Writable, the map is grown before copying
+        let mut db = self.read_view(None);
+        db.read_only = self.read_only;
+
+        BackupHandle { db: Arc::new(db) }
+    }
+}
+
+impl BackupHandle {
+    // Writes a copy of `evm.mdb` to `path`, compaction omits free pages and renumbers the
+    // remaining ones. `progress` receives the bytes written so far and the estimated total,
+    // which is the size of all pages in use.
+    pub fn copy_to(
+        &self,
+        path: &Path,
+        compact: bool,
+        mut progress: impl FnMut(u64, u64),
+    ) -> Result<BackupInfo, Error> {
+        // The map must not be resized while LMDB copies it, the backup holds off resizes until the
+        // copy is done. Reads continue meanwhile, commits only have to wait once the reserved space
+        // is used up.
+        self.db.reserve_map(RESERVED_MAP_SIZE)?;
+        let _backup = self.db.resize_gate.backup();
+
+        let env = &self.db.env;
+        let estimated_size = env.non_free_pages_size()?;
+        let option = match compact {
+            true => CompactionOption::Enabled,
+            false => CompactionOption::Disabled,
+        };
+
+        let mut file = File::create_new(path)?;
+
+        // LMDB writes the copy into the pipe from a separate thread, which allows tracking the
+        // progress while the data is written to the target file.
+        let result = std::thread::scope(|scope| -> Result<u64, Error> {
+            let (reader, writer) = std::io::pipe()?;
+
+            let copy = scope.spawn(move || -> Result<(), Error> {
+                let result = unsafe { env.copy_to_fd(writer.as_raw_fd(), option) };
+                // closing the pipe signals the end of the copy to the reader
+                drop(writer);
+                Ok(result?)
+            });
+
+            // the reader is dropped on error, which makes a still running copy fail instead of
+            // blocking on a full pipe
+            let written = write_pipe(reader, &mut file, estimated_size, &mut progress);
+            let copied = copy.join().expect("backup thread");
+
+            let written = written?;
+            copied?;
+
+            file.sync_all()?;
+            progress(written, estimated_size);
+
+            Ok(written)
+        });
+
+        match result {
+            Ok(size) => Ok(BackupInfo {
+                path: path.to_path_buf(),
+                size,
+                compacted: compact,
+            }),
+            Err(err) => {
+                // never leave a partial copy behind
+                drop(file);
+                let _ = std::fs::remove_file(path);
+                Err(err)
+            }
+        }
+    }
+}
+
+fn write_pipe(
+    mut reader: PipeReader,
+    file: &mut File,
+    estimated_size: u64,
+    progress: &mut impl FnMut(u64, u64),
+) -> Result<u64, Error> {
+    let mut buffer = vec![0u8; 1024 * 1024];
+    let mut written = 0u64;
+    let mut reported = 0u64;
+
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+
+        file.write_all(&buffer[..read])?;
+        written += read as u64;
+
+        if written - reported >= PROGRESS_INTERVAL {
+            progress(written, estimated_size);
+            reported = written;
+        }
+    }
+
+    Ok(written)
+}
+
+#[test]
+fn test_backup() {
+    use revm::{DatabaseRef, primitives::address};
+
+    use crate::{db::PersistentDBOptions, trie::pending_commit};
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().join("source"))).expect("database");
+
+    let address = address!("bd6f65c58a46427af4b257cbe231d0ed69ed5508");
+
+    for block_number in 1..=10 {
+        crate::state_commit::commit_to_db(
+            &mut db,
+            pending_commit(
+                block_number,
+                vec![(address, block_number, vec![(block_number, 0, 1)])],
+            ),
+            None,
+        )
+        .expect("ok");
+    }
+
+    let handle = db.backup_handle();
+
+    for compact in [false, true] {
+        let target = path.path().join(format!("backup-{}", compact));
+        std::fs::create_dir_all(&target).unwrap();
+
+        let mut reports = Vec::new();
+        let info = handle
+            .copy_to(&target.join("evm.mdb"), compact, |written, total| {
+                reports.push((written, total))
+            })
+            .expect("backup");
+
+        assert_eq!(info.compacted, compact);
+        assert_eq!(
+            info.size,
+            std::fs::metadata(target.join("evm.mdb")).unwrap().len()
+        );
+        assert_eq!(reports.last().map(|report| report.0), Some(info.size));
+
+        // The copy can be opened as a regular database
+        let copy = PersistentDB::new(PersistentDBOptions::new(target)).expect("database");
+        assert_eq!(
+            copy.basic_ref(address).unwrap().unwrap().balance,
+            revm::primitives::U256::from(10)
+        );
+        assert!(copy.is_block_committed(10));
+    }
+
+    // Existing files are never overwritten
+    assert!(
+        handle
+            .copy_to(&path.path().join("backup-true/evm.mdb"), true, |_, _| {})
+            .is_err()
+    );
+}
+
+#[test]
+fn test_backup_during_commits() {
+    use std::sync::mpsc;
+
+    use revm::{
+        DatabaseRef,
+        primitives::{U256, address},
+    };
+
+    use crate::{db::PersistentDBOptions, trie::pending_commit};
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let target = path.path().join("backup");
+    std::fs::create_dir_all(&target).unwrap();
+
+    // start with a map of a few kB
+    let opts = PersistentDBOptions::new(path.path().join("source"));
+    std::fs::create_dir_all(&opts.path).unwrap();
+    let env = PersistentDB::create_env(&opts).expect("env");
+    unsafe { env.resize(4096 * 10) }.expect("resize");
+
+    let mut db = PersistentDB::new_with_env(env, opts).expect("database");
+    assert_eq!(db.env.info().map_size, 4096 * 10);
+
+    let address = address!("bd6f65c58a46427af4b257cbe231d0ed69ed5508");
+    crate::state_commit::commit_to_db(&mut db, pending_commit(1, vec![(address, 1, vec![])]), None)
+        .expect("ok");
+
+    let handle = db.backup_handle();
+    let target = target.join("evm.mdb");
+    let (started_tx, started_rx) = mpsc::channel();
+    let (resume_tx, resume_rx) = mpsc::channel::<()>();
+
+    std::thread::scope(|scope| {
+        // the backup is held open in its final progress report
+        let backup = scope.spawn(|| {
+            handle.copy_to(&target, false, move |_, _| {
+                let _ = started_tx.send(());
+                let _ = resume_rx.recv();
+            })
+        });
+
+        started_rx.recv().unwrap();
+
+        // commits past the initial map size continue in the reserved space
+        crate::state_commit::commit_to_db(
+            &mut db,
+            pending_commit(
+                2,
+                vec![(address, 2, (1..=2000).map(|slot| (slot, 0, 1)).collect())],
+            ),
+            None,
+        )
+        .expect("ok");
+        assert!(db.is_block_committed(2));
+
+        // resizes wait for the backup, reads continue meanwhile
+        let resize = scope.spawn(|| db.resize());
+        {
+            let read_handle = db.read_handle().expect("read handle");
+            assert_eq!(
+                read_handle.storage_ref(address, U256::from(2000)).unwrap(),
+                U256::from(1)
+            );
+        }
+        assert!(!resize.is_finished());
+
+        resume_tx.send(()).unwrap();
+        backup.join().unwrap().expect("backup");
+        resize.join().unwrap().expect("resize");
+    });
+
+    assert!(db.env.info().map_size > RESERVED_MAP_SIZE);
+
+    // The copy reflects the state at the time the backup started
+    let copy =
+        PersistentDB::new(PersistentDBOptions::new(path.path().join("backup"))).expect("database");
+    assert!(copy.is_block_committed(1));
+    assert!(!copy.is_block_committed(2));
+}
@@ -0,0 +1,360 @@
+use std::{borrow::Cow, ops::Bound};
+
+use heed::{RwTxn, WithoutTls};
+use revm::{
+    primitives::{Address, Bytes, U256},
+    state::AccountInfo,
+};
+
+use crate::{
+    account::AccountInfoExtended,
+    db::{AddressWrapper, Error, PersistentDB, TablesTxn},
+    legacy::LegacyAccountAttributes,
+};
+
+// Upper bound of accounts returned by a single `get_accounts_by_balance` call.
+pub const MAX_ACCOUNTS_LIMIT: u64 = 10_000;
+
+const BALANCE_KEY_LEN: usize = 52;
+
+// Key of the balance index, the balance is encoded big endian such that accounts are sorted by
+// balance and then by address.
+#[derive(Debug)]
+pub(crate) struct BalanceKey(pub(crate) U256, pub(crate) Address);
+
+impl BalanceKey {
+    fn to_be_bytes(&self) -> [u8; BALANCE_KEY_LEN] {
+        let mut bytes = [0; BALANCE_KEY_LEN];
+        bytes[..32].copy_from_slice(&self.0.to_be_bytes::<32>());
+        bytes[32..].copy_from_slice(self.1.as_slice());
+        bytes
+    }
+
+    fn from_be_slice(bytes: &[u8]) -> Self {
+        BalanceKey(
+            U256::from_be_slice(&bytes[..32]),
+            Address::from_slice(&bytes[32..BALANCE_KEY_LEN]),
+        )
+    }
+}
+
+impl heed::BytesEncode<'_> for BalanceKey {
+    type EItem = BalanceKey;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<[u8]>, heed::BoxedError> {
+        Ok(Cow::Owned(item.to_be_bytes().to_vec()))
+    }
+}
+
+impl heed::BytesDecode<'_> for BalanceKey {
+    type DItem = BalanceKey;
+
+    fn bytes_decode(bytes: &'_ [u8]) -> Result<Self::DItem, heed::BoxedError> {
+        Ok(BalanceKey::from_be_slice(bytes))
+    }
+}
+
+// Table indexing the accounts by balance. The account info itself remains in the accounts table.
+#[derive(Clone)]
+pub(crate) struct BalanceIndexTables {
+    pub balances: heed::Database<BalanceKey, heed::types::Unit>,
+}
+
+impl BalanceIndexTables {
+    pub const MAX_DBS: u32 = 1;
+
+    pub fn create(env: &heed::Env<WithoutTls>, txn: &mut TablesTxn) -> Result<Self, Error> {
+        let balances = txn.database::<BalanceKey, heed::types::Unit>(env, "account_balances")?;
+
+        Ok(Self { balances })
+    }
+
+    // Moves `address` from its previous to its current balance, `None` if the account does not
+    // exist (anymore).
+    pub fn update(
+        &self,
+        txn: &mut RwTxn,
+        address: Address,
+        previous: Option<&AccountInfo>,
+        current: Option<&AccountInfo>,
+    ) -> Result<(), Error> {
+        let previous = previous.map(|info| info.balance);
+        let current = current.map(|info| info.balance);
+
+        if previous == current {
+            return Ok(());
+        }
+
+        if let Some(balance) = previous {
+            self.balances.delete(txn, &BalanceKey(balance, address))?;
+        }
+
+        if let Some(balance) = current {
+            self.balances.put(txn, &BalanceKey(balance, address), &())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountKind {
+    // Accounts without code
+    Eoa,
+    Contract,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BalanceFilter {
+    pub min_balance: U256,
+    // Matches both kinds if unset
+    pub kind: Option<AccountKind>,
+    pub with_legacy_attributes: bool,
+}
+
+impl BalanceFilter {
+    pub fn matches(
+        &self,
+        info: &AccountInfo,
+        legacy_attributes: &Option<LegacyAccountAttributes>,
+    ) -> bool {
+        if info.balance < self.min_balance {
+            return false;
+        }
+
+        let kind_matches = match self.kind {
+            Some(AccountKind::Eoa) => info.is_empty_code_hash(),
+            Some(AccountKind::Contract) => !info.is_empty_code_hash(),
+            None => true,
+        };
+
+        kind_matches && (!self.with_legacy_attributes || legacy_attributes.is_some())
+    }
+}
+
+impl PersistentDB {
+    // Lists the accounts matching `filter` from the highest to the lowest balance, accounts with
+    // equal balances are ordered by descending address. The cursor is opaque to callers.
+    pub fn get_accounts_by_balance(
+        &self,
+        filter: &BalanceFilter,
+        cursor: Option<Bytes>,
+        limit: u64,
+    ) -> Result<(Option<Bytes>, Vec<AccountInfoExtended>), Error> {
+        let end = match &cursor {
+            None => Bound::Unbounded,
+            Some(cursor) if cursor.len() == BALANCE_KEY_LEN => {
+                Bound::Excluded(BalanceKey::from_be_slice(cursor))
+            }
+            Some(cursor) => return Err(Error::InvalidCursor(cursor.clone())),
+        };
+        let start = Bound::Included(BalanceKey(filter.min_balance, Address::ZERO));
+
+        let limit = limit.min(MAX_ACCOUNTS_LIMIT) as usize;
+        let mut accounts = Vec::with_capacity(limit.min(1000));
+        let mut last_key = None;
+
+        if limit == 0 {
+            return Ok((None, accounts));
+        }
+
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        for item in inner
+            .balance_index
+            .balances
+            .rev_range(&rtxn, &(start, end))?
+        {
+            let (key, _) = item?;
+            let address = AddressWrapper(key.1);
+
+            // The index is updated together with the accounts
+            let Some(info) = inner.accounts.get(&rtxn, &address)? else {
+                continue;
+            };
+
+            let legacy_attributes = inner
+                .legacy_attributes
+                .get(&rtxn, &address)?
+                .filter(|legacy_attributes| !legacy_attributes.is_empty());
+
+            if !filter.matches(&info, &legacy_attributes) {
+                continue;
+            }
+
+            accounts.push(AccountInfoExtended {
+                address: key.1,
+                info: AccountInfo {
+                    balance: info.balance,
+                    nonce: info.nonce,
+                    code_hash: info.code_hash,
+                    ..Default::default()
+                },
+                legacy_attributes: legacy_attributes.unwrap_or_default(),
+            });
+            last_key = Some(key);
+
+            if accounts.len() == limit {
+                break;
+            }
+        }
+
+        let next = match last_key {
+            Some(key) if accounts.len() == limit => {
+                Some(Bytes::copy_from_slice(&key.to_be_bytes()))
+            }
+            _ => None,
+        };
+
+        Ok((next, accounts))
+    }
+}
+
+#[test]
+fn test_get_accounts_by_balance() {
+    use revm::{
+        primitives::{address, keccak256},
+        state::Bytecode,
+    };
+
+    use crate::{db::PersistentDBOptions, state_commit::commit_to_db, trie::pending_commit};
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+
+    let alice = address!("1000000000000000000000000000000000000001");
+    let bob = address!("2000000000000000000000000000000000000002");
+    let carol = address!("3000000000000000000000000000000000000003");
+    let contract = address!("4000000000000000000000000000000000000004");
+
+    let mut pending = pending_commit(
+        1,
+        vec![
+            (alice, 100, vec![]),
+            (bob, 300, vec![]),
+            (carol, 100, vec![]),
+            (contract, 200, vec![]),
+        ],
+    );
+
+    let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x00]));
+    let info = pending
+        .transitions
+        .transitions
+        .get_mut(&contract)
+        .and_then(|transition| transition.info.as_mut())
+        .expect("contract");
+    info.code_hash = keccak256(code.original_byte_slice());
+    info.code = Some(code);
+
+    pending.legacy_attributes.insert(
+        carol,
+        LegacyAccountAttributes {
+            second_public_key: Some("public key".to_owned()),
+            ..Default::default()
+        },
+    );
+
+    commit_to_db(&mut db, pending, None).expect("commit");
+
+    let list = |filter: &BalanceFilter, limit: u64| {
+        let mut addresses = Vec::new();
+        let mut cursor = None;
+
+        loop {
+            let (next, accounts) = db
+                .get_accounts_by_balance(filter, cursor, limit)
+                .expect("accounts");
+            assert!(accounts.len() <= limit as usize);
+
+            addresses.extend(
+                accounts
+                    .iter()
+                    .map(|account| (account.address, account.info.balance.to::<u64>())),
+            );
+
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break addresses,
+            }
+        }
+    };
+
+    let all = vec![(bob, 300), (contract, 200), (carol, 100), (alice, 100)];
+    assert_eq!(list(&BalanceFilter::default(), 10), all);
+    assert_eq!(list(&BalanceFilter::default(), 1), all);
+
+    assert_eq!(
+        list(
+            &BalanceFilter {
+                min_balance: U256::from(200),
+                ..Default::default()
+            },
+            10
+        ),
+        vec![(bob, 300), (contract, 200)]
+    );
+    assert_eq!(
+        list(
+            &BalanceFilter {
+                kind: Some(AccountKind::Eoa),
+                ..Default::default()
+            },
+            1
+        ),
+        vec![(bob, 300), (carol, 100), (alice, 100)]
+    );
+    assert_eq!(
+        list(
+            &BalanceFilter {
+                kind: Some(AccountKind::Contract),
+                ..Default::default()
+            },
+            10
+        ),
+        vec![(contract, 200)]
+    );
+
+    let (_, accounts) = db
+        .get_accounts_by_balance(
+            &BalanceFilter {
+                with_legacy_attributes: true,
+                ..Default::default()
+            },
+            None,
+            10,
+        )
+        .expect("accounts");
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].address, carol);
+    assert!(!accounts[0].legacy_attributes.is_empty());
+
+    // Balance changes move the accounts within the index and unwinding restores them
+    commit_to_db(&mut db, pending_commit(2, vec![(alice, 500, vec![])]), None).expect("commit");
+    assert_eq!(
+        list(&BalanceFilter::default(), 10),
+        vec![(alice, 500), (bob, 300), (contract, 200), (carol, 100)]
+    );
+
+    db.unwind_to(1).expect("unwind");
+    assert_eq!(list(&BalanceFilter::default(), 10), all);
+
+    let rtxn = db.env.read_txn().unwrap();
+    assert_eq!(db.inner.balance_index.balances.len(&rtxn).unwrap(), 4);
+    drop(rtxn);
+
+    assert!(matches!(
+        db.get_accounts_by_balance(
+            &BalanceFilter::default(),
+            Some(Bytes::from_static(&[1])),
+            10
+        ),
+        Err(Error::InvalidCursor(_))
+    ));
+}
@@ -0,0 +1,297 @@
+use heed::{RwTxn, WithoutTls};
+use revm::{
+    primitives::{B256, KECCAK_EMPTY},
+    state::AccountInfo,
+};
+
+use crate::db::{Error, HashWrapper, InnerStorage, PersistentDB, TablesTxn, map_db_full};
+
+type HeedU64 = heed::types::U64<heed::byteorder::BigEndian>;
+
+// Outcome of a single `collect_garbage` run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GarbageCollection {
+    // Number of deleted bytecodes
+    pub deleted: u64,
+    // Size of the deleted keys and values
+    pub reclaimed_bytes: u64,
+    // Unreferenced bytecodes which are kept since they are still reachable through the reverse
+    // changesets or the account history
+    pub retained: u64,
+}
+
+// Tables tracking how many accounts reference each code hash. Unreferenced code hashes are moved
+// to the orphans together with the block which released the last reference.
+#[derive(Clone)]
+pub(crate) struct CodeReferenceTables {
+    pub references: heed::Database<HashWrapper, HeedU64>,
+    pub orphans: heed::Database<HashWrapper, HeedU64>,
+}
+
+impl CodeReferenceTables {
+    pub const MAX_DBS: u32 = 2;
+
+    pub fn create(env: &heed::Env<WithoutTls>, txn: &mut TablesTxn) -> Result<Self, Error> {
+        let references = txn.database::<HashWrapper, HeedU64>(env, "code_references")?;
+        let orphans = txn.database::<HashWrapper, HeedU64>(env, "orphaned_code")?;
+
+        Ok(Self {
+            references,
+            orphans,
+        })
+    }
+
+    // Moves the reference of an account from its previous to its current code hash, `None` if
+    // the account does not exist (anymore).
+    pub fn update(
+        &self,
+        txn: &mut RwTxn,
+        block_number: u64,
+        previous: Option<&AccountInfo>,
+        current: Option<&AccountInfo>,
+    ) -> Result<(), Error> {
+        let referenced =
+            |info: &AccountInfo| Some(info.code_hash).filter(|hash| *hash != KECCAK_EMPTY);
+
+        let previous = previous.and_then(referenced);
+        let current = current.and_then(referenced);
+
+        if previous == current {
+            return Ok(());
+        }
+
+        if let Some(code_hash) = previous {
+            let key = HashWrapper(code_hash);
+
+            match self.references.get(txn, &key)? {
+                Some(count) if count > 1 => self.references.put(txn, &key, &(count - 1))?,
+                _ => {
+                    self.references.delete(txn, &key)?;
+                    self.orphans.put(txn, &key, &block_number)?;
+                }
+            }
+        }
+
+        if let Some(code_hash) = current {
+            let key = HashWrapper(code_hash);
+            let count = self.references.get(txn, &key)?.unwrap_or_default();
+
+            self.orphans.delete(txn, &key)?;
+            self.references.put(txn, &key, &(count + 1))?;
+        }
+
+        Ok(())
+    }
+
+    // Tracks newly stored bytecode, which is orphaned right away unless an account references it.
+    pub fn insert_code(
+        &self,
+        txn: &mut RwTxn,
+        block_number: u64,
+        code_hash: B256,
+    ) -> Result<(), Error> {
+        let key = HashWrapper(code_hash);
+
+        if self.references.get(txn, &key)?.is_none() && self.orphans.get(txn, &key)?.is_none() {
+            self.orphans.put(txn, &key, &block_number)?;
+        }
+
+        Ok(())
+    }
+
+    // Forgets bytecode which got removed by unwinding the commit that stored it.
+    pub fn delete_code(&self, txn: &mut RwTxn, code_hash: B256) -> Result<(), Error> {
+        let key = HashWrapper(code_hash);
+
+        self.references.delete(txn, &key)?;
+        self.orphans.delete(txn, &key)?;
+
+        Ok(())
+    }
+}
+
+// Bytecode orphaned before the returned block cannot be reached anymore. Unwinding the block which
+// released the last reference restores the account and historical reads before that block may
+// return it, thus both its reverse changeset and any older account history have to be gone.
+fn collectable_before(txn: &heed::RoTxn, inner: &InnerStorage) -> Result<u64, Error> {
+    let mut block_number = u64::MAX;
+
+    if let Some((first, _)) = inner.reverts.first(txn)? {
+        block_number = block_number.min(first);
+    }
+
+    if let Some(database) = &inner.accounts_history
+        && let Some((first, _)) = database.first(txn)?
+    {
+        block_number = block_number.min(first.saturating_add(1));
+    }
+
+    Ok(block_number)
+}
+
+impl PersistentDB {
+    // Deletes the bytecode which is no longer referenced by any account and can neither be
+    // restored by unwinding nor read through the account history.
+    pub fn collect_garbage(&self) -> Result<GarbageCollection, Error> {
+        loop {
+            match self.collect_garbage_txn() {
+                Err(Error::DbFull) => self.resize()?,
+                result => return result,
+            }
+        }
+    }
+
+    fn collect_garbage_txn(&self) -> Result<GarbageCollection, Error> {
+        let mut rwtxn = self.write_txn()?;
+        let inner = &self.inner;
+
+        let collect = |rwtxn: &mut RwTxn| -> Result<GarbageCollection, Error> {
+            let collectable_before = collectable_before(rwtxn, inner)?;
+            let code_references = &inner.code_references;
+
+            let mut collection = GarbageCollection::default();
+            let mut collectable = Vec::new();
+
+            for item in code_references.orphans.iter(rwtxn)? {
+                let (code_hash, orphaned_at) = item?;
+
+                match orphaned_at < collectable_before {
+                    true => collectable.push(code_hash.0),
+                    false => collection.retained += 1,
+                }
+            }
+
+            let contracts = inner.contracts.remap_data_type::<heed::types::Bytes>();
+
+            for code_hash in collectable {
+                let key = HashWrapper(code_hash);
+
+                if let Some(bytecode) = contracts.get(rwtxn, &key)? {
+                    collection.deleted += 1;
+                    collection.reclaimed_bytes += (code_hash.len() + bytecode.len()) as u64;
+                }
+
+                contracts.delete(rwtxn, &key)?;
+                code_references.orphans.delete(rwtxn, &key)?;
+            }
+
+            Ok(collection)
+        };
+
+        match collect(&mut rwtxn) {
+            Ok(collection) => {
+                rwtxn.commit()?;
+                Ok(collection)
+            }
+            Err(err) => {
+                rwtxn.abort();
+                Err(map_db_full(err))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_collect_garbage() {
+    use revm::{
+        DatabaseRef,
+        primitives::{Bytes, address, keccak256},
+        state::Bytecode,
+    };
+
+    use crate::{
+        db::{PendingCommit, PersistentDBOptions},
+        state_commit::commit_to_db,
+        trie::pending_commit,
+    };
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf()).with_unwind_depth(2))
+            .expect("database");
+
+    let contract = address!("1000000000000000000000000000000000000001");
+    let alice = address!("2000000000000000000000000000000000000002");
+
+    let code_x = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x01]));
+    let code_y = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x02]));
+    let hash_x = keccak256(code_x.original_byte_slice());
+    let hash_y = keccak256(code_y.original_byte_slice());
+
+    let deploy = |block_number: u64, code: &Bytecode| -> PendingCommit {
+        let mut pending = pending_commit(block_number, vec![(contract, 0, vec![])]);
+        let info = pending
+            .transitions
+            .transitions
+            .get_mut(&contract)
+            .and_then(|transition| transition.info.as_mut())
+            .expect("contract");
+        info.code_hash = keccak256(code.original_byte_slice());
+        info.code = Some(code.clone());
+        pending
+    };
+
+    let references = |db: &PersistentDB, code_hash: B256| {
+        let rtxn = db.env.read_txn().unwrap();
+        let inner = &db.inner;
+        (
+            inner
+                .code_references
+                .references
+                .get(&rtxn, &HashWrapper(code_hash))
+                .unwrap(),
+            inner
+                .code_references
+                .orphans
+                .get(&rtxn, &HashWrapper(code_hash))
+                .unwrap(),
+        )
+    };
+
+    commit_to_db(&mut db, deploy(1, &code_x), None).expect("commit");
+    assert_eq!(references(&db, hash_x), (Some(1), None));
+
+    // Replacing the code releases the previous one
+    commit_to_db(&mut db, deploy(2, &code_y), None).expect("commit");
+    assert_eq!(references(&db, hash_x), (None, Some(2)));
+    assert_eq!(references(&db, hash_y), (Some(1), None));
+
+    // The reverse changeset of block 2 still restores the code
+    assert_eq!(
+        db.collect_garbage().unwrap(),
+        GarbageCollection {
+            deleted: 0,
+            reclaimed_bytes: 0,
+            retained: 1,
+        }
+    );
+
+    db.unwind_to(1).expect("unwind");
+    assert_eq!(references(&db, hash_x), (Some(1), None));
+    assert_eq!(references(&db, hash_y), (None, None));
+
+    commit_to_db(&mut db, deploy(2, &code_y), None).expect("commit");
+    for block_number in 3..=4 {
+        commit_to_db(
+            &mut db,
+            pending_commit(block_number, vec![(alice, block_number, vec![])]),
+            None,
+        )
+        .expect("commit");
+    }
+
+    let collection = db.collect_garbage().unwrap();
+    assert_eq!(collection.deleted, 1);
+    assert!(collection.reclaimed_bytes > hash_x.len() as u64);
+    assert_eq!(collection.retained, 0);
+
+    assert_eq!(references(&db, hash_x), (None, None));
+    assert!(db.code_by_hash_ref(hash_x).unwrap().is_empty());
+    assert_eq!(db.code_by_hash_ref(hash_y).unwrap(), code_y);
+
+    assert_eq!(db.collect_garbage().unwrap(), GarbageCollection::default());
+}
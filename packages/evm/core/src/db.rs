@@ -1,37 +1,48 @@
 use std::{
     borrow::Cow,
-    cell::RefCell,
     cmp::Ordering,
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     convert::Infallible,
-    path::PathBuf,
-    sync::{LazyLock, RwLock},
+    ops::{Bound, Deref},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, LazyLock, Mutex, PoisonError, RwLock,
+        atomic::{self, AtomicU64},
+    },
 };
 
-use heed::{Comparator, EnvFlags, EnvOpenOptions};
-use rayon::slice::ParallelSliceMut;
+use heed::{Comparator, EnvFlags, EnvOpenOptions, RoTxn, WithoutTls};
 use revm::{
     Database, DatabaseRef,
-    context::{DBErrorMarker, result::ExecutionResult},
+    context::DBErrorMarker,
     database::{CacheState, TransitionState},
-    primitives::*,
+    primitives::{alloy_primitives::Bloom, *},
     state::{AccountInfo, Bytecode},
 };
 use serde::{Deserialize, Serialize};
 
 use crate::{
     account::AccountInfoExtended,
-    historical::{AccountHistory, HistoricalAccountData},
+    backend::StorageTxn,
+    balance_index::BalanceIndexTables,
+    code_references::CodeReferenceTables,
+    deployment::{self, ContractDeployment},
+    historical::{AccountHistory, HistoricalAccountData, HistoricalState, StorageHistory},
     legacy::{LegacyAccountAttributes, LegacyAddress, LegacyColdWallet},
+    log_index::LogIndexTables,
     logger::{LogLevel, Logger},
-    receipt::{TxReceipt, map_execution_result},
-    state_changes,
+    migrations::{self, SCHEMA_VERSION},
+    pruning::{self, PrunedTable, Retained, RetentionPolicy},
+    read_handle::ResizeGate,
+    receipt::{ExecutedTransaction, IndexedReceipt, ReceiptLocation, TxReceipt},
+    revert::{BlockDataRevert, BlockRevert, StorageRevert},
     state_commit::StateCommit,
-    state_root,
+    state_root::StateRootMode,
+    trie::{AccountProof, StorageTrieUpdate, TrieChanges, TrieTables},
 };
 
 #[derive(Debug)]
-pub(crate) struct AddressWrapper(Address);
+pub(crate) struct AddressWrapper(pub(crate) Address);
 impl heed::BytesEncode<'_> for AddressWrapper {
     type EItem = AddressWrapper;
 
@@ -49,7 +60,7 @@ impl heed::BytesDecode<'_> for AddressWrapper {
 }
 
 #[derive(Debug)]
-pub(crate) struct LegacyAddressWrapper(LegacyAddress);
+pub(crate) struct LegacyAddressWrapper(pub(crate) LegacyAddress);
 impl heed::BytesEncode<'_> for LegacyAddressWrapper {
     type EItem = LegacyAddressWrapper;
 
@@ -84,7 +95,7 @@ impl heed::BytesDecode<'_> for BytesWrapper {
 }
 
 #[derive(Debug)]
-pub(crate) struct HashWrapper(B256);
+pub(crate) struct HashWrapper(pub(crate) B256);
 impl heed::BytesEncode<'_> for HashWrapper {
     type EItem = HashWrapper;
 
@@ -93,8 +104,16 @@ impl heed::BytesEncode<'_> for HashWrapper {
     }
 }
 
+impl heed::BytesDecode<'_> for HashWrapper {
+    type DItem = HashWrapper;
+
+    fn bytes_decode(bytes: &'_ [u8]) -> Result<Self::DItem, heed::BoxedError> {
+        Ok(HashWrapper(B256::from_slice(bytes)))
+    }
+}
+
 #[derive(Debug)]
-pub(crate) struct StringWrapper(String);
+pub(crate) struct StringWrapper(pub(crate) String);
 impl heed::BytesEncode<'_> for StringWrapper {
     type EItem = StringWrapper;
 
@@ -104,7 +123,7 @@ impl heed::BytesEncode<'_> for StringWrapper {
 }
 
 #[derive(Debug)]
-pub(crate) struct StaticStringWrapper(&'static str);
+pub(crate) struct StaticStringWrapper(pub(crate) &'static str);
 impl heed::BytesEncode<'_> for StaticStringWrapper {
     type EItem = StaticStringWrapper;
 
@@ -116,7 +135,7 @@ impl heed::BytesEncode<'_> for StaticStringWrapper {
 type HeedBlockNumber = heed::types::U64<heed::byteorder::BigEndian>;
 
 #[derive(Debug)]
-pub(crate) struct StorageEntryWrapper(U256, U256);
+pub(crate) struct StorageEntryWrapper(pub(crate) U256, pub(crate) U256);
 impl heed::BytesEncode<'_> for StorageEntryWrapper {
     type EItem = StorageEntryWrapper;
 
@@ -152,15 +171,46 @@ impl Comparator for StorageEntryDupSortCmp {
     }
 }
 
-// txHash -> receipt
+// (txHash, receipt) in execution order
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct CommitReceipts {
+    pub(crate) accounts_hash: B256,
+    pub(crate) storage_hash: B256,
+    pub(crate) contracts_hash: B256,
+    // Unknown for commits whose receipts got pruned before the bloom was stored
+    pub(crate) logs_bloom: Option<Bloom>,
+    // Unknown if the state root was not calculated before committing
+    pub(crate) state_root: Option<B256>,
+    pub(crate) tx_receipts: Vec<(B256, TxReceipt)>,
+}
+
+// Leading fields of `CommitReceipts`. Bincode ignores the trailing receipts, which allows reading
+// the metadata of a commit without decoding its receipts.
 #[derive(Default, Debug, Serialize, Deserialize)]
-pub(crate) struct CommitReceipts {
-    accounts_hash: B256,
-    storage_hash: B256,
-    contracts_hash: B256,
-    tx_receipts: HashMap<B256, TxReceipt>,
+pub(crate) struct CommitMetadata {
+    pub(crate) accounts_hash: B256,
+    pub(crate) storage_hash: B256,
+    pub(crate) contracts_hash: B256,
+    pub(crate) logs_bloom: Option<Bloom>,
+    pub(crate) state_root: Option<B256>,
+}
+
+impl CommitReceipts {
+    pub(crate) fn tx_hashes(&self) -> impl Iterator<Item = &B256> {
+        self.tx_receipts.iter().map(|(tx_hash, _)| tx_hash)
+    }
+
+    pub(crate) fn take_receipt(&mut self, tx_hash: &B256) -> Option<TxReceipt> {
+        let index = self
+            .tx_receipts
+            .iter()
+            .position(|(hash, _)| hash == tx_hash)?;
+
+        Some(self.tx_receipts.swap_remove(index).1)
+    }
 }
 
+#[derive(Clone)]
 pub(crate) struct InnerStorage {
     pub accounts: heed::Database<AddressWrapper, heed::types::SerdeBincode<AccountInfo>>,
     pub accounts_history: Option<
@@ -169,6 +219,12 @@ pub(crate) struct InnerStorage {
             heed::types::SerdeBincode<BTreeMap<Address, HistoricalAccountData>>,
         >,
     >,
+    pub storage_history: Option<
+        heed::Database<
+            HeedBlockNumber,
+            heed::types::SerdeBincode<BTreeMap<Address, BTreeMap<U256, U256>>>,
+        >,
+    >,
     pub commits: heed::Database<HeedBlockNumber, heed::types::SerdeBincode<CommitReceipts>>,
     pub contracts: heed::Database<HashWrapper, heed::types::SerdeBincode<Bytecode>>,
     pub legacy_attributes:
@@ -186,9 +242,17 @@ pub(crate) struct InnerStorage {
     pub proofs: heed::Database<HeedBlockNumber, heed::types::SerdeBincode<Bytes>>,
     pub blocks: heed::Database<HeedBlockNumber, heed::types::SerdeBincode<Bytes>>,
     pub blocks_hash_number: heed::Database<HashWrapper, HeedBlockNumber>,
+    pub blocks_number_hash: heed::Database<HeedBlockNumber, HashWrapper>,
     pub transactions: heed::Database<StringWrapper, heed::types::SerdeBincode<Bytes>>,
     pub transactions_hash_key: heed::Database<HashWrapper, heed::types::SerdeBincode<String>>,
     //
+    pub receipt_locations: heed::Database<HashWrapper, heed::types::SerdeBincode<ReceiptLocation>>,
+    pub reverts: heed::Database<HeedBlockNumber, heed::types::SerdeBincode<BlockRevert>>,
+    pub deployments: heed::Database<AddressWrapper, heed::types::SerdeBincode<ContractDeployment>>,
+    pub log_index: LogIndexTables,
+    pub balance_index: BalanceIndexTables,
+    pub code_references: CodeReferenceTables,
+    pub trie: Option<TrieTables>,
 }
 
 // A key of (block_number, round, block_hash) used to associate state with a processable unit.
@@ -209,7 +273,7 @@ pub struct CommitData {
 pub struct PendingCommit {
     pub key: CommitKey,
     pub cache: CacheState,
-    pub results: BTreeMap<B256, ExecutionResult>,
+    pub results: BTreeMap<B256, ExecutedTransaction>,
     pub transitions: TransitionState,
 
     // Map of legacy attributes
@@ -239,11 +303,25 @@ pub struct GenesisInfo {
 }
 
 pub struct PersistentDB {
-    pub(crate) env: heed::Env,
-    pub(crate) inner: RefCell<InnerStorage>,
+    pub(crate) env: heed::Env<WithoutTls>,
+    pub(crate) inner: InnerStorage,
     pub(crate) accounts_history: Option<AccountHistory>,
-    logger: Logger,
-    pub genesis_info: Option<GenesisInfo>,
+    pub(crate) storage_history: Option<StorageHistory>,
+    pub(crate) unwind_depth: u64,
+    pub(crate) retention: RetentionPolicy,
+    pub(crate) state_root_mode: StateRootMode,
+    pub(crate) logger: Logger,
+    // Shared with the read handles, which may be opened before the genesis is initialized
+    pub(crate) genesis_info: Arc<RwLock<Option<GenesisInfo>>>,
+    pub(crate) read_only: bool,
+    // Set for read handles, which serve all reads from this transaction
+    pub(crate) pinned_txn: Option<Mutex<RoTxn<'static, WithoutTls>>>,
+    pub(crate) resize_gate: Arc<ResizeGate>,
+    // Number of map resizes since opening, including those adopted from other processes
+    pub(crate) resize_count: Arc<AtomicU64>,
+    // Set for ephemeral databases, the directory is removed once the database and its read handles
+    // are gone
+    pub(crate) temp_dir: Option<Arc<tempfile::TempDir>>,
 }
 
 #[derive(Default)]
@@ -251,6 +329,10 @@ pub struct PersistentDBOptions {
     pub path: PathBuf,
     pub logger: Option<Logger>,
     pub history_size: Option<u64>,
+    pub unwind_depth: Option<u64>,
+    pub retention: RetentionPolicy,
+    pub state_root_mode: StateRootMode,
+    pub read_only: bool,
 }
 
 impl PersistentDBOptions {
@@ -270,6 +352,33 @@ impl PersistentDBOptions {
         self.history_size.replace(history_size);
         self
     }
+
+    // Limits the number of most recent blocks that can be unwound, reverse changesets of older
+    // blocks are discarded. Defaults to `DEFAULT_UNWIND_DEPTH`, the history size is kept at least.
+    pub fn with_unwind_depth(mut self, unwind_depth: u64) -> Self {
+        self.unwind_depth.replace(unwind_depth);
+        self
+    }
+
+    // Limits the number of most recent blocks for which block data, proofs, transactions and
+    // receipts are kept. Older data is pruned incrementally while committing.
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    // The state root mode of a chain cannot be changed once the first block has been committed.
+    pub fn with_state_root_mode(mut self, state_root_mode: StateRootMode) -> Self {
+        self.state_root_mode = state_root_mode;
+        self
+    }
+
+    // Opens an existing database for reading only, e.g. by secondary processes next to the one
+    // committing blocks. Any mutating call fails with `Error::ReadOnly`.
+    pub fn with_read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -286,131 +395,342 @@ pub enum Error {
     Infallible(#[from] Infallible),
     #[error("Lock error")]
     Lock,
+    #[error("missing reverse changeset for block {0}")]
+    MissingRevert(u64),
+    #[error("block {0} is outside of the history window")]
+    HistoryUnavailable(u64),
+    #[error("state root mode mismatch, database uses {0:?}")]
+    StateRootModeMismatch(StateRootMode),
+    #[error("proofs require the merkle patricia trie state root mode")]
+    ProofUnavailable,
+    #[error("invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+    #[error("unknown database schema: {0}")]
+    UnknownSchema(String),
+    #[error("invalid log filter: {0}")]
+    InvalidLogFilter(String),
+    #[error("invalid cursor: {0}")]
+    InvalidCursor(Bytes),
+    #[error("database is opened read-only")]
+    ReadOnly,
 }
 
 impl DBErrorMarker for Error {}
 
-static ENV: LazyLock<RwLock<HashMap<PathBuf, heed::Env>>> = LazyLock::new(RwLock::default);
+// Transaction the tables are opened with, tables are only created if the database is writable.
+pub(crate) enum TablesTxn<'e> {
+    Write(heed::RwTxn<'e>),
+    Read(RoTxn<'e, WithoutTls>),
+}
+
+impl<'e> TablesTxn<'e> {
+    pub(crate) fn database<KC: 'static, DC: 'static>(
+        &mut self,
+        env: &heed::Env<WithoutTls>,
+        name: &str,
+    ) -> Result<heed::Database<KC, DC>, Error> {
+        match self {
+            TablesTxn::Write(wtxn) => Ok(env.create_database(wtxn, Some(name))?),
+            TablesTxn::Read(rtxn) => env
+                .open_database(rtxn, Some(name))?
+                .ok_or_else(|| missing_table(name)),
+        }
+    }
+
+    fn commit(self) -> Result<(), Error> {
+        match self {
+            TablesTxn::Write(wtxn) => wtxn.commit()?,
+            // Committing keeps the opened tables available after the transaction
+            TablesTxn::Read(rtxn) => rtxn.commit()?,
+        }
+
+        Ok(())
+    }
+}
+
+impl<'e> Deref for TablesTxn<'e> {
+    type Target = RoTxn<'e>;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            TablesTxn::Write(wtxn) => wtxn,
+            TablesTxn::Read(rtxn) => rtxn,
+        }
+    }
+}
+
+pub(crate) fn missing_table(name: &str) -> Error {
+    Error::UnknownSchema(format!("missing table {}", name))
+}
+
+// Like `TablesTxn::database` for tables which need further options, e.g. custom comparators.
+macro_rules! open_table {
+    ($txn:expr, $name:expr, $options:expr) => {
+        match $txn {
+            $crate::db::TablesTxn::Write(wtxn) => $options.create(wtxn)?,
+            $crate::db::TablesTxn::Read(rtxn) => $options
+                .open(rtxn)?
+                .ok_or_else(|| $crate::db::missing_table($name))?,
+        }
+    };
+}
+
+pub(crate) use open_table;
+
+// Backed by tmpfs, see `PersistentDB::new_ephemeral`
+const SHARED_MEMORY_PATH: &str = "/dev/shm";
+
+// The environments are shared by all databases opened from the same path, together with the gate
+// which keeps resizes from happening while any of them has a read handle open.
+static ENV: LazyLock<RwLock<HashMap<PathBuf, (heed::Env<WithoutTls>, Arc<ResizeGate>)>>> =
+    LazyLock::new(RwLock::default);
 
 impl PersistentDB {
-    const MAX_DBS: u32 = 12;
+    const MAX_DBS: u32 = 21;
 
     pub fn new(opts: PersistentDBOptions) -> Result<Self, Error> {
-        std::fs::create_dir_all(&opts.path)?;
+        if !opts.read_only {
+            std::fs::create_dir_all(&opts.path)?;
+        }
 
         let mut lock = ENV.write().map_err(|_| Error::Lock)?;
 
-        let env = match lock.get(&opts.path) {
-            Some(env) => env.clone(),
+        let (env, resize_gate) = match lock.get(&opts.path) {
+            Some((env, resize_gate)) => (env.clone(), resize_gate.clone()),
             None => {
-                let mut env_builder = EnvOpenOptions::new();
+                let env = Self::create_env(&opts)?;
+                let resize_gate = Arc::<ResizeGate>::default();
+                lock.insert(opts.path.clone(), (env.clone(), resize_gate.clone()));
 
-                let mut max_dbs = Self::MAX_DBS;
-                if opts.history_size.is_some() {
-                    max_dbs += 1;
-                }
+                (env, resize_gate)
+            }
+        };
 
-                env_builder.max_dbs(max_dbs);
-                env_builder.map_size(1 * MAP_SIZE_UNIT);
-                unsafe { env_builder.flags(EnvFlags::NO_SUB_DIR) };
+        Self::open_env(env, resize_gate, opts)
+    }
 
-                let env = unsafe { env_builder.open(opts.path.join("evm.mdb")) }?;
-                lock.insert(opts.path.clone(), env.clone());
+    // Opens the environment without sharing it, see `ENV` for databases opened through `new`.
+    pub(crate) fn create_env(opts: &PersistentDBOptions) -> Result<heed::Env<WithoutTls>, Error> {
+        // Transactions are not bound to threads, which allows read handles to move between threads
+        let mut env_builder = EnvOpenOptions::new().read_txn_without_tls();
 
-                env
-            }
-        };
+        let mut max_dbs = Self::MAX_DBS;
+        if opts.history_size.is_some() {
+            max_dbs += 2;
+        }
+
+        if opts.state_root_mode == StateRootMode::MerklePatriciaTrie {
+            max_dbs += TrieTables::MAX_DBS;
+        }
 
-        Self::new_with_env(env, opts)
+        env_builder.max_dbs(max_dbs);
+        env_builder.map_size(1 * MAP_SIZE_UNIT);
+
+        // The map size of read-only databases grows with the file written by others
+        let mut flags = EnvFlags::NO_SUB_DIR;
+        if opts.read_only {
+            flags |= EnvFlags::READ_ONLY;
+        }
+        unsafe { env_builder.flags(flags) };
+
+        Ok(unsafe { env_builder.open(opts.path.join("evm.mdb")) }?)
+    }
+
+    pub fn new_with_env(
+        env: heed::Env<WithoutTls>,
+        opts: PersistentDBOptions,
+    ) -> Result<Self, Error> {
+        Self::open_env(env, Default::default(), opts)
+    }
+
+    // Opens a database in a temporary directory on tmpfs, which keeps it in memory. The path of the
+    // options is ignored. Fails if no tmpfs is available instead of falling back to the disk.
+    pub fn new_ephemeral(mut opts: PersistentDBOptions) -> Result<Self, Error> {
+        if !Path::new(SHARED_MEMORY_PATH).is_dir() {
+            return Err(Error::IO(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} is not available", SHARED_MEMORY_PATH),
+            )));
+        }
+
+        let temp_dir = tempfile::Builder::new()
+            .prefix("evm.mdb")
+            .tempdir_in(SHARED_MEMORY_PATH)?;
+
+        opts.path = temp_dir.path().to_path_buf();
+        opts.read_only = false;
+
+        // Not shared through `ENV`, which would keep the environment of the removed directory open
+        // for the lifetime of the process
+        let env = Self::create_env(&opts)?;
+        let mut db = Self::new_with_env(env, opts)?;
+        db.temp_dir = Some(Arc::new(temp_dir));
+
+        Ok(db)
     }
 
-    pub fn new_with_env(env: heed::Env, opts: PersistentDBOptions) -> Result<Self, Error> {
+    fn open_env(
+        env: heed::Env<WithoutTls>,
+        resize_gate: Arc<ResizeGate>,
+        opts: PersistentDBOptions,
+    ) -> Result<Self, Error> {
         let real_disk_size = env.real_disk_size()?;
-        if real_disk_size >= env.info().map_size as u64 {
+        if !opts.read_only && real_disk_size >= env.info().map_size as u64 {
             // ensure initial map size is always larger than disk size
-            unsafe { env.resize(next_map_size(real_disk_size as usize))? };
+            resize_gate
+                .exclusive(|| unsafe { env.resize(next_map_size(real_disk_size as usize)) })?;
         }
 
         let tx_env = env.clone();
-        let mut wtxn = tx_env.write_txn()?;
+        let mut tables = match opts.read_only {
+            true => TablesTxn::Read(tx_env.read_txn()?),
+            false => TablesTxn::Write(tx_env.write_txn()?),
+        };
 
-        let accounts = env
-            .create_database::<AddressWrapper, heed::types::SerdeBincode<AccountInfo>>(
-                &mut wtxn,
-                Some("accounts"),
-            )?;
+        let accounts = tables
+            .database::<AddressWrapper, heed::types::SerdeBincode<AccountInfo>>(&env, "accounts")?;
 
         let (accounts_history_db, accounts_history) = match opts.history_size {
             Some(history_size) if history_size > 0 => {
-                let db = env.create_database::<HeedBlockNumber, heed::types::SerdeBincode<
-            BTreeMap<Address, HistoricalAccountData>>>(&mut wtxn, Some("accounts_history")) ?;
+                let db = tables.database::<HeedBlockNumber, heed::types::SerdeBincode<
+                    BTreeMap<Address, HistoricalAccountData>,
+                >>(&env, "accounts_history")?;
                 (Some(db), Some(AccountHistory::new(history_size)))
             }
             _ => (None, None),
         };
 
-        let commits = env
-            .create_database::<HeedBlockNumber, heed::types::SerdeBincode<CommitReceipts>>(
-                &mut wtxn,
-                Some("commits"),
+        let (storage_history_db, storage_history) = match opts.history_size {
+            Some(history_size) if history_size > 0 => {
+                let db = tables.database::<HeedBlockNumber, heed::types::SerdeBincode<
+                    BTreeMap<Address, BTreeMap<U256, U256>>,
+                >>(&env, "storage_history")?;
+                (Some(db), Some(StorageHistory::new(history_size)))
+            }
+            _ => (None, None),
+        };
+
+        let commits = tables
+            .database::<HeedBlockNumber, heed::types::SerdeBincode<CommitReceipts>>(
+                &env, "commits",
             )?;
-        let contracts = env.create_database::<HashWrapper, heed::types::SerdeBincode<Bytecode>>(
-            &mut wtxn,
-            Some("contracts"),
-        )?;
-        let legacy_attributes = env
-            .create_database::<AddressWrapper, heed::types::SerdeBincode<LegacyAccountAttributes>>(
-                &mut wtxn,
-                Some("legacy_attributes"),
+        let contracts = tables
+            .database::<HashWrapper, heed::types::SerdeBincode<Bytecode>>(&env, "contracts")?;
+        let legacy_attributes = tables
+            .database::<AddressWrapper, heed::types::SerdeBincode<LegacyAccountAttributes>>(
+                &env,
+                "legacy_attributes",
             )?;
-        let legacy_cold_wallets = env
-            .create_database::<LegacyAddressWrapper, heed::types::SerdeBincode<LegacyColdWallet>>(
-                &mut wtxn,
-                Some("legacy_cold_wallets"),
+        let legacy_cold_wallets = tables
+            .database::<LegacyAddressWrapper, heed::types::SerdeBincode<LegacyColdWallet>>(
+                &env,
+                "legacy_cold_wallets",
             )?;
-        let storage = env
-            .database_options()
-            .types::<AddressWrapper, StorageEntryWrapper>()
-            .name("storage")
-            .flags(heed::DatabaseFlags::DUP_SORT)
-            .dup_sort_comparator::<StorageEntryDupSortCmp>()
-            .create(&mut wtxn)?;
+        let storage = open_table!(
+            &mut tables,
+            "storage",
+            env.database_options()
+                .types::<AddressWrapper, StorageEntryWrapper>()
+                .name("storage")
+                .flags(heed::DatabaseFlags::DUP_SORT)
+                .dup_sort_comparator::<StorageEntryDupSortCmp>()
+        );
 
         // Carried over from previous database-service.ts lmdb backend
-        let state = env.create_database::<StaticStringWrapper, heed::types::SerdeBincode<Bytes>>(
-            &mut wtxn,
-            Some("state"),
-        )?;
-        let proofs = env.create_database::<HeedBlockNumber, heed::types::SerdeBincode<Bytes>>(
-            &mut wtxn,
-            Some("proofs"),
-        )?;
-        let blocks = env.create_database::<HeedBlockNumber, heed::types::SerdeBincode<Bytes>>(
-            &mut wtxn,
-            Some("blocks"),
-        )?;
-        let blocks_hash_number = env.create_database::<HashWrapper, HeedBlockNumber>(
-            &mut wtxn,
-            Some("blocks_hash_number"),
-        )?;
-        let transactions = env.create_database::<StringWrapper, heed::types::SerdeBincode<Bytes>>(
-            &mut wtxn,
-            Some("transactions"),
-        )?;
-        let transactions_hash_key = env
-            .create_database::<HashWrapper, heed::types::SerdeBincode<String>>(
-                &mut wtxn,
-                Some("transactions_hash_key"),
+        let state = tables
+            .database::<StaticStringWrapper, heed::types::SerdeBincode<Bytes>>(&env, "state")?;
+        let proofs =
+            tables.database::<HeedBlockNumber, heed::types::SerdeBincode<Bytes>>(&env, "proofs")?;
+        let blocks =
+            tables.database::<HeedBlockNumber, heed::types::SerdeBincode<Bytes>>(&env, "blocks")?;
+        let blocks_hash_number =
+            tables.database::<HashWrapper, HeedBlockNumber>(&env, "blocks_hash_number")?;
+        let blocks_number_hash =
+            tables.database::<HeedBlockNumber, HashWrapper>(&env, "blocks_number_hash")?;
+        let transactions = tables
+            .database::<StringWrapper, heed::types::SerdeBincode<Bytes>>(&env, "transactions")?;
+        let transactions_hash_key = tables
+            .database::<HashWrapper, heed::types::SerdeBincode<String>>(
+                &env,
+                "transactions_hash_key",
             )?;
         //
 
-        wtxn.commit()?;
+        let receipt_locations = tables
+            .database::<HashWrapper, heed::types::SerdeBincode<ReceiptLocation>>(
+                &env,
+                "receipt_locations",
+            )?;
+
+        let reverts = tables
+            .database::<HeedBlockNumber, heed::types::SerdeBincode<BlockRevert>>(&env, "reverts")?;
+
+        let deployments = tables
+            .database::<AddressWrapper, heed::types::SerdeBincode<ContractDeployment>>(
+                &env,
+                "contract_deployments",
+            )?;
+
+        let log_index = LogIndexTables::create(&env, &mut tables)?;
+        let balance_index = BalanceIndexTables::create(&env, &mut tables)?;
+        let code_references = CodeReferenceTables::create(&env, &mut tables)?;
+
+        // Existing databases without a schema version predate versioning and get migrated below
+        let schema_version = match migrations::read_schema_version(&tables, &state)? {
+            Some(version) => version,
+            None if commits.is_empty(&tables)? => {
+                if let TablesTxn::Write(wtxn) = &mut tables {
+                    migrations::write_schema_version(wtxn, &state, SCHEMA_VERSION)?;
+                }
+                SCHEMA_VERSION
+            }
+            None => 0,
+        };
+
+        if schema_version > SCHEMA_VERSION {
+            return Err(Error::UnknownSchema(format!(
+                "version {} is newer than the supported version {}",
+                schema_version, SCHEMA_VERSION
+            )));
+        }
+
+        // Read-only databases cannot migrate, the writer has to do so first
+        if opts.read_only && schema_version < SCHEMA_VERSION {
+            return Err(Error::UnknownSchema(format!(
+                "version {} has not been migrated to version {} yet",
+                schema_version, SCHEMA_VERSION
+            )));
+        }
+
+        // Existing databases without a stored mode predate the Merkle Patricia Trie
+        let state_root_mode_key = StaticStringWrapper("state_root_mode");
+        let state_root_mode = match state.get(&tables, &state_root_mode_key)? {
+            Some(mode) => StateRootMode::from_bytes(&mode),
+            None if commits.is_empty(&tables)? => opts.state_root_mode,
+            None => StateRootMode::Legacy,
+        };
+
+        if state_root_mode != opts.state_root_mode {
+            return Err(Error::StateRootModeMismatch(state_root_mode));
+        }
+
+        if let TablesTxn::Write(wtxn) = &mut tables {
+            state.put(wtxn, &state_root_mode_key, &state_root_mode.to_bytes())?;
+        }
+
+        let trie = match state_root_mode {
+            StateRootMode::MerklePatriciaTrie => Some(TrieTables::create(&env, &mut tables)?),
+            StateRootMode::Legacy => None,
+        };
 
-        Ok(Self {
+        tables.commit()?;
+
+        let db = Self {
             env,
-            inner: RefCell::new(InnerStorage {
+            inner: InnerStorage {
                 accounts,
                 accounts_history: accounts_history_db,
+                storage_history: storage_history_db,
                 commits,
                 contracts,
                 legacy_attributes,
@@ -420,57 +740,89 @@ impl PersistentDB {
                 proofs,
                 blocks,
                 blocks_hash_number,
+                blocks_number_hash,
                 transactions,
                 transactions_hash_key,
-            }),
+                receipt_locations,
+                reverts,
+                deployments,
+                log_index,
+                balance_index,
+                code_references,
+                trie,
+            },
             accounts_history,
+            storage_history,
+            // Historical reads within the history window depend on the reverse changesets
+            unwind_depth: opts
+                .unwind_depth
+                .unwrap_or(DEFAULT_UNWIND_DEPTH)
+                .max(opts.history_size.unwrap_or_default()),
+            retention: opts.retention,
+            state_root_mode,
             logger: opts.logger.unwrap_or_default(),
-            genesis_info: None,
-        })
+            genesis_info: Default::default(),
+            read_only: opts.read_only,
+            pinned_txn: None,
+            resize_gate,
+            resize_count: Default::default(),
+            temp_dir: None,
+        };
+
+        if !db.read_only {
+            db.run_migrations(migrations::MIGRATIONS)?;
+        }
+
+        Ok(db)
     }
 
     pub fn set_genesis_info(&mut self, genesis_info: GenesisInfo) {
-        self.genesis_info.replace(genesis_info);
+        self.genesis_info
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replace(genesis_info);
+    }
+
+    pub fn genesis_info(&self) -> Option<GenesisInfo> {
+        self.genesis_info
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn state_root_mode(&self) -> StateRootMode {
+        self.state_root_mode
     }
 
     pub fn get_accounts(
         &self,
-        offset: u64,
+        cursor: Option<Bytes>,
         limit: u64,
-    ) -> Result<(Option<u64>, Vec<AccountInfoExtended>), Error> {
-        let tx_env = self.env.read_txn()?;
-        let iter = self
-            .inner
-            .borrow()
-            .accounts
-            .iter(&tx_env)?
-            .skip(offset as usize);
+    ) -> Result<(Option<Bytes>, Vec<AccountInfoExtended>), Error> {
+        let tx_env = self.read_txn()?;
+        let inner = &self.inner;
+
+        let start = cursor_start::<AddressWrapper>(&cursor, Address::len_bytes())?;
+        let iter = inner.accounts.range(&tx_env, &(start, Bound::Unbounded))?;
 
-        let (cursor, mut accounts) = self.get_items(
+        let (cursor, mut accounts) = self.get_items::<AddressWrapper, _, _, _>(
             iter,
-            |item| match item {
-                Some(item) => {
-                    let (address, info) = item?;
-                    Ok(Some(AccountInfoExtended {
-                        address: address.0,
-                        info: AccountInfo {
-                            balance: info.balance,
-                            nonce: info.nonce,
-                            ..Default::default()
-                        },
+            |address, info| {
+                Ok(AccountInfoExtended {
+                    address: address.0,
+                    info: AccountInfo {
+                        balance: info.balance,
+                        nonce: info.nonce,
                         ..Default::default()
-                    }))
-                }
-                None => Ok(None),
+                    },
+                    ..Default::default()
+                })
             },
-            offset,
             limit,
         )?;
 
         for account in accounts.iter_mut() {
-            if let Some(legacy_attributes) = self
-                .inner
-                .borrow()
+            if let Some(legacy_attributes) = inner
                 .legacy_attributes
                 .get(&tx_env, &AddressWrapper(account.address))?
             {
@@ -481,56 +833,92 @@ impl PersistentDB {
         Ok((cursor, accounts))
     }
 
+    // Lists the non-zero storage slots of `address` beginning at `start`. Slots are returned in
+    // the order of the storage table, which compares them by their little endian bytes, and the
+    // slot to continue from is returned if there are more.
+    pub fn get_storage_range(
+        &self,
+        address: Address,
+        start: Option<U256>,
+        limit: u64,
+    ) -> Result<(Option<U256>, Vec<(U256, U256)>), Error> {
+        let limit = limit.min(MAX_STORAGE_RANGE_LIMIT) as usize;
+        let mut slots = Vec::with_capacity(limit.min(1000));
+
+        let txn = self.read_txn()?;
+        let inner = &self.inner;
+
+        let mut iter = inner.storage.iter(&txn)?;
+        let address = AddressWrapper(address);
+        let location = &StorageEntryWrapper(start.unwrap_or_default(), U256::ZERO);
+
+        let Some(first) = iter.move_on_key_dup(&address, location)? else {
+            return Ok((None, slots));
+        };
+
+        // The cursor is positioned on the first slot, the following items continue from there
+        for item in std::iter::once(Ok(first)).chain(iter) {
+            let (key, entry) = item?;
+            if key.0 != address.0 {
+                break;
+            }
+
+            if entry.1.is_zero() {
+                continue;
+            }
+
+            if slots.len() == limit {
+                return Ok((Some(entry.0), slots));
+            }
+
+            slots.push((entry.0, entry.1));
+        }
+
+        Ok((None, slots))
+    }
+
     pub fn get_legacy_cold_wallets(
         &self,
-        offset: u64,
+        cursor: Option<Bytes>,
         limit: u64,
-    ) -> Result<(Option<u64>, Vec<LegacyColdWallet>), Error> {
-        let tx_env = self.env.read_txn()?;
-        let iter = self
-            .inner
-            .borrow()
+    ) -> Result<(Option<Bytes>, Vec<LegacyColdWallet>), Error> {
+        let tx_env = self.read_txn()?;
+        let inner = &self.inner;
+
+        let start = cursor_start::<LegacyAddressWrapper>(&cursor, LegacyAddress::len_bytes())?;
+        let iter = inner
             .legacy_cold_wallets
-            .iter(&tx_env)?
-            .skip(offset as usize);
+            .range(&tx_env, &(start, Bound::Unbounded))?;
 
-        self.get_items(
+        self.get_items::<LegacyAddressWrapper, _, _, _>(
             iter,
-            |item| match item {
-                Some(item) => {
-                    let (_, legacy_cold_wallet) = item?;
-                    Ok(Some(legacy_cold_wallet))
-                }
-                None => Ok(None),
-            },
-            offset,
+            |_, legacy_cold_wallet| Ok(legacy_cold_wallet),
             limit,
         )
     }
 
     pub fn get_receipts(
         &self,
-        offset: u64,
+        cursor: Option<Bytes>,
         limit: u64,
-    ) -> Result<(Option<u64>, Vec<(u64, Vec<(B256, TxReceipt)>)>), Error> {
-        let tx_env = self.env.read_txn()?;
-        let iter = self
-            .inner
-            .borrow()
-            .commits
-            .iter(&tx_env)?
-            .skip(offset as usize);
+    ) -> Result<(Option<Bytes>, Vec<(u64, Vec<(B256, TxReceipt)>)>), Error> {
+        let tx_env = self.read_txn()?;
+        let inner = &self.inner;
+
+        // The commits of blocks whose receipts got pruned are kept without receipts, they are
+        // skipped instead of being returned as blocks without transactions
+        let start = cursor_start::<HeedBlockNumber>(&cursor, size_of::<u64>())?;
+        let start = match pruning::read_pruned_range(&tx_env, inner, PrunedTable::Receipts)? {
+            Some(pruned) if !matches!(start, Bound::Excluded(start) if start >= pruned.last) => {
+                Bound::Excluded(pruned.last)
+            }
+            _ => start,
+        };
+        let iter = inner.commits.range(&tx_env, &(start, Bound::Unbounded))?;
 
-        self.get_items(
+        self.get_items::<HeedBlockNumber, _, _, _>(
             iter,
-            |item| match item {
-                Some(item) => {
-                    let (block_number, CommitReceipts { tx_receipts, .. }) = item?;
-                    Ok(Some((block_number, tx_receipts.into_iter().collect())))
-                }
-                None => Ok(None),
-            },
-            offset,
+            |block_number, CommitReceipts { tx_receipts, .. }| Ok((*block_number, tx_receipts)),
             limit,
         )
     }
@@ -539,25 +927,48 @@ impl PersistentDB {
         &self,
         block_number: u64,
         tx_hash: B256,
-    ) -> Result<Option<TxReceipt>, Error> {
-        let tx_env = self.env.read_txn()?;
+    ) -> Result<Retained<TxReceipt>, Error> {
+        let tx_env = self.read_txn()?;
+        let inner = &self.inner;
 
-        let commits = self.inner.borrow().commits.get(&tx_env, &block_number)?;
+        let commits = inner.commits.get(&tx_env, &block_number)?;
 
-        Ok(match commits {
-            Some(inner) => inner.tx_receipts.get(&tx_hash).cloned(),
-            None => None,
-        })
+        if let Some(receipt) = commits.and_then(|mut inner| inner.take_receipt(&tx_hash)) {
+            return Ok(Retained::Present(receipt));
+        }
+
+        retained_or_absent(&tx_env, inner, PrunedTable::Receipts, block_number)
+    }
+
+    // Looks up a receipt by transaction hash alone. Pruned receipts are removed from the index
+    // and thus not found.
+    pub fn get_receipt_by_hash(&self, tx_hash: B256) -> Result<Option<IndexedReceipt>, Error> {
+        let tx_env = self.read_txn()?;
+        let inner = &self.inner;
+
+        let Some(location) = inner
+            .receipt_locations
+            .get(&tx_env, &HashWrapper(tx_hash))?
+        else {
+            return Ok(None);
+        };
+
+        let receipt = inner
+            .commits
+            .get(&tx_env, &location.block_number)?
+            .and_then(|mut commit| commit.take_receipt(&tx_hash));
+
+        Ok(receipt.map(|receipt| IndexedReceipt { receipt, location }))
     }
 
     pub fn get_historical_account_info(
-        &mut self,
+        &self,
         block_number: u64,
         address: Address,
     ) -> Result<(Option<AccountInfo>, bool), Error> {
-        match self.inner.borrow().accounts_history {
+        match self.inner.accounts_history {
             Some(db) => {
-                let tx_env = self.env.read_txn()?;
+                let tx_env = self.read_txn()?;
 
                 match self.accounts_history.as_ref() {
                     Some(accounts_history) => {
@@ -588,85 +999,222 @@ impl PersistentDB {
         }
     }
 
+    pub fn get_historical_storage(
+        &self,
+        block_number: u64,
+        address: Address,
+        slot: U256,
+    ) -> Result<(Option<U256>, bool), Error> {
+        let (Some(db), Some(storage_history)) =
+            (self.inner.storage_history, self.storage_history.as_ref())
+        else {
+            return Ok((None, false));
+        };
+
+        let tx_env = self.read_txn()?;
+        storage_history.get_by_block_and_slot(&tx_env, &db, block_number, &address, &slot)
+    }
+
+    // Returns a read-only view of the state at `block_number`, which must be within the history window.
+    pub fn historical_state(&self, block_number: u64) -> Result<HistoricalState<'_>, Error> {
+        let txn = self.read_txn()?;
+        let inner = &self.inner;
+
+        let latest_block_number = inner
+            .commits
+            .remap_data_type::<heed::types::DecodeIgnore>()
+            .last(&txn)?
+            .map(|(block_number, _)| block_number);
+
+        match latest_block_number {
+            Some(latest_block_number) if block_number == latest_block_number => {
+                Ok(HistoricalState::new(self, block_number, true))
+            }
+            Some(latest_block_number) if block_number < latest_block_number => {
+                let oldest_block_number = match (inner.accounts_history, inner.storage_history) {
+                    (Some(accounts_history), Some(_)) => accounts_history
+                        .remap_data_type::<heed::types::DecodeIgnore>()
+                        .first(&txn)?
+                        .map(|(block_number, _)| block_number),
+                    _ => None,
+                };
+
+                match oldest_block_number {
+                    Some(oldest_block_number) if oldest_block_number <= block_number => {
+                        Ok(HistoricalState::new(self, block_number, false))
+                    }
+                    _ => Err(Error::HistoryUnavailable(block_number)),
+                }
+            }
+            _ => Err(Error::HistoryUnavailable(block_number)),
+        }
+    }
+
+    // Generates `eth_getProof` style proofs for an account and its storage slots. The trie only
+    // reflects the current state, thus only proofs against the latest committed block are served.
+    pub fn get_proof(
+        &self,
+        address: Address,
+        slots: &[U256],
+        block_number: u64,
+    ) -> Result<AccountProof, Error> {
+        let txn = self.read_txn()?;
+
+        let Some(trie) = &self.inner.trie else {
+            return Err(Error::ProofUnavailable);
+        };
+
+        let latest_block_number = match trie.parent(&txn)? {
+            Some((block_number, _)) => block_number,
+            None => 0,
+        };
+        if block_number != latest_block_number {
+            return Err(Error::HistoryUnavailable(block_number));
+        }
+
+        trie.proof(&txn, address, slots)
+    }
+
     pub fn get_legacy_attributes(
-        &mut self,
+        &self,
         address: Address,
     ) -> Result<Option<LegacyAccountAttributes>, Error> {
-        let tx_env = self.env.read_txn()?;
+        let tx_env = self.read_txn()?;
         Ok(self
             .inner
-            .borrow()
             .legacy_attributes
             .get(&tx_env, &AddressWrapper(address))?)
     }
 
     pub fn get_legacy_cold_wallet(
-        &mut self,
+        &self,
         address: LegacyAddress,
     ) -> Result<Option<LegacyColdWallet>, Error> {
-        let tx_env = self.env.read_txn()?;
+        let tx_env = self.read_txn()?;
         Ok(self
             .inner
-            .borrow()
             .legacy_cold_wallets
             .get(&tx_env, &LegacyAddressWrapper(address))?)
     }
 
     pub fn resize(&self) -> Result<(), Error> {
-        let info = self.env.info();
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+
+        self.resize_to(next_map_size(self.env.info().map_size))
+    }
 
-        let current_map_size = info.map_size;
+    // Grows the map until at least `free_size` bytes are left on top of the pages in use, e.g. to
+    // let commits continue during a backup, which holds off resizes. Read-only databases are left
+    // as they are.
+    pub(crate) fn reserve_map(&self, free_size: usize) -> Result<(), Error> {
+        if self.read_only {
+            return Ok(());
+        }
+
+        let used_size = self.env.non_free_pages_size()? as usize;
+        let map_size = next_map_size(used_size + free_size);
+
+        if self.env.info().map_size >= map_size {
+            return Ok(());
+        }
 
-        let next_map_size = next_map_size(current_map_size);
+        self.resize_to(map_size)
+    }
+
+    fn resize_to(&self, map_size: usize) -> Result<(), Error> {
+        let current_map_size = self.env.info().map_size;
 
         self.logger.log(
             LogLevel::Info,
-            format!("resizing db {} -> {}", current_map_size, next_map_size),
+            format!("resizing db {} -> {}", current_map_size, map_size),
         );
 
-        unsafe { self.env.resize(next_map_size)? };
+        self.resize_gate
+            .exclusive(|| unsafe { self.env.resize(map_size) })?;
+        self.resize_count.fetch_add(1, atomic::Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    // Adopts the map size after the database got resized by another process, which is noticed
+    // once a new transaction is started.
+    pub(crate) fn adopt_map_size(&self) -> Result<(), Error> {
+        self.logger
+            .log(LogLevel::Info, "adopting resized db map".to_owned());
+
+        self.resize_gate
+            .exclusive(|| unsafe { self.env.resize(0) })?;
+        self.resize_count.fetch_add(1, atomic::Ordering::Relaxed);
 
         Ok(())
     }
 
-    fn get_items<T, I, F>(
+    pub(crate) fn write_txn(&self) -> Result<heed::RwTxn<'_>, Error> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+
+        Ok(self.env.write_txn()?)
+    }
+
+    pub(crate) fn account_or_default(
+        &self,
+        address: Address,
+        account: Option<AccountInfo>,
+    ) -> AccountInfo {
+        match account {
+            Some(account) => account,
+            None => match &self.genesis_info() {
+                Some(genesis) if genesis.account == address => revm::state::AccountInfo {
+                    balance: genesis.initial_supply,
+                    ..Default::default()
+                },
+                _ => AccountInfo::default(),
+            },
+        }
+    }
+
+    // Reads up to `limit` items and returns the encoded key of the last one as cursor if there
+    // might be more to read.
+    pub(crate) fn get_items<KC, K, V, T>(
         &self,
-        mut iter: impl Iterator<Item = I>,
-        map: F,
-        offset: u64,
+        iter: impl Iterator<Item = heed::Result<(K, V)>>,
+        map: impl Fn(&K, V) -> Result<T, Error>,
         limit: u64,
-    ) -> Result<(Option<u64>, Vec<T>), Error>
+    ) -> Result<(Option<Bytes>, Vec<T>), Error>
     where
-        F: Fn(Option<I>) -> Result<Option<T>, Error>,
+        KC: for<'a> heed::BytesEncode<'a, EItem = K>,
     {
         let limit = limit as usize;
         let mut items = Vec::with_capacity(limit);
+        let mut last_key = None;
 
-        loop {
-            let item = map(iter.next())?;
-            let Some(item) = item else {
-                break;
-            };
-
-            items.push(item);
-
-            if items.len() == limit {
-                break;
-            }
+        for item in iter.take(limit) {
+            let (key, value) = item?;
+            items.push(map(&key, value)?);
+            last_key = Some(key);
         }
 
-        let next = if items.len() == limit {
-            // return next offset as there might be more to read
-            Some(offset + items.len() as u64)
-        } else {
-            None
+        let next = match last_key {
+            Some(key) if items.len() == limit => Some(Bytes::copy_from_slice(
+                &KC::bytes_encode(&key).map_err(heed::Error::Encoding)?,
+            )),
+            _ => None,
         };
 
         Ok((next, items))
     }
 }
 
-const MAP_SIZE_UNIT: usize = 1024 * 1024 * 1024; // 1 GB
+// Upper bound of slots returned by a single `get_storage_range` call.
+pub const MAX_STORAGE_RANGE_LIMIT: u64 = 10_000;
+
+// Number of most recent blocks that can be unwound unless configured otherwise.
+pub const DEFAULT_UNWIND_DEPTH: u64 = 1_000;
+
+pub(crate) const MAP_SIZE_UNIT: usize = 1024 * 1024 * 1024; // 1 GB
 fn next_map_size(map_size: usize) -> usize {
     map_size / MAP_SIZE_UNIT * MAP_SIZE_UNIT + MAP_SIZE_UNIT
 }
@@ -695,26 +1243,17 @@ impl DatabaseRef for PersistentDB {
     type Error = Error;
 
     fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
-        let txn = self.env.read_txn()?;
-        let inner = self.inner.borrow();
+        let txn = self.read_txn()?;
+        let inner = &self.inner;
 
-        let basic = match inner.accounts.get(&txn, &AddressWrapper(address))? {
-            Some(account) => account,
-            None => match &self.genesis_info {
-                Some(genesis) if genesis.account == address => revm::state::AccountInfo {
-                    balance: genesis.initial_supply,
-                    ..Default::default()
-                },
-                _ => AccountInfo::default(),
-            },
-        };
+        let basic = inner.accounts.get(&txn, &AddressWrapper(address))?;
 
-        Ok(basic.into())
+        Ok(self.account_or_default(address, basic).into())
     }
 
     fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
-        let txn = self.env.read_txn()?;
-        let inner = self.inner.borrow();
+        let txn = self.read_txn()?;
+        let inner = &self.inner;
 
         let contract = match inner.contracts.get(&txn, &HashWrapper(code_hash))? {
             Some(contract) => contract,
@@ -725,8 +1264,8 @@ impl DatabaseRef for PersistentDB {
     }
 
     fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
-        let txn = self.env.read_txn()?;
-        let inner = self.inner.borrow_mut();
+        let txn = self.read_txn()?;
+        let inner = &self.inner;
 
         let mut iter = inner.storage.iter(&txn)?;
         let location = &StorageEntryWrapper(index, U256::ZERO);
@@ -737,288 +1276,322 @@ impl DatabaseRef for PersistentDB {
         }
     }
 
-    fn block_hash_ref(&self, _number: u64) -> Result<B256, Self::Error> {
-        todo!()
-    }
-}
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        let txn = self.read_txn()?;
+        let inner = &self.inner;
 
-impl PersistentDB {
-    pub fn commit(
-        &self,
-        state_commit: &mut StateCommit,
-        commit_data: &Option<CommitData>,
-    ) -> Result<(), Error> {
-        let StateCommit {
-            key,
-            change_set,
-            results,
-        } = state_commit;
-
-        match self.commit_to_db(key, change_set, commit_data, results) {
-            Ok(_) => return Ok(()),
-            Err(err) => match &err {
-                Error::Heed(heed_err) => match heed_err {
-                    heed::Error::Mdb(mdb_err) => match mdb_err {
-                        heed::MdbError::MapFull => return Err(Error::DbFull),
-                        _ => return Err(err),
-                    },
-                    _ => return Err(err),
-                },
-                _ => return Err(err),
-            },
+        let Some((latest_block_number, _)) = inner.blocks_number_hash.last(&txn)? else {
+            return Ok(B256::ZERO);
+        };
+
+        // Only the hashes of the most recent 256 committed blocks are accessible
+        if number > latest_block_number || latest_block_number - number >= BLOCK_HASH_HISTORY {
+            return Ok(B256::ZERO);
         }
+
+        Ok(match inner.blocks_number_hash.get(&txn, &number)? {
+            Some(block_hash) => block_hash.0,
+            None => B256::ZERO,
+        })
     }
+}
 
-    fn commit_to_db(
+impl PersistentDB {
+    // Runs `apply` in a write transaction, which is committed if it succeeds and aborted otherwise.
+    pub fn write<T>(
         &self,
-        key: &CommitKey,
-        change_set: &mut state_changes::StateChangeset,
-        commit_data: &Option<CommitData>,
-        results: &BTreeMap<B256, ExecutionResult>,
-    ) -> Result<(), Error> {
-        assert!(!self.is_block_committed(key.0));
-
-        let mut rwtxn = self.env.write_txn()?;
-        let inner = self.inner.borrow_mut();
-
-        let mut apply_changes = |rwtxn: &mut heed::RwTxn| -> Result<(), Error> {
-            let state_changes::StateChangeset {
-                accounts,
-                storage,
-                contracts,
-                legacy_attributes,
-                legacy_cold_wallets,
-                merged_legacy_cold_wallets,
-            } = change_set;
-
-            accounts.par_sort_by_key(|a| a.0);
-            contracts.par_sort_by_key(|a| a.0);
-            storage.par_sort_by_key(|a| a.address);
-
-            // Update accounts
-            for (address, account) in accounts.iter() {
-                let address = AddressWrapper(*address);
-
-                if let Some(account) = account {
-                    inner.accounts.put(rwtxn, &address, &account)?;
-                } else {
-                    inner.accounts.delete(rwtxn, &address)?;
-                }
-            }
+        apply: impl FnOnce(&mut PersistentTxn<'_>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut txn = PersistentTxn {
+            db: self,
+            rwtxn: self.write_txn()?,
+            revert: BlockRevert::default(),
+        };
 
-            // Update account history
-            if let Some(db) = &inner.accounts_history {
-                self.accounts_history
-                    .as_ref()
-                    .expect("accounts history")
-                    .insert(
-                        rwtxn,
-                        db,
-                        key.0,
-                        accounts
-                            .iter()
-                            .map(|a| (a.0, a.1.clone().unwrap_or_default()))
-                            .collect(),
-                    )?;
+        let result = match apply(&mut txn) {
+            Ok(result) => result,
+            Err(err) => {
+                txn.rwtxn.abort();
+                return Err(map_db_full(err));
             }
+        };
 
-            // Update legacy attributes
-            for (address, legacy_attributes) in legacy_attributes.into_iter() {
-                let address = AddressWrapper(*address);
-                inner
-                    .legacy_attributes
-                    .put(rwtxn, &address, legacy_attributes)?;
-            }
+        txn.rwtxn.commit().map_err(|err| map_db_full(err.into()))?;
 
-            // Update legacy cold wallets
-            for (address, legacy_cold_wallets) in legacy_cold_wallets.into_iter() {
-                let address = LegacyAddressWrapper(*address);
-                inner
-                    .legacy_cold_wallets
-                    .put(rwtxn, &address, legacy_cold_wallets)?;
-            }
+        Ok(result)
+    }
 
-            // Update contracts
-            for (hash, bytecode) in contracts.into_iter() {
-                inner.contracts.put(rwtxn, &HashWrapper(*hash), &bytecode)?;
-            }
+    // Calculates the Merkle Patricia Trie state root of the commit without writing anything. The
+    // trie updates are kept with the commit, which writes them instead of calculating them again.
+    pub fn calculate_trie_root(&self, state_commit: &mut StateCommit) -> Result<B256, Error> {
+        let Some(trie) = &self.inner.trie else {
+            return Err(Error::StateRootModeMismatch(self.state_root_mode));
+        };
 
-            // Update storage
-            for state_changes::StorageChangeset {
-                address,
-                wipe_storage,
-                storage,
-            } in storage.into_iter()
-            {
-                let mut iter = inner.storage.iter_mut(rwtxn)?;
-                let address = AddressWrapper(*address);
+        let txn = self.read_txn()?;
+        let updates =
+            trie.calculate(&txn, TrieChanges::from_change_set(&state_commit.change_set))?;
+        let state_root = updates.state_root;
 
-                if iter.move_on_key(&address)? {
-                    if *wipe_storage {
-                        // wipe all existing storage for address
-                        unsafe { iter.del_current_with_flags(heed::DeleteFlags::NO_DUP_DATA)? };
-                    }
-                }
+        state_commit.trie_updates.replace(updates);
 
-                storage.par_sort_unstable_by_key(|a| a.0);
-
-                for value in storage.into_iter() {
-                    let new_storage_value = &StorageEntryWrapper(value.0, value.1.present_value());
-
-                    if let Some((_, iter_value)) =
-                        iter.move_on_key_dup(&address, &new_storage_value)?
-                    {
-                        // overwrite or delete if key matches
-                        if iter_value.0 == value.0 {
-                            if value.1.present_value().is_zero() {
-                                let success = unsafe { iter.del_current()? };
-                                assert!(success);
-                            } else if value.1.present_value() != iter_value.1 {
-                                unsafe {
-                                    // overwrite current position of cursor
-                                    let success = iter.put_current(&address, &new_storage_value)?;
-                                    assert!(success);
-                                }
-                            } else {
-                                // skip unchanged storage
-                            }
+        Ok(state_root)
+    }
 
-                            // cursor matched existing entry, move on to next
-                            continue;
-                        }
-                    }
+    // Restores the state as it was right after committing `block_number` by applying the reverse
+    // changesets of all newer commits. Either all commits are unwound or none.
+    pub fn unwind_to(&self, block_number: u64) -> Result<(), Error> {
+        let mut rwtxn = self.write_txn()?;
+        let inner = &self.inner;
 
-                    if value.1.present_value() != U256::ZERO {
-                        unsafe {
-                            iter.put_current_with_options(
-                                heed::PutFlags::NO_DUP_DATA,
-                                &address,
-                                &new_storage_value,
-                            )?;
+        let apply_reverts = |rwtxn: &mut heed::RwTxn| -> Result<(), Error> {
+            let block_numbers = inner
+                .commits
+                .remap_data_type::<heed::types::DecodeIgnore>()
+                .rev_range(rwtxn, &(block_number + 1..))?
+                .map(|item| item.map(|(block_number, _)| block_number))
+                .collect::<Result<Vec<u64>, heed::Error>>()?;
+
+            let mut trie_accounts = BTreeSet::new();
+            // Restored slots by address, `None` if the storage got wiped
+            let mut trie_storage = BTreeMap::<Address, Option<BTreeMap<U256, U256>>>::new();
+
+            for block_number in block_numbers {
+                let Some(revert) = inner.reverts.get(rwtxn, &block_number)? else {
+                    return Err(Error::MissingRevert(block_number));
+                };
+
+                if inner.trie.is_some() {
+                    trie_accounts.extend(revert.accounts.keys().copied());
+
+                    // reverts are applied from the newest to the oldest, thus the value of the
+                    // oldest revert is the one restored
+                    for storage in revert.storage.iter() {
+                        let slots = trie_storage
+                            .entry(storage.address)
+                            .or_insert_with(|| Some(BTreeMap::new()));
+
+                        match slots {
+                            Some(slots) if !storage.wiped => {
+                                slots.extend(storage.slots.iter().copied())
+                            }
+                            _ => *slots = None,
                         }
                     }
                 }
-            }
 
-            // Mark legacy cold wallets as merged in storage and migrate legacy attributes
-            for (address, legacy) in merged_legacy_cold_wallets {
                 self.logger.log(
                     LogLevel::Info,
-                    format!(
-                        "Merging legacy cold wallet '{}' with '{}'",
-                        legacy.1, address
-                    ),
+                    format!("Unwinding commit of block {}", block_number),
                 );
 
-                let key = &LegacyAddressWrapper(legacy.1);
-                let mut legacy_cold_wallet = inner
-                    .legacy_cold_wallets
-                    .get(&rwtxn, key)?
-                    .expect("legacy cold wallet to be found");
-
-                assert!(legacy_cold_wallet.merge_info.is_none());
-                legacy_cold_wallet.merge_info.replace((legacy.0, *address));
-
-                inner
-                    .legacy_cold_wallets
-                    .put(rwtxn, key, &legacy_cold_wallet)?;
+                self.apply_revert(rwtxn, inner, block_number, revert)?;
+            }
 
-                // The legacy balance has already been applied to the `PendingCommit`,
-                // thus only the legacy attributes need to be moved to a different storage.
-                inner.legacy_attributes.put(
+            // Wiped storage tries are rebuilt since the reverts do not contain the slots written
+            // after the wipe
+            if let Some(trie) = &inner.trie {
+                trie.update(
                     rwtxn,
-                    &AddressWrapper(*address),
-                    &legacy_cold_wallet.legacy_attributes,
+                    inner,
+                    trie_accounts,
+                    trie_storage
+                        .into_iter()
+                        .map(|(address, slots)| match slots {
+                            Some(slots) => (
+                                address,
+                                StorageTrieUpdate::Slots(slots.into_iter().collect()),
+                            ),
+                            None => (address, StorageTrieUpdate::Rebuild),
+                        })
+                        .collect(),
                 )?;
             }
 
-            // ========================================
-            //
-            if let Some(commit_data) = commit_data {
-                let CommitData {
-                    commit_round,
-                    block_hash,
-                    block,
-                    proof,
-                    transaction_hashes,
-                    transactions,
-                } = commit_data;
-
-                // Update blocks
-                inner.blocks.put(rwtxn, &key.0, &block)?;
-                inner
-                    .blocks_hash_number
-                    .put(rwtxn, &HashWrapper(*block_hash), &key.0)?;
+            pruning::unwind_pruned_ranges(rwtxn, inner, block_number)?;
+
+            Ok(())
+        };
+
+        if let Err(err) = apply_reverts(&mut rwtxn) {
+            rwtxn.abort();
+            return Err(err);
+        }
+
+        rwtxn.commit()?;
+
+        Ok(())
+    }
+
+    fn apply_revert(
+        &self,
+        rwtxn: &mut heed::RwTxn,
+        inner: &InnerStorage,
+        block_number: u64,
+        revert: BlockRevert,
+    ) -> Result<(), Error> {
+        let BlockRevert {
+            accounts,
+            storage,
+            contracts,
+            legacy_attributes,
+            legacy_cold_wallets,
+            block,
+        } = revert;
+
+        // Restore accounts
+        for (address, account) in accounts {
+            let current = inner.accounts.get(rwtxn, &AddressWrapper(address))?;
+            inner
+                .balance_index
+                .update(rwtxn, address, current.as_ref(), account.as_ref())?;
+            inner.code_references.update(
+                rwtxn,
+                block_number,
+                current.as_ref(),
+                account.as_ref(),
+            )?;
+
+            let address = AddressWrapper(address);
+
+            match account {
+                Some(account) => inner.accounts.put(rwtxn, &address, &account)?,
+                None => {
+                    inner.accounts.delete(rwtxn, &address)?;
+                }
+            }
+        }
 
-                // Update proofs
-                inner.proofs.put(rwtxn, &key.0, proof)?;
+        // Restore storage
+        for StorageRevert {
+            address,
+            wiped,
+            slots,
+        } in storage
+        {
+            let address = AddressWrapper(address);
+            let mut iter = inner.storage.iter_mut(rwtxn)?;
+
+            if wiped && iter.move_on_key(&address)? {
+                // drop storage written after the wipe, the previous storage is restored below
+                unsafe { iter.del_current_with_flags(heed::DeleteFlags::NO_DUP_DATA)? };
+            }
+
+            for (slot, value) in slots {
+                write_storage_slot(&mut iter, &address, slot, value)?;
+            }
+        }
 
-                // Update transactions
-                for (sequence, transaction) in transactions.iter().enumerate() {
-                    let key = format!("{}-{}", key.0, sequence);
-                    let transaction_hash = transaction_hashes[sequence];
+        // Remove contracts which did not exist before
+        for hash in contracts {
+            inner.contracts.delete(rwtxn, &HashWrapper(hash))?;
+            inner.code_references.delete_code(rwtxn, hash)?;
+        }
 
+        // Restore legacy attributes
+        for (address, legacy_attributes) in legacy_attributes {
+            let address = AddressWrapper(address);
+
+            match legacy_attributes {
+                Some(legacy_attributes) => {
                     inner
-                        .transactions_hash_key
-                        .put(rwtxn, &HashWrapper(transaction_hash), &key)?;
+                        .legacy_attributes
+                        .put(rwtxn, &address, &legacy_attributes)?
+                }
+                None => {
+                    inner.legacy_attributes.delete(rwtxn, &address)?;
+                }
+            }
+        }
 
+        // Restore legacy cold wallets, this also resets the merge info
+        for (address, legacy_cold_wallet) in legacy_cold_wallets {
+            let address = LegacyAddressWrapper(address);
+
+            match legacy_cold_wallet {
+                Some(legacy_cold_wallet) => {
                     inner
-                        .transactions
-                        .put(rwtxn, &StringWrapper(key), transaction)?;
+                        .legacy_cold_wallets
+                        .put(rwtxn, &address, &legacy_cold_wallet)?
+                }
+                None => {
+                    inner.legacy_cold_wallets.delete(rwtxn, &address)?;
                 }
+            }
+        }
+
+        // Remove block data
+        if let Some(BlockDataRevert {
+            block_hash,
+            transaction_hashes,
+            total_round,
+        }) = block
+        {
+            inner.blocks.delete(rwtxn, &block_number)?;
+            inner
+                .blocks_hash_number
+                .delete(rwtxn, &HashWrapper(block_hash))?;
+            inner.blocks_number_hash.delete(rwtxn, &block_number)?;
+            inner.proofs.delete(rwtxn, &block_number)?;
 
-                // Update state
-                let total_round_key = StaticStringWrapper("total_round");
-                let current_total_round =
-                    read_total_round(inner.state.get(rwtxn, &total_round_key)?);
+            for (sequence, transaction_hash) in transaction_hashes.into_iter().enumerate() {
+                let key = format!("{}-{}", block_number, sequence);
 
-                inner.state.put(
-                    rwtxn,
-                    &total_round_key,
-                    &Bytes::from_iter((current_total_round + commit_round + 1).to_le_bytes()),
-                )?;
+                inner
+                    .transactions_hash_key
+                    .delete(rwtxn, &HashWrapper(transaction_hash))?;
+                inner.transactions.delete(rwtxn, &StringWrapper(key))?;
             }
-            // ========================================
 
-            // Finalize commit
-            let mut tx_receipts = HashMap::new();
-            for (k, result) in results {
-                tx_receipts.insert(k.clone(), map_execution_result(result.clone()));
+            let total_round_key = StaticStringWrapper("total_round");
+            match total_round {
+                Some(total_round) => inner.state.put(rwtxn, &total_round_key, &total_round)?,
+                None => {
+                    inner.state.delete(rwtxn, &total_round_key)?;
+                }
             }
+        }
 
-            inner.commits.put(
-                rwtxn,
-                &key.0,
-                &CommitReceipts {
-                    accounts_hash: state_root::calculate_accounts_hash(&change_set)?,
-                    contracts_hash: state_root::calculate_contracts_hash(&change_set)?,
-                    storage_hash: state_root::calculate_storage_hash(&change_set)?,
-                    tx_receipts,
-                },
-            )?;
+        if let Some(db) = &inner.accounts_history {
+            db.delete(rwtxn, &block_number)?;
+        }
 
-            Ok(())
-        };
+        if let Some(db) = &inner.storage_history {
+            db.delete(rwtxn, &block_number)?;
+        }
 
-        if let Err(err) = apply_changes(&mut rwtxn) {
-            rwtxn.abort();
-            return Err(err.into());
+        if let Some(trie) = &inner.trie {
+            trie.state_roots.delete(rwtxn, &block_number)?;
         }
 
-        rwtxn.commit()?;
+        if let Some(commit) = inner.commits.get(rwtxn, &block_number)? {
+            for tx_hash in commit.tx_hashes() {
+                inner
+                    .receipt_locations
+                    .delete(rwtxn, &HashWrapper(*tx_hash))?;
+            }
+
+            inner
+                .log_index
+                .delete(rwtxn, block_number, &commit.tx_receipts)?;
+            deployment::delete(rwtxn, inner, block_number, &commit.tx_receipts)?;
+        }
+
+        inner.commits.delete(rwtxn, &block_number)?;
+        inner.reverts.delete(rwtxn, &block_number)?;
 
         Ok(())
     }
 
     pub fn is_block_committed(&self, block_number: u64) -> bool {
-        let env = self.env.clone();
-        let rtxn = env.read_txn().expect("read");
-        let inner = self.inner.borrow();
+        let inner = &self.inner;
 
-        inner
-            .commits
-            .get(&rtxn, &block_number)
-            .is_ok_and(|v| v.is_some())
+        self.read_txn().is_ok_and(|rtxn| {
+            inner
+                .commits
+                .get(&rtxn, &block_number)
+                .is_ok_and(|v| v.is_some())
+        })
     }
 
     pub fn get_committed_receipt(
@@ -1026,12 +1599,11 @@ impl PersistentDB {
         block_number: u64,
         tx_hash: B256,
     ) -> Result<(bool, Option<TxReceipt>), Error> {
-        let env = self.env.clone();
-        let rtxn = env.read_txn().expect("read");
-        let inner = self.inner.borrow();
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
 
         match inner.commits.get(&rtxn, &block_number)? {
-            Some(receipts) => Ok((true, receipts.tx_receipts.get(&tx_hash).cloned())),
+            Some(mut receipts) => Ok((true, receipts.take_receipt(&tx_hash))),
             None => Ok((false, None)),
         }
     }
@@ -1040,89 +1612,578 @@ impl PersistentDB {
         &self,
         block_number: u64,
     ) -> Result<Option<(B256, B256, B256)>, Error> {
-        let env = self.env.clone();
-        let rtxn = env.read_txn().expect("read");
-        let inner = self.inner.borrow();
-
-        match inner.commits.get(&rtxn, &block_number)? {
-            Some(receipts) => Ok(Some((
-                receipts.accounts_hash,
-                receipts.contracts_hash,
-                receipts.storage_hash,
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        match read_commit_metadata(&rtxn, inner, block_number)? {
+            Some(metadata) => Ok(Some((
+                metadata.accounts_hash,
+                metadata.contracts_hash,
+                metadata.storage_hash,
             ))),
             None => Ok(None),
         }
     }
 
     pub fn is_empty(&self) -> Result<bool, Error> {
-        let env = self.env.clone();
-        let rtxn = env.read_txn().expect("read");
-        let inner = self.inner.borrow();
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
 
         Ok(inner.blocks.is_empty(&rtxn)?)
     }
 
     pub fn get_state(&self) -> Result<(u64, u64), Error> {
-        let env = self.env.clone();
-        let rtxn = env.read_txn().expect("read");
-        let inner = self.inner.borrow();
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        let total_round = read_total_round(
+            inner
+                .state
+                .get(&rtxn, &StaticStringWrapper("total_round"))?,
+        );
+
+        let block_number = match inner.blocks.last(&rtxn)? {
+            Some((block_number, _)) => block_number,
+            None => 0,
+        };
+
+        Ok((block_number, total_round))
+    }
+
+    pub fn get_state_root(&self, block_number: u64) -> Result<Option<B256>, Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        Ok(read_commit_metadata(&rtxn, inner, block_number)?
+            .and_then(|metadata| metadata.state_root))
+    }
+
+    pub fn get_logs_bloom(&self, block_number: u64) -> Result<Option<Bloom>, Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        Ok(read_commit_metadata(&rtxn, inner, block_number)?
+            .and_then(|metadata| metadata.logs_bloom))
+    }
+
+    pub fn get_block_header_bytes(&self, block_number: u64) -> Result<Retained<Bytes>, Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        match inner.blocks.get(&rtxn, &block_number)? {
+            Some(block) => Ok(Retained::Present(block)),
+            None => retained_or_absent(&rtxn, inner, PrunedTable::Blocks, block_number),
+        }
+    }
+
+    pub fn get_block_hash(&self, block_number: u64) -> Result<Option<B256>, Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        Ok(inner
+            .blocks_number_hash
+            .get(&rtxn, &block_number)?
+            .map(|block_hash| block_hash.0))
+    }
+
+    pub fn get_block_number_by_hash(&self, block_hash: B256) -> Result<Option<u64>, Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        Ok(inner
+            .blocks_hash_number
+            .get(&rtxn, &HashWrapper(block_hash))?)
+    }
+
+    pub fn get_proof_bytes(&self, block_number: u64) -> Result<Retained<Bytes>, Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        match inner.proofs.get(&rtxn, &block_number)? {
+            Some(proof) => Ok(Retained::Present(proof)),
+            None => retained_or_absent(&rtxn, inner, PrunedTable::Proofs, block_number),
+        }
+    }
+
+    pub fn get_transaction_bytes(&self, key: String) -> Result<Retained<Bytes>, Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        // keys are formatted as "{block_number}-{sequence}"
+        let block_number = key
+            .split_once('-')
+            .and_then(|(block_number, _)| block_number.parse::<u64>().ok());
+
+        match inner.transactions.get(&rtxn, &StringWrapper(key))? {
+            Some(transaction) => Ok(Retained::Present(transaction)),
+            None => match block_number {
+                Some(block_number) => {
+                    retained_or_absent(&rtxn, inner, PrunedTable::Transactions, block_number)
+                }
+                None => Ok(Retained::Absent),
+            },
+        }
+    }
+
+    // The hash index of pruned transactions is pruned as well, hence their hashes are unknown.
+    pub fn get_transaction_key_by_hash(&self, tx_hash: B256) -> Result<Option<String>, Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        Ok(inner
+            .transactions_hash_key
+            .get(&rtxn, &HashWrapper(tx_hash))?)
+    }
+}
+
+// Write transaction of `PersistentDB`, see `PersistentDB::write`. Besides the tables it maintains
+// the secondary indexes and records the reverse changeset, which allows unwinding the commit.
+pub struct PersistentTxn<'a> {
+    db: &'a PersistentDB,
+    rwtxn: heed::RwTxn<'a>,
+    revert: BlockRevert,
+}
+
+impl StorageTxn for PersistentTxn<'_> {
+    fn put_account(
+        &mut self,
+        block_number: u64,
+        address: Address,
+        account: Option<&AccountInfo>,
+    ) -> Result<(), Error> {
+        let inner = &self.db.inner;
+        let rwtxn = &mut self.rwtxn;
+
+        let previous = inner.accounts.get(rwtxn, &AddressWrapper(address))?;
+        inner
+            .balance_index
+            .update(rwtxn, address, previous.as_ref(), account)?;
+        inner
+            .code_references
+            .update(rwtxn, block_number, previous.as_ref(), account)?;
+        self.revert.accounts.insert(address, previous);
+
+        let address = AddressWrapper(address);
+
+        if let Some(account) = account {
+            inner.accounts.put(rwtxn, &address, account)?;
+        } else {
+            inner.accounts.delete(rwtxn, &address)?;
+        }
+
+        Ok(())
+    }
+
+    fn put_contract(
+        &mut self,
+        block_number: u64,
+        code_hash: B256,
+        bytecode: &Bytecode,
+    ) -> Result<(), Error> {
+        let inner = &self.db.inner;
+        let rwtxn = &mut self.rwtxn;
+
+        if inner
+            .contracts
+            .get(rwtxn, &HashWrapper(code_hash))?
+            .is_none()
+        {
+            self.revert.contracts.push(code_hash);
+        }
+
+        inner
+            .contracts
+            .put(rwtxn, &HashWrapper(code_hash), bytecode)?;
+        inner
+            .code_references
+            .insert_code(rwtxn, block_number, code_hash)?;
+
+        Ok(())
+    }
+
+    fn write_storage(
+        &mut self,
+        address: Address,
+        wipe: bool,
+        slots: &[(U256, U256)],
+    ) -> Result<Vec<U256>, Error> {
+        let inner = &self.db.inner;
+        let rwtxn = &mut self.rwtxn;
+        let address = AddressWrapper(address);
+
+        let mut storage_revert = StorageRevert {
+            address: address.0,
+            wiped: wipe,
+            slots: Vec::with_capacity(slots.len()),
+        };
+
+        if wipe {
+            // keep the entire storage of the address before wiping it
+            if let Some(iter) = inner.storage.get_duplicates(rwtxn, &address)? {
+                for item in iter {
+                    let (_, value) = item?;
+                    storage_revert.slots.push((value.0, value.1));
+                }
+            }
+        }
+
+        let mut iter = inner.storage.iter_mut(rwtxn)?;
+
+        if iter.move_on_key(&address)? {
+            if wipe {
+                // wipe all existing storage for address
+                unsafe { iter.del_current_with_flags(heed::DeleteFlags::NO_DUP_DATA)? };
+            }
+        }
+
+        for (slot, value) in slots.iter() {
+            let previous_value = write_storage_slot(&mut iter, &address, *slot, *value)?;
+
+            if !wipe {
+                storage_revert.slots.push((*slot, previous_value));
+            }
+        }
+
+        drop(iter);
+
+        let wiped_slots = match wipe {
+            true => storage_revert.slots.iter().map(|(slot, _)| *slot).collect(),
+            false => Vec::new(),
+        };
+
+        self.revert.storage.push(storage_revert);
+
+        Ok(wiped_slots)
+    }
+
+    fn get_legacy_attributes(
+        &self,
+        address: Address,
+    ) -> Result<Option<LegacyAccountAttributes>, Error> {
+        Ok(self
+            .db
+            .inner
+            .legacy_attributes
+            .get(&self.rwtxn, &AddressWrapper(address))?)
+    }
+
+    fn put_legacy_attributes(
+        &mut self,
+        address: Address,
+        legacy_attributes: &LegacyAccountAttributes,
+    ) -> Result<(), Error> {
+        if !self.revert.legacy_attributes.contains_key(&address) {
+            let previous = self.get_legacy_attributes(address)?;
+            self.revert.legacy_attributes.insert(address, previous);
+        }
+
+        self.db.inner.legacy_attributes.put(
+            &mut self.rwtxn,
+            &AddressWrapper(address),
+            legacy_attributes,
+        )?;
+
+        Ok(())
+    }
+
+    fn get_legacy_cold_wallet(
+        &self,
+        address: LegacyAddress,
+    ) -> Result<Option<LegacyColdWallet>, Error> {
+        Ok(self
+            .db
+            .inner
+            .legacy_cold_wallets
+            .get(&self.rwtxn, &LegacyAddressWrapper(address))?)
+    }
+
+    fn put_legacy_cold_wallet(
+        &mut self,
+        address: LegacyAddress,
+        legacy_cold_wallet: &LegacyColdWallet,
+    ) -> Result<(), Error> {
+        if !self.revert.legacy_cold_wallets.contains_key(&address) {
+            let previous = self.get_legacy_cold_wallet(address)?;
+            self.revert.legacy_cold_wallets.insert(address, previous);
+        }
+
+        self.db.inner.legacy_cold_wallets.put(
+            &mut self.rwtxn,
+            &LegacyAddressWrapper(address),
+            legacy_cold_wallet,
+        )?;
+
+        Ok(())
+    }
+
+    fn keeps_history(&self) -> bool {
+        self.db.inner.accounts_history.is_some()
+    }
+
+    fn insert_account_history(
+        &mut self,
+        block_number: u64,
+        accounts: Vec<(Address, AccountInfo)>,
+    ) -> Result<(), Error> {
+        let Some(database) = &self.db.inner.accounts_history else {
+            return Ok(());
+        };
+
+        self.db
+            .accounts_history
+            .as_ref()
+            .expect("accounts history")
+            .insert(&mut self.rwtxn, database, block_number, accounts)
+    }
+
+    fn insert_storage_history(
+        &mut self,
+        block_number: u64,
+        storage: BTreeMap<Address, BTreeMap<U256, U256>>,
+    ) -> Result<(), Error> {
+        let Some(database) = &self.db.inner.storage_history else {
+            return Ok(());
+        };
+
+        self.db
+            .storage_history
+            .as_ref()
+            .expect("storage history")
+            .insert(&mut self.rwtxn, database, block_number, storage)
+    }
+
+    fn put_block(&mut self, block_number: u64, commit_data: &CommitData) -> Result<(), Error> {
+        let inner = &self.db.inner;
+        let rwtxn = &mut self.rwtxn;
+
+        let CommitData {
+            commit_round,
+            block_hash,
+            block,
+            proof,
+            transaction_hashes,
+            transactions,
+        } = commit_data;
+
+        // Update blocks
+        inner.blocks.put(rwtxn, &block_number, block)?;
+        inner
+            .blocks_hash_number
+            .put(rwtxn, &HashWrapper(*block_hash), &block_number)?;
+        inner
+            .blocks_number_hash
+            .put(rwtxn, &block_number, &HashWrapper(*block_hash))?;
+
+        // Update proofs
+        inner.proofs.put(rwtxn, &block_number, proof)?;
+
+        // Update transactions
+        for (sequence, transaction) in transactions.iter().enumerate() {
+            let key = format!("{}-{}", block_number, sequence);
+            let transaction_hash = transaction_hashes[sequence];
+
+            inner
+                .transactions_hash_key
+                .put(rwtxn, &HashWrapper(transaction_hash), &key)?;
+
+            inner
+                .transactions
+                .put(rwtxn, &StringWrapper(key), transaction)?;
+        }
+
+        // Update state
+        let total_round_key = StaticStringWrapper("total_round");
+        let previous_total_round = inner.state.get(rwtxn, &total_round_key)?;
+        let current_total_round = read_total_round(previous_total_round.clone());
+
+        inner.state.put(
+            rwtxn,
+            &total_round_key,
+            &Bytes::from_iter((current_total_round + commit_round + 1).to_le_bytes()),
+        )?;
+
+        self.revert.block.replace(BlockDataRevert {
+            block_hash: *block_hash,
+            transaction_hashes: transaction_hashes.clone(),
+            total_round: previous_total_round,
+        });
+
+        Ok(())
+    }
+
+    fn update_trie(&mut self, state_commit: &StateCommit) -> Result<Option<B256>, Error> {
+        let Some(trie) = &self.db.inner.trie else {
+            return Ok(None);
+        };
+
+        // Reuse the trie updates of the calculated root if they are still based on the latest
+        // commit. The trie tables are only written below, thus the calculation is not affected by
+        // the state written before.
+        let calculated;
+        let updates = match &state_commit.trie_updates {
+            Some(updates) if updates.parent == trie.parent(&self.rwtxn)? => updates,
+            _ => {
+                calculated = trie.calculate(
+                    &self.rwtxn,
+                    TrieChanges::from_change_set(&state_commit.change_set),
+                )?;
+                &calculated
+            }
+        };
+
+        trie.write(&mut self.rwtxn, updates)?;
+        trie.state_roots.put(
+            &mut self.rwtxn,
+            &state_commit.key.0,
+            &HashWrapper(updates.state_root),
+        )?;
+
+        Ok(Some(updates.state_root))
+    }
+
+    fn put_commit(
+        &mut self,
+        key: &CommitKey,
+        commit_data: &Option<CommitData>,
+        receipts: CommitReceipts,
+    ) -> Result<(), Error> {
+        let db = self.db;
+        let inner = &db.inner;
+        let rwtxn = &mut self.rwtxn;
+
+        // Index receipts by transaction hash
+        let (block_hash, transaction_indices) = match commit_data {
+            Some(commit_data) => (
+                commit_data.block_hash,
+                commit_data
+                    .transaction_hashes
+                    .iter()
+                    .enumerate()
+                    .map(|(index, hash)| (*hash, index as u64))
+                    .collect(),
+            ),
+            None => (key.2, HashMap::new()),
+        };
+
+        for (tx_hash, _) in receipts.tx_receipts.iter() {
+            inner.receipt_locations.put(
+                rwtxn,
+                &HashWrapper(*tx_hash),
+                &ReceiptLocation {
+                    block_number: key.0,
+                    block_hash,
+                    transaction_index: transaction_indices.get(tx_hash).copied(),
+                },
+            )?;
+        }
+
+        inner
+            .log_index
+            .insert(rwtxn, key.0, &receipts.tx_receipts)?;
+        deployment::insert(rwtxn, inner, key.0, &receipts.tx_receipts)?;
+
+        inner.commits.put(rwtxn, &key.0, &receipts)?;
+
+        db.prune(rwtxn, inner, key.0)?;
+
+        // Keep reverse changeset to allow unwinding the commit
+        inner.reverts.put(rwtxn, &key.0, &self.revert)?;
 
-        let total_round = read_total_round(
+        if let Some(oldest_block_number) = key.0.checked_sub(db.unwind_depth) {
             inner
-                .state
-                .get(&rtxn, &StaticStringWrapper("total_round"))?,
-        );
-
-        let block_number = match inner.blocks.last(&rtxn)? {
-            Some((block_number, _)) => block_number,
-            None => 0,
-        };
+                .reverts
+                .delete_range(rwtxn, &(..=oldest_block_number))?;
+        }
 
-        Ok((block_number, total_round))
+        Ok(())
     }
+}
 
-    pub fn get_block_header_bytes(&self, block_number: u64) -> Result<Option<Bytes>, Error> {
-        let env = self.env.clone();
-        let rtxn = env.read_txn().expect("read");
-        let inner = self.inner.borrow();
+// Writes a single storage slot at the position of the cursor, a zero value removes the slot.
+// Returns the previous value of the slot.
+fn write_storage_slot(
+    iter: &mut heed::RwIter<'_, AddressWrapper, StorageEntryWrapper>,
+    address: &AddressWrapper,
+    slot: U256,
+    value: U256,
+) -> Result<U256, Error> {
+    let new_storage_value = &StorageEntryWrapper(slot, value);
+
+    if let Some((_, iter_value)) = iter.move_on_key_dup(address, new_storage_value)? {
+        // overwrite or delete if key matches
+        if iter_value.0 == slot {
+            if value.is_zero() {
+                let success = unsafe { iter.del_current()? };
+                assert!(success);
+            } else if value != iter_value.1 {
+                unsafe {
+                    // overwrite current position of cursor
+                    let success = iter.put_current(address, new_storage_value)?;
+                    assert!(success);
+                }
+            } else {
+                // skip unchanged storage
+            }
 
-        Ok(inner.blocks.get(&rtxn, &block_number)?)
+            // cursor matched existing entry
+            return Ok(iter_value.1);
+        }
     }
 
-    pub fn get_block_number_by_hash(&self, block_hash: B256) -> Result<Option<u64>, Error> {
-        let env = self.env.clone();
-        let rtxn = env.read_txn().expect("read");
-        let inner = self.inner.borrow();
-
-        Ok(inner
-            .blocks_hash_number
-            .get(&rtxn, &HashWrapper(block_hash))?)
+    if !value.is_zero() {
+        unsafe {
+            iter.put_current_with_options(heed::PutFlags::NO_DUP_DATA, address, new_storage_value)?;
+        }
     }
 
-    pub fn get_proof_bytes(&self, block_number: u64) -> Result<Option<Bytes>, Error> {
-        let env = self.env.clone();
-        let rtxn = env.read_txn().expect("read");
-        let inner = self.inner.borrow();
+    Ok(U256::ZERO)
+}
 
-        Ok(inner.proofs.get(&rtxn, &block_number)?)
+fn retained_or_absent<T>(
+    txn: &heed::RoTxn,
+    inner: &InnerStorage,
+    table: PrunedTable,
+    block_number: u64,
+) -> Result<Retained<T>, Error> {
+    match pruning::is_pruned(txn, inner, table, block_number)? {
+        true => Ok(Retained::Pruned),
+        false => Ok(Retained::Absent),
     }
+}
 
-    pub fn get_transaction_bytes(&self, key: String) -> Result<Option<Bytes>, Error> {
-        let env = self.env.clone();
-        let rtxn = env.read_txn().expect("read");
-        let inner = self.inner.borrow();
-
-        Ok(inner.transactions.get(&rtxn, &StringWrapper(key))?)
+// Pages resume right after the key of the last item read, so keys inserted or removed by
+// concurrent commits do not shift the following pages. The cursor is opaque to callers.
+pub(crate) fn cursor_start<'a, KC>(
+    cursor: &'a Option<Bytes>,
+    key_len: usize,
+) -> Result<Bound<KC::DItem>, Error>
+where
+    KC: heed::BytesDecode<'a>,
+{
+    match cursor {
+        None => Ok(Bound::Unbounded),
+        Some(cursor) if cursor.len() == key_len => Ok(Bound::Excluded(
+            KC::bytes_decode(cursor).map_err(heed::Error::Decoding)?,
+        )),
+        Some(cursor) => Err(Error::InvalidCursor(cursor.clone())),
     }
+}
 
-    pub fn get_transaction_key_by_hash(&self, tx_hash: B256) -> Result<Option<String>, Error> {
-        let env = self.env.clone();
-        let rtxn = env.read_txn().expect("read");
-        let inner = self.inner.borrow();
+pub(crate) fn read_commit_metadata(
+    txn: &heed::RoTxn,
+    inner: &InnerStorage,
+    block_number: u64,
+) -> Result<Option<CommitMetadata>, Error> {
+    Ok(inner
+        .commits
+        .remap_data_type::<heed::types::SerdeBincode<CommitMetadata>>()
+        .get(txn, &block_number)?)
+}
 
-        Ok(inner
-            .transactions_hash_key
-            .get(&rtxn, &HashWrapper(tx_hash))?)
+pub(crate) fn map_db_full(err: Error) -> Error {
+    match err {
+        Error::Heed(heed::Error::Mdb(heed::MdbError::MapFull)) => Error::DbFull,
+        err => err,
     }
 }
 
@@ -1449,6 +2510,81 @@ fn test_storage_overwrite() {
     assert_eq!(account_storage, U256::from(2));
 }
 
+#[test]
+fn test_storage_range() {
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+
+    let before = address!("1000000000000000000000000000000000000001");
+    let contract = address!("2000000000000000000000000000000000000002");
+    let after = address!("3000000000000000000000000000000000000003");
+
+    crate::state_commit::commit_to_db(
+        &mut db,
+        crate::trie::pending_commit(
+            1,
+            vec![
+                (before, 0, vec![(1, 0, 1)]),
+                (
+                    contract,
+                    0,
+                    vec![(1, 0, 10), (2, 0, 20), (3, 0, 30), (256, 0, 40)],
+                ),
+                (after, 0, vec![(1, 0, 1)]),
+            ],
+        ),
+        None,
+    )
+    .expect("ok");
+
+    // zeroed slots are removed
+    crate::state_commit::commit_to_db(
+        &mut db,
+        crate::trie::pending_commit(2, vec![(contract, 0, vec![(2, 20, 0)])]),
+        None,
+    )
+    .expect("ok");
+
+    let read = |limit: u64| {
+        let mut slots = Vec::new();
+        let mut start = None;
+
+        loop {
+            let (next, page) = db.get_storage_range(contract, start, limit).unwrap();
+            assert!(page.len() <= limit as usize);
+            slots.extend(
+                page.into_iter()
+                    .map(|(slot, value)| (slot.to::<u64>(), value.to::<u64>())),
+            );
+
+            match next {
+                Some(next) => start = Some(next),
+                None => break slots,
+            }
+        }
+    };
+
+    // slots are ordered by their little endian bytes
+    let expected = vec![(256, 40), (1, 10), (3, 30)];
+    assert_eq!(read(10), expected);
+    assert_eq!(read(1), expected);
+
+    assert_eq!(
+        db.get_storage_range(contract, Some(U256::from(1)), 1)
+            .unwrap(),
+        (Some(U256::from(3)), vec![(U256::from(1), U256::from(10))])
+    );
+    assert_eq!(
+        db.get_storage_range(Address::ZERO, None, 10).unwrap(),
+        (None, vec![])
+    );
+}
+
 #[test]
 fn test_next_map_size() {
     let input = vec![0, 1, 2, 3, 4];
@@ -1503,7 +2639,7 @@ fn test_resize_on_commit() {
         .tempdir()
         .unwrap();
 
-    let mut env_builder = EnvOpenOptions::new();
+    let mut env_builder = EnvOpenOptions::new().read_txn_without_tls();
     env_builder.max_dbs(PersistentDB::MAX_DBS);
     env_builder.map_size(4096 * 10); // start with very small (few kB)
 
@@ -1571,7 +2707,6 @@ fn test_read_accounts() {
 
         for (index, address) in addresses.iter().enumerate() {
             db.inner
-                .borrow_mut()
                 .accounts
                 .put(
                     &mut wtxn,
@@ -1588,15 +2723,29 @@ fn test_read_accounts() {
     }
 
     const LIMIT: u64 = 5;
-    let mut offset = 0;
+    let mut cursor = None;
 
-    let mut read = 0;
+    let mut read = vec![];
 
     loop {
-        let (next, accounts) = db.get_accounts(offset, LIMIT).unwrap();
+        let (next, accounts) = db.get_accounts(cursor, LIMIT).unwrap();
         for account in accounts {
             println!("{:?}", account);
-            read += 1;
+            read.push(account.address);
+        }
+
+        if read.len() == LIMIT as usize {
+            // accounts inserted before the cursor do not shift the following pages
+            let mut wtxn = db.env.write_txn().unwrap();
+            db.inner
+                .accounts
+                .put(
+                    &mut wtxn,
+                    &AddressWrapper(Address::ZERO),
+                    &Default::default(),
+                )
+                .unwrap();
+            wtxn.commit().unwrap();
         }
 
         if next.is_none() {
@@ -1605,7 +2754,7 @@ fn test_read_accounts() {
 
         match next {
             Some(next) => {
-                offset = next;
+                cursor = Some(next);
             }
             None => {
                 break;
@@ -1613,7 +2762,12 @@ fn test_read_accounts() {
         }
     }
 
-    assert_eq!(read, addresses.len());
+    assert_eq!(read, addresses);
+
+    assert!(matches!(
+        db.get_accounts(Some(Bytes::from_static(&[1, 2, 3])), LIMIT),
+        Err(Error::InvalidCursor(_))
+    ));
 }
 
 #[test]
@@ -1644,17 +2798,16 @@ fn test_read_receipts() {
         for i in 0..target_block {
             let block_number = (i + 1) as u64;
 
-            let receipts: HashMap<B256, TxReceipt> = HashMap::from([
+            let receipts: Vec<(B256, TxReceipt)> = vec![
                 (random_b256(block_number, 0), TxReceipt::default()),
                 (random_b256(block_number, 1), TxReceipt::default()),
                 (random_b256(block_number, 2), TxReceipt::default()),
                 (random_b256(block_number, 3), TxReceipt::default()),
-            ]);
+            ];
 
             total_receipts += receipts.len();
 
             db.inner
-                .borrow_mut()
                 .commits
                 .put(
                     &mut wtxn,
@@ -1670,13 +2823,13 @@ fn test_read_receipts() {
     }
 
     const LIMIT: u64 = 7;
-    let mut offset = 0;
+    let mut cursor = None;
 
     let mut read_block_number = 0;
     let mut read_receipts = 0;
 
     loop {
-        let (next, items) = db.get_receipts(offset, LIMIT).unwrap();
+        let (next, items) = db.get_receipts(cursor, LIMIT).unwrap();
         for (block_number, receipts) in items {
             read_block_number = block_number;
             read_receipts += receipts.len();
@@ -1688,7 +2841,7 @@ fn test_read_receipts() {
 
         match next {
             Some(next) => {
-                offset = next;
+                cursor = Some(next);
             }
             None => {
                 break;
@@ -1699,3 +2852,394 @@ fn test_read_receipts() {
     assert_eq!(read_block_number, target_block);
     assert_eq!(read_receipts, total_receipts);
 }
+
+#[test]
+fn test_commit_metadata() {
+    use revm::context::result::{ExecutionResult, Output, SuccessReason};
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+
+    let log = Log::new_unchecked(
+        address!("bd6f65c58a46427af4b257cbe231d0ed69ed5508"),
+        vec![B256::repeat_byte(1)],
+        Bytes::new(),
+    );
+
+    let mut commit = crate::trie::pending_commit(1, vec![]);
+    commit.results.insert(
+        B256::repeat_byte(2),
+        crate::receipt::executed_transaction(
+            0,
+            ExecutionResult::Success {
+                reason: SuccessReason::Stop,
+                gas_used: 21000,
+                gas_refunded: 0,
+                logs: vec![log.clone()],
+                output: Output::Call(Bytes::new()),
+            },
+        ),
+    );
+
+    let logs_bloom = crate::logs_bloom::calculate(&commit).expect("ok");
+    let state_root = crate::state_root::calculate(&mut db, &mut commit, B256::ZERO).expect("ok");
+    assert_eq!(db.get_state_root(1).unwrap(), None);
+    assert_eq!(db.get_logs_bloom(1).unwrap(), None);
+
+    crate::state_commit::commit_to_db(&mut db, commit, None).expect("ok");
+    assert_eq!(db.get_state_root(1).unwrap(), Some(state_root));
+    assert_eq!(db.get_logs_bloom(1).unwrap(), Some(logs_bloom));
+    assert_eq!(
+        db.get_logs_bloom(1).unwrap(),
+        Some(Bloom::from_iter(std::iter::once(&log)))
+    );
+
+    // without calculating the state root upfront it remains unknown
+    crate::state_commit::commit_to_db(&mut db, crate::trie::pending_commit(2, vec![]), None)
+        .expect("ok");
+    assert_eq!(db.get_state_root(2).unwrap(), None);
+    assert_eq!(db.get_logs_bloom(2).unwrap(), Some(Bloom::ZERO));
+}
+
+#[test]
+fn test_block_hash_window() {
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+
+    // Nothing committed yet
+    assert_eq!(db.block_hash_ref(0).expect("block hash"), B256::ZERO);
+
+    let block_hash = |block_number: u64| B256::from(U256::from(block_number + 1));
+
+    let latest_block_number = 299;
+    for block_number in 0..=latest_block_number {
+        crate::state_commit::commit_to_db(
+            &mut db,
+            PendingCommit {
+                key: CommitKey(block_number, 0, block_hash(block_number)),
+                ..Default::default()
+            },
+            Some(CommitData {
+                block_hash: block_hash(block_number),
+                ..Default::default()
+            }),
+        )
+        .expect("ok");
+    }
+
+    // Latest committed block
+    assert_eq!(
+        db.block_hash_ref(latest_block_number).expect("block hash"),
+        block_hash(latest_block_number)
+    );
+
+    // Oldest block inside the window
+    let oldest_block_number = latest_block_number - (BLOCK_HASH_HISTORY - 1);
+    assert_eq!(
+        db.block_hash_ref(oldest_block_number).expect("block hash"),
+        block_hash(oldest_block_number)
+    );
+
+    // Just outside the window
+    assert_eq!(
+        db.block_hash_ref(oldest_block_number - 1)
+            .expect("block hash"),
+        B256::ZERO
+    );
+    assert_eq!(db.block_hash_ref(0).expect("block hash"), B256::ZERO);
+
+    // Not committed yet
+    assert_eq!(
+        db.block_hash_ref(latest_block_number + 1)
+            .expect("block hash"),
+        B256::ZERO
+    );
+}
+
+#[test]
+fn test_unwind() {
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+
+    let address = address!("bd6f65c58a46427af4b257cbe231d0ed69ed5508");
+
+    fn commit(
+        db: &mut PersistentDB,
+        block_number: u64,
+        commit_round: u64,
+        address: Address,
+        balance: u64,
+        slots: Vec<(u64, u64, u64)>,
+    ) {
+        let mut storage = HashMap::new();
+        for (slot, previous_value, value) in slots {
+            storage.insert(
+                U256::from(slot),
+                revm::database::states::StorageSlot::new_changed(
+                    U256::from(previous_value),
+                    U256::from(value),
+                ),
+            );
+        }
+
+        let mut state = HashMap::new();
+        state.insert(
+            address,
+            revm::database::TransitionAccount {
+                status: revm::database::AccountStatus::InMemoryChange,
+                info: Some(AccountInfo {
+                    balance: U256::from(balance),
+                    ..Default::default()
+                }),
+                previous_status: revm::database::AccountStatus::Loaded,
+                previous_info: None,
+                storage,
+                storage_was_destroyed: false,
+            },
+        );
+
+        let block_hash = B256::from(U256::from(1000 + block_number));
+        let transaction_hash = B256::from(U256::from(2000 + block_number));
+
+        crate::state_commit::commit_to_db(
+            db,
+            PendingCommit {
+                key: CommitKey(block_number, commit_round, block_hash),
+                transitions: TransitionState { transitions: state },
+                results: BTreeMap::from([(
+                    transaction_hash,
+                    crate::receipt::executed_transaction(
+                        0,
+                        revm::context::result::ExecutionResult::Success {
+                            reason: revm::context::result::SuccessReason::Stop,
+                            gas_used: 21000,
+                            gas_refunded: 0,
+                            logs: vec![],
+                            output: revm::context::result::Output::Call(Bytes::new()),
+                        },
+                    ),
+                )]),
+                ..Default::default()
+            },
+            Some(CommitData {
+                commit_round,
+                block_hash,
+                transaction_hashes: vec![transaction_hash],
+                transactions: vec![Bytes::from_static(&[1, 2, 3])],
+                ..Default::default()
+            }),
+        )
+        .expect("ok");
+    }
+
+    commit(&mut db, 1, 0, address, 100, vec![(1, 0, 10), (2, 0, 20)]);
+    commit(
+        &mut db,
+        2,
+        2,
+        address,
+        50,
+        vec![(1, 10, 11), (2, 20, 0), (3, 0, 30)],
+    );
+
+    assert_eq!(db.basic(address).unwrap().unwrap().balance, U256::from(50));
+    assert_eq!(db.get_state().unwrap(), (2, 4));
+
+    // Unwind block 2
+    db.unwind_to(1).expect("unwind");
+
+    assert_eq!(db.basic(address).unwrap().unwrap().balance, U256::from(100));
+    assert_eq!(db.storage(address, U256::from(1)).unwrap(), U256::from(10));
+    assert_eq!(db.storage(address, U256::from(2)).unwrap(), U256::from(20));
+    assert_eq!(db.storage(address, U256::from(3)).unwrap(), U256::ZERO);
+
+    assert_eq!(db.get_state().unwrap(), (1, 1));
+    assert!(db.is_block_committed(1));
+    assert!(!db.is_block_committed(2));
+    assert_eq!(
+        db.get_block_number_by_hash(B256::from(U256::from(1002)))
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        db.get_transaction_key_by_hash(B256::from(U256::from(2002)))
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        db.get_transaction_key_by_hash(B256::from(U256::from(2001)))
+            .unwrap(),
+        Some("1-0".into())
+    );
+    assert!(
+        db.get_receipt_by_hash(B256::from(U256::from(2002)))
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(
+        db.get_receipt_by_hash(B256::from(U256::from(2001)))
+            .unwrap()
+            .map(|receipt| receipt.location),
+        Some(ReceiptLocation {
+            block_number: 1,
+            block_hash: B256::from(U256::from(1001)),
+            transaction_index: Some(0),
+        })
+    );
+
+    // Block 2 can be committed again
+    commit(&mut db, 2, 0, address, 75, vec![(3, 0, 30)]);
+    assert_eq!(db.get_state().unwrap(), (2, 2));
+
+    // Unwind everything
+    db.unwind_to(0).expect("unwind");
+
+    assert_eq!(db.basic(address).unwrap().unwrap().balance, U256::ZERO);
+    assert_eq!(db.storage(address, U256::from(1)).unwrap(), U256::ZERO);
+    assert_eq!(db.storage(address, U256::from(3)).unwrap(), U256::ZERO);
+    assert!(db.is_empty().unwrap());
+    assert_eq!(db.get_state().unwrap(), (0, 0));
+}
+
+#[test]
+fn test_unwind_depth() {
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf()).with_unwind_depth(2))
+            .expect("database");
+
+    for block_number in 1..=5 {
+        crate::state_commit::commit_to_db(
+            &mut db,
+            PendingCommit {
+                key: CommitKey(block_number, 0, B256::ZERO),
+                ..Default::default()
+            },
+            None,
+        )
+        .expect("ok");
+    }
+
+    // Only the last two blocks can be unwound
+    assert!(matches!(db.unwind_to(2), Err(Error::MissingRevert(3))));
+    assert!(db.is_block_committed(5));
+
+    db.unwind_to(3).expect("unwind");
+    assert!(db.is_block_committed(3));
+    assert!(!db.is_block_committed(4));
+
+    // The reverse changesets are bounded by default and cover the history
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+    let db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+    assert_eq!(db.unwind_depth, DEFAULT_UNWIND_DEPTH);
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+    let db = PersistentDB::new(
+        PersistentDBOptions::new(path.path().to_path_buf())
+            .with_history_size(DEFAULT_UNWIND_DEPTH * 2),
+    )
+    .expect("database");
+    assert_eq!(db.unwind_depth, DEFAULT_UNWIND_DEPTH * 2);
+}
+
+#[test]
+fn test_read_only() {
+    use crate::trie::pending_commit;
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    // Read-only databases are never created
+    assert!(
+        PersistentDB::new(PersistentDBOptions::new(path.path().join("missing")).with_read_only())
+            .is_err()
+    );
+
+    let alice = address!("1000000000000000000000000000000000000001");
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+    crate::state_commit::commit_to_db(&mut db, pending_commit(1, vec![(alice, 100, vec![])]), None)
+        .expect("commit");
+
+    let mut reader =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf()).with_read_only())
+            .expect("database");
+
+    assert!(reader.is_block_committed(1));
+    assert_eq!(
+        reader.basic_ref(alice).unwrap().map(|info| info.balance),
+        Some(U256::from(100))
+    );
+    assert_eq!(reader.schema_version().unwrap(), SCHEMA_VERSION);
+
+    // Commits of the writer are visible to the reader
+    crate::state_commit::commit_to_db(&mut db, pending_commit(2, vec![(alice, 200, vec![])]), None)
+        .expect("commit");
+    assert_eq!(
+        reader.basic_ref(alice).unwrap().map(|info| info.balance),
+        Some(U256::from(200))
+    );
+
+    assert!(matches!(
+        crate::state_commit::commit_to_db(
+            &mut reader,
+            pending_commit(3, vec![(alice, 300, vec![])]),
+            None
+        ),
+        Err(Error::ReadOnly)
+    ));
+    assert!(matches!(reader.unwind_to(1), Err(Error::ReadOnly)));
+    assert!(matches!(reader.resize(), Err(Error::ReadOnly)));
+    assert!(matches!(reader.collect_garbage(), Err(Error::ReadOnly)));
+    assert!(reader.is_block_committed(2));
+}
+
+#[test]
+fn test_new_ephemeral() {
+    if !Path::new(SHARED_MEMORY_PATH).is_dir() {
+        return;
+    }
+
+    let db = PersistentDB::new_ephemeral(PersistentDBOptions::default()).expect("database");
+    let path = db.temp_dir.as_ref().unwrap().path().to_path_buf();
+    assert!(path.starts_with(SHARED_MEMORY_PATH));
+    assert!(path.join("evm.mdb").exists());
+
+    // The directory lives as long as the read handles of the database
+    let read_handle = db.read_handles().open().unwrap();
+    drop(db);
+    assert_eq!(read_handle.get_state().unwrap(), (0, 0));
+    assert!(path.exists());
+
+    drop(read_handle);
+    assert!(!path.exists());
+}
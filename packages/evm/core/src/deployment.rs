@@ -0,0 +1,221 @@
+use std::{ops::Bound, str::FromStr};
+
+use heed::RwTxn;
+use revm::primitives::{Address, B256, Bytes, KECCAK_EMPTY};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::{AddressWrapper, Error, InnerStorage, PersistentDB, cursor_start},
+    receipt::TxReceipt,
+};
+
+// Creation info of a contract deployed by a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractDeployment {
+    // Unknown for receipts committed before the sender got stored
+    pub deployer: Option<Address>,
+    pub transaction_hash: B256,
+    pub block_number: u64,
+    pub code_hash: B256,
+}
+
+// Contracts created by successful deployment transactions, contracts created by other contracts
+// are not part of the receipts.
+fn deployed_contracts(
+    tx_receipts: &[(B256, TxReceipt)],
+) -> impl Iterator<Item = (&B256, &TxReceipt, Address)> {
+    tx_receipts.iter().filter_map(|(tx_hash, receipt)| {
+        let address = receipt.contract_address.as_deref()?;
+        Some((tx_hash, receipt, Address::from_str(address).ok()?))
+    })
+}
+
+// Registers the contracts deployed by the receipts committed in `block_number`. The code hash is
+// read from the accounts, which thus have to be updated beforehand.
+pub(crate) fn insert(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    block_number: u64,
+    tx_receipts: &[(B256, TxReceipt)],
+) -> Result<(), Error> {
+    for (tx_hash, receipt, address) in deployed_contracts(tx_receipts) {
+        let address = AddressWrapper(address);
+        let code_hash = inner
+            .accounts
+            .get(txn, &address)?
+            .map_or(KECCAK_EMPTY, |info| info.code_hash);
+
+        inner.deployments.put(
+            txn,
+            &address,
+            &ContractDeployment {
+                deployer: receipt.from,
+                transaction_hash: *tx_hash,
+                block_number,
+                code_hash,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+// Removes the contracts deployed by the receipts committed in `block_number`.
+pub(crate) fn delete(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    block_number: u64,
+    tx_receipts: &[(B256, TxReceipt)],
+) -> Result<(), Error> {
+    for (_, _, address) in deployed_contracts(tx_receipts) {
+        let address = AddressWrapper(address);
+
+        if inner
+            .deployments
+            .get(txn, &address)?
+            .is_some_and(|deployment| deployment.block_number == block_number)
+        {
+            inner.deployments.delete(txn, &address)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl PersistentDB {
+    pub fn get_contract_deployment(
+        &self,
+        address: Address,
+    ) -> Result<Option<ContractDeployment>, Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        Ok(inner.deployments.get(&rtxn, &AddressWrapper(address))?)
+    }
+
+    // Lists the deployed contracts in address order.
+    pub fn get_contract_deployments(
+        &self,
+        cursor: Option<Bytes>,
+        limit: u64,
+    ) -> Result<(Option<Bytes>, Vec<(Address, ContractDeployment)>), Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        let start = cursor_start::<AddressWrapper>(&cursor, Address::len_bytes())?;
+        let iter = inner.deployments.range(&rtxn, &(start, Bound::Unbounded))?;
+
+        self.get_items::<AddressWrapper, _, _, _>(
+            iter,
+            |address, deployment| Ok((address.0, deployment)),
+            limit,
+        )
+    }
+}
+
+#[test]
+fn test_contract_deployments() {
+    use revm::{
+        context::result::{ExecutionResult, Output, SuccessReason},
+        primitives::{address, keccak256},
+        state::Bytecode,
+    };
+
+    use crate::{
+        db::PersistentDBOptions, receipt::executed_transaction, state_commit::commit_to_db,
+        trie::pending_commit,
+    };
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+
+    let contract1 = address!("1000000000000000000000000000000000000001");
+    let contract2 = address!("2000000000000000000000000000000000000002");
+    let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x00]));
+    let code_hash = keccak256(code.original_byte_slice());
+
+    let deploy = |contract: Address| ExecutionResult::Success {
+        reason: SuccessReason::Return,
+        gas_used: 21000,
+        gas_refunded: 0,
+        logs: vec![],
+        output: Output::Create(Bytes::new(), Some(contract)),
+    };
+
+    for (block_number, contract) in [(1, contract1), (2, contract2)] {
+        let mut pending = pending_commit(block_number, vec![(contract, 0, vec![])]);
+        let info = pending
+            .transitions
+            .transitions
+            .get_mut(&contract)
+            .and_then(|transition| transition.info.as_mut())
+            .expect("contract");
+        info.code_hash = code_hash;
+        info.code = Some(code.clone());
+
+        pending.results.insert(
+            B256::repeat_byte(block_number as u8),
+            executed_transaction(0, deploy(contract)),
+        );
+        // Failed deployments are not registered
+        pending.results.insert(
+            B256::repeat_byte(0x10 + block_number as u8),
+            executed_transaction(
+                1,
+                ExecutionResult::Revert {
+                    gas_used: 21000,
+                    output: Bytes::new(),
+                },
+            ),
+        );
+
+        commit_to_db(&mut db, pending, None).expect("commit");
+    }
+
+    assert_eq!(
+        db.get_contract_deployment(contract2).unwrap(),
+        Some(ContractDeployment {
+            deployer: Some(Address::ZERO),
+            transaction_hash: B256::repeat_byte(2),
+            block_number: 2,
+            code_hash,
+        })
+    );
+    assert_eq!(db.get_contract_deployment(Address::ZERO).unwrap(), None);
+
+    let (next, deployments) = db.get_contract_deployments(None, 1).unwrap();
+    assert_eq!(deployments.len(), 1);
+    assert_eq!(deployments[0].0, contract1);
+    assert_eq!(deployments[0].1.block_number, 1);
+
+    let (next, deployments) = db.get_contract_deployments(next, 1).unwrap();
+    assert_eq!(deployments.len(), 1);
+    assert_eq!(deployments[0].0, contract2);
+
+    let (next, deployments) = db.get_contract_deployments(next, 1).unwrap();
+    assert!(next.is_none());
+    assert!(deployments.is_empty());
+
+    // Unwinding removes the deployments of the reverted blocks
+    db.unwind_to(1).expect("unwind");
+    assert_eq!(db.get_contract_deployment(contract2).unwrap(), None);
+
+    let (_, deployments) = db.get_contract_deployments(None, 10).unwrap();
+    assert_eq!(
+        deployments
+            .iter()
+            .map(|(address, _)| *address)
+            .collect::<Vec<_>>(),
+        vec![contract1]
+    );
+
+    assert!(matches!(
+        db.get_contract_deployments(Some(Bytes::from_static(&[1])), 10),
+        Err(Error::InvalidCursor(_))
+    ));
+}
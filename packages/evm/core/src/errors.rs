@@ -0,0 +1,87 @@
+use alloy_sol_types::{SolError, sol};
+
+sol! {
+    // ConsensusV1
+    error AlreadyVoted();
+    error BellowMinValidators();
+    error BlsKeyAlreadyRegistered();
+    error BlsKeyIsInvalid();
+    error CallerIsNotValidator();
+    error ImportIsNotAllowed();
+    error InvalidFee();
+    error InvalidParameters();
+    error InvalidRange(uint256 min, uint256 max);
+    error MissingVote();
+    error NoActiveValidators();
+    error RefundFailed();
+    error ValidatorAlreadyRegistered();
+    error ValidatorAlreadyResigned();
+    error ValidatorNotRegistered();
+    error VoteResignedValidator();
+    error VoteSameValidator();
+    error VoteValidatorWithoutBlsPublicKey();
+
+    // MultiPaymentV1
+    error InvalidValue();
+    error RecipientsAndAmountsMismatch();
+
+    // UsernamesV1
+    error InvalidUsername();
+    error TakenUsername();
+    error UsernameNotRegistered();
+
+    // OpenZeppelin (proxy, ownership and initialization)
+    error AddressEmptyCode(address target);
+    error ERC1967InvalidImplementation(address implementation);
+    error ERC1967NonPayable();
+    error FailedCall();
+    error InvalidInitialization();
+    error NotInitializing();
+    error OwnableInvalidOwner(address owner);
+    error OwnableUnauthorizedAccount(address account);
+    error UUPSUnauthorizedCallContext();
+    error UUPSUnsupportedProxiableUUID(bytes32 slot);
+}
+
+macro_rules! known_errors {
+    ($($error:ident),* $(,)?) => {
+        &[$(($error::SELECTOR, $error::SIGNATURE)),*]
+    };
+}
+
+// Selector and signature of the custom errors raised by the Mainsail contracts.
+pub(crate) const KNOWN_ERRORS: &[([u8; 4], &str)] = known_errors![
+    AlreadyVoted,
+    BellowMinValidators,
+    BlsKeyAlreadyRegistered,
+    BlsKeyIsInvalid,
+    CallerIsNotValidator,
+    ImportIsNotAllowed,
+    InvalidFee,
+    InvalidParameters,
+    InvalidRange,
+    MissingVote,
+    NoActiveValidators,
+    RefundFailed,
+    ValidatorAlreadyRegistered,
+    ValidatorAlreadyResigned,
+    ValidatorNotRegistered,
+    VoteResignedValidator,
+    VoteSameValidator,
+    VoteValidatorWithoutBlsPublicKey,
+    InvalidValue,
+    RecipientsAndAmountsMismatch,
+    InvalidUsername,
+    TakenUsername,
+    UsernameNotRegistered,
+    AddressEmptyCode,
+    ERC1967InvalidImplementation,
+    ERC1967NonPayable,
+    FailedCall,
+    InvalidInitialization,
+    NotInitializing,
+    OwnableInvalidOwner,
+    OwnableUnauthorizedAccount,
+    UUPSUnauthorizedCallContext,
+    UUPSUnsupportedProxiableUUID,
+];
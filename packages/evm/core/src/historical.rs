@@ -2,11 +2,12 @@ use std::collections::BTreeMap;
 
 use heed::{RoTxn, RwTxn};
 use revm::{
-    primitives::{Address, B256, U256},
-    state::AccountInfo,
+    DatabaseRef,
+    primitives::{Address, B256, BLOCK_HASH_HISTORY, U256},
+    state::{AccountInfo, Bytecode},
 };
 
-use crate::db::Error;
+use crate::db::{Error, PersistentDB};
 
 #[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct HistoricalAccountData {
@@ -25,6 +26,7 @@ impl From<AccountInfo> for HistoricalAccountData {
     }
 }
 
+#[derive(Clone)]
 pub struct AccountHistory {
     capacity: u64,
 }
@@ -87,6 +89,259 @@ impl AccountHistory {
 
         Ok((None, missing_fallback))
     }
+
+    // Returns the first block after `block_number` which changed the account.
+    pub fn get_next_change_by_address(
+        &self,
+        txn: &RoTxn,
+        database: &heed::Database<
+            heed::types::U64<heed::byteorder::BigEndian>,
+            heed::types::SerdeBincode<BTreeMap<Address, HistoricalAccountData>>,
+        >,
+        block_number: u64,
+        address: &Address,
+    ) -> Result<Option<u64>, Error> {
+        let mut iter = database.range(txn, &(block_number + 1..))?;
+
+        while let Some((changed_at, history)) = iter.next().transpose()? {
+            if history.contains_key(address) {
+                return Ok(Some(changed_at));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[derive(Clone)]
+pub struct StorageHistory {
+    capacity: u64,
+}
+
+impl StorageHistory {
+    pub fn new(capacity: u64) -> Self {
+        Self { capacity }
+    }
+
+    pub fn insert(
+        &self,
+        txn: &mut RwTxn,
+        database: &heed::Database<
+            heed::types::U64<heed::byteorder::BigEndian>,
+            heed::types::SerdeBincode<BTreeMap<Address, BTreeMap<U256, U256>>>,
+        >,
+        block_number: u64,
+        storage: BTreeMap<Address, BTreeMap<U256, U256>>,
+    ) -> Result<(), Error> {
+        assert!(database.get(txn, &block_number)?.is_none());
+
+        let count = database.len(txn)?;
+        if count >= self.capacity {
+            // delete oldest entries
+            let range = ..=block_number.saturating_sub(self.capacity);
+            database.delete_range(txn, &range)?;
+        }
+
+        database.put(txn, &block_number, &storage)?;
+
+        Ok(())
+    }
+
+    pub fn get_by_block_and_slot(
+        &self,
+        txn: &RoTxn,
+        database: &heed::Database<
+            heed::types::U64<heed::byteorder::BigEndian>,
+            heed::types::SerdeBincode<BTreeMap<Address, BTreeMap<U256, U256>>>,
+        >,
+        block_number: u64,
+        address: &Address,
+        slot: &U256,
+    ) -> Result<(Option<U256>, bool), Error> {
+        let mut iter = database.rev_range(txn, &..=block_number)?;
+
+        let mut missing_fallback = false;
+
+        while let Some((_, history)) = iter.next().transpose()? {
+            if let Some(value) = history.get(address).and_then(|storage| storage.get(slot)) {
+                return Ok((Some(*value), false));
+            }
+
+            missing_fallback = true;
+        }
+
+        Ok((None, missing_fallback))
+    }
+
+    // Returns the first block after `block_number` which changed the storage slot.
+    pub fn get_next_change_by_slot(
+        &self,
+        txn: &RoTxn,
+        database: &heed::Database<
+            heed::types::U64<heed::byteorder::BigEndian>,
+            heed::types::SerdeBincode<BTreeMap<Address, BTreeMap<U256, U256>>>,
+        >,
+        block_number: u64,
+        address: &Address,
+        slot: &U256,
+    ) -> Result<Option<u64>, Error> {
+        let mut iter = database.range(txn, &(block_number + 1..))?;
+
+        while let Some((changed_at, history)) = iter.next().transpose()? {
+            if history
+                .get(address)
+                .is_some_and(|storage| storage.contains_key(slot))
+            {
+                return Ok(Some(changed_at));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+// Read-only view of the state right after `block_number` got committed.
+//
+// Values are looked up in the account and storage history first. Values which only changed after
+// `block_number` are taken from the reverse changeset of the next change, anything else did not
+// change within the history window and is read from the latest state.
+pub struct HistoricalState<'a> {
+    db: &'a PersistentDB,
+    block_number: u64,
+    is_latest: bool,
+}
+
+impl<'a> HistoricalState<'a> {
+    pub(crate) fn new(db: &'a PersistentDB, block_number: u64, is_latest: bool) -> Self {
+        Self {
+            db,
+            block_number,
+            is_latest,
+        }
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.block_number
+    }
+}
+
+impl DatabaseRef for HistoricalState<'_> {
+    type Error = Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let (false, Some(accounts_history), Some(database)) = (
+            self.is_latest,
+            self.db.accounts_history.as_ref(),
+            self.db.inner.accounts_history,
+        ) else {
+            return self.db.basic_ref(address);
+        };
+
+        let txn = self.db.read_txn()?;
+
+        if let (Some(data), _) = accounts_history.get_by_block_and_address(
+            &txn,
+            &database,
+            self.block_number,
+            &address,
+        )? {
+            return Ok(Some(AccountInfo {
+                balance: data.balance,
+                nonce: data.nonce,
+                code_hash: data.code_hash,
+                ..Default::default()
+            }));
+        }
+
+        match accounts_history.get_next_change_by_address(
+            &txn,
+            &database,
+            self.block_number,
+            &address,
+        )? {
+            Some(changed_at) => {
+                let revert = self
+                    .db
+                    .inner
+                    .reverts
+                    .get(&txn, &changed_at)?
+                    .ok_or(Error::MissingRevert(changed_at))?;
+
+                let account = revert.accounts.get(&address).cloned().flatten();
+                Ok(Some(self.db.account_or_default(address, account)))
+            }
+            None => {
+                // The transaction of a read handle is locked until dropped
+                drop(txn);
+                self.db.basic_ref(address)
+            }
+        }
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.db.code_by_hash_ref(code_hash)
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        let (false, Some(storage_history), Some(database)) = (
+            self.is_latest,
+            self.db.storage_history.as_ref(),
+            self.db.inner.storage_history,
+        ) else {
+            return self.db.storage_ref(address, index);
+        };
+
+        let txn = self.db.read_txn()?;
+
+        if let (Some(value), _) = storage_history.get_by_block_and_slot(
+            &txn,
+            &database,
+            self.block_number,
+            &address,
+            &index,
+        )? {
+            return Ok(value);
+        }
+
+        match storage_history.get_next_change_by_slot(
+            &txn,
+            &database,
+            self.block_number,
+            &address,
+            &index,
+        )? {
+            Some(changed_at) => {
+                let revert = self
+                    .db
+                    .inner
+                    .reverts
+                    .get(&txn, &changed_at)?
+                    .ok_or(Error::MissingRevert(changed_at))?;
+
+                // a wiped storage lists all previous slots, any other slot was zero
+                Ok(revert
+                    .storage
+                    .iter()
+                    .find(|storage| storage.address == address)
+                    .and_then(|storage| storage.slots.iter().find(|(slot, _)| *slot == index))
+                    .map(|(_, value)| *value)
+                    .unwrap_or_default())
+            }
+            None => {
+                drop(txn);
+                self.db.storage_ref(address, index)
+            }
+        }
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        // Only the hashes of the 256 blocks preceding `block_number` are accessible
+        if number >= self.block_number || self.block_number - number > BLOCK_HASH_HISTORY {
+            return Ok(B256::ZERO);
+        }
+
+        Ok(self.db.get_block_hash(number)?.unwrap_or_default())
+    }
 }
 
 #[test]
@@ -104,7 +359,7 @@ fn test_account_history() {
     let history = AccountHistory::new(10);
     let mut txn = db.env.write_txn().unwrap();
 
-    let history_db = &db.inner.borrow().accounts_history.unwrap();
+    let history_db = &db.inner.accounts_history.unwrap();
 
     // Block 1
     history
@@ -415,7 +670,7 @@ fn test_accounts_history_capacity() {
     let history = AccountHistory::new(3);
     let mut txn = db.env.write_txn().unwrap();
 
-    let history_db = &db.inner.borrow().accounts_history.unwrap();
+    let history_db = &db.inner.accounts_history.unwrap();
 
     for i in 0..5 {
         println!("writing i... {}", i);
@@ -542,3 +797,254 @@ fn test_accounts_history_capacity() {
         }
     }
 }
+
+#[test]
+fn test_storage_history() {
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let db = crate::db::PersistentDB::new(
+        crate::db::PersistentDBOptions::new(path.path().to_path_buf()).with_history_size(3),
+    )
+    .expect("database");
+
+    let history = StorageHistory::new(3);
+    let mut txn = db.env.write_txn().unwrap();
+
+    let history_db = &db.inner.storage_history.unwrap();
+
+    let address = revm::primitives::address!("0000000000000000000000000000000000000001");
+
+    // Block 1
+    history
+        .insert(
+            &mut txn,
+            history_db,
+            1,
+            BTreeMap::from([(
+                address,
+                BTreeMap::from([
+                    (U256::from(1), U256::from(10)),
+                    (U256::from(2), U256::from(20)),
+                ]),
+            )]),
+        )
+        .unwrap();
+
+    // Block 2 (empty)
+    history
+        .insert(&mut txn, history_db, 2, BTreeMap::new())
+        .unwrap();
+
+    // Block 3
+    history
+        .insert(
+            &mut txn,
+            history_db,
+            3,
+            BTreeMap::from([(address, BTreeMap::from([(U256::from(1), U256::ZERO)]))]),
+        )
+        .unwrap();
+
+    for (block_number, slot, expected) in vec![
+        (1, U256::from(1), (Some(U256::from(10)), false)),
+        (1, U256::from(2), (Some(U256::from(20)), false)),
+        (1, U256::from(3), (None, true)),
+        // unchanged since block 1
+        (2, U256::from(1), (Some(U256::from(10)), false)),
+        // deleted in block 3
+        (3, U256::from(1), (Some(U256::ZERO), false)),
+        (3, U256::from(2), (Some(U256::from(20)), false)),
+    ] {
+        assert_eq!(
+            history
+                .get_by_block_and_slot(&txn, history_db, block_number, &address, &slot)
+                .unwrap(),
+            expected
+        );
+    }
+
+    // Block 4 evicts block 1
+    history
+        .insert(&mut txn, history_db, 4, BTreeMap::new())
+        .unwrap();
+
+    assert_eq!(
+        history
+            .get_by_block_and_slot(&txn, history_db, 4, &address, &U256::from(2))
+            .unwrap(),
+        (None, true)
+    );
+    assert_eq!(
+        history
+            .get_by_block_and_slot(&txn, history_db, 1, &address, &U256::from(2))
+            .unwrap(),
+        (None, false)
+    );
+}
+
+#[test]
+fn test_historical_state() {
+    use revm::primitives::HashMap;
+
+    use crate::db::{CommitKey, PendingCommit, PersistentDBOptions};
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf()).with_history_size(2))
+            .expect("database");
+
+    let address = revm::primitives::address!("0000000000000000000000000000000000000001");
+
+    fn commit(
+        db: &mut PersistentDB,
+        block_number: u64,
+        address: Address,
+        balance: u64,
+        slots: Vec<(u64, u64, u64)>,
+    ) {
+        let mut storage = HashMap::default();
+        for (slot, previous_value, value) in slots {
+            storage.insert(
+                U256::from(slot),
+                revm::database::states::StorageSlot::new_changed(
+                    U256::from(previous_value),
+                    U256::from(value),
+                ),
+            );
+        }
+
+        let mut state = HashMap::default();
+        state.insert(
+            address,
+            revm::database::TransitionAccount {
+                status: revm::database::AccountStatus::InMemoryChange,
+                info: Some(AccountInfo {
+                    balance: U256::from(balance),
+                    ..Default::default()
+                }),
+                previous_status: revm::database::AccountStatus::Loaded,
+                previous_info: None,
+                storage,
+                storage_was_destroyed: false,
+            },
+        );
+
+        crate::state_commit::commit_to_db(
+            db,
+            PendingCommit {
+                key: CommitKey(block_number, 0, B256::ZERO),
+                transitions: revm::database::TransitionState { transitions: state },
+                ..Default::default()
+            },
+            None,
+        )
+        .expect("ok");
+    }
+
+    commit(&mut db, 1, address, 100, vec![(1, 0, 10)]);
+    commit(&mut db, 2, address, 200, vec![(1, 10, 20)]);
+    commit(&mut db, 3, address, 300, vec![(2, 0, 30)]);
+
+    // Block 1 is no longer part of the history window
+    assert!(matches!(
+        db.historical_state(1),
+        Err(Error::HistoryUnavailable(1))
+    ));
+    assert!(matches!(
+        db.historical_state(4),
+        Err(Error::HistoryUnavailable(4))
+    ));
+
+    let state = db.historical_state(2).expect("historical state");
+    assert_eq!(
+        state.basic_ref(address).unwrap().unwrap().balance,
+        U256::from(200)
+    );
+    assert_eq!(
+        state.storage_ref(address, U256::from(1)).unwrap(),
+        U256::from(20)
+    );
+    // Slot 2 was only written in block 3
+    assert_eq!(
+        state.storage_ref(address, U256::from(2)).unwrap(),
+        U256::ZERO
+    );
+
+    commit(&mut db, 4, address, 400, vec![(1, 20, 40)]);
+
+    // Slot 1 did not change in block 3, but in block 4
+    let state = db.historical_state(3).expect("historical state");
+    assert_eq!(
+        state.basic_ref(address).unwrap().unwrap().balance,
+        U256::from(300)
+    );
+    assert_eq!(
+        state.storage_ref(address, U256::from(1)).unwrap(),
+        U256::from(20)
+    );
+    assert_eq!(
+        state.storage_ref(address, U256::from(2)).unwrap(),
+        U256::from(30)
+    );
+
+    // Latest block reads the current state
+    let state = db.historical_state(4).expect("historical state");
+    assert_eq!(
+        state.basic_ref(address).unwrap().unwrap().balance,
+        U256::from(400)
+    );
+    assert_eq!(
+        state.storage_ref(address, U256::from(1)).unwrap(),
+        U256::from(40)
+    );
+}
+
+#[test]
+fn test_historical_state_unwind_depth() {
+    use crate::{db::PersistentDBOptions, trie::pending_commit};
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    // The reverse changesets of the history window are kept regardless of the unwind depth
+    let mut db = PersistentDB::new(
+        PersistentDBOptions::new(path.path().to_path_buf())
+            .with_history_size(3)
+            .with_unwind_depth(1),
+    )
+    .expect("database");
+    assert_eq!(db.unwind_depth, 3);
+
+    let alice = revm::primitives::address!("0000000000000000000000000000000000000001");
+    let bob = revm::primitives::address!("0000000000000000000000000000000000000002");
+
+    for (block_number, address, balance) in [
+        (1, alice, 100),
+        (2, bob, 200),
+        (3, alice, 300),
+        (4, bob, 400),
+    ] {
+        crate::state_commit::commit_to_db(
+            &mut db,
+            pending_commit(block_number, vec![(address, balance, vec![])]),
+            None,
+        )
+        .expect("commit");
+    }
+
+    // The history entry of block 1 got evicted, the revert of block 3 restores the balance
+    let state = db.historical_state(2).expect("historical state");
+    assert_eq!(
+        state.basic_ref(alice).unwrap().unwrap().balance,
+        U256::from(100)
+    );
+}
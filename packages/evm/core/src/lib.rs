@@ -1,11 +1,27 @@
 pub mod account;
+pub mod backend;
+pub mod backup;
+pub mod balance_index;
+pub mod code_references;
 pub mod db;
+pub mod deployment;
+mod errors;
 mod events;
 pub mod historical;
 pub mod legacy;
+pub mod log_index;
 pub mod logger;
 pub mod logs_bloom;
+pub mod memory;
+pub mod migrations;
+pub mod pruning;
+pub mod read_handle;
 pub mod receipt;
+pub mod revert;
+pub mod snapshot;
 pub mod state_changes;
 pub mod state_commit;
 pub mod state_root;
+pub mod stats;
+pub mod trie;
+pub mod verify;
@@ -0,0 +1,765 @@
+use std::{borrow::Cow, iter::Peekable};
+
+use heed::{RoTxn, RwTxn, WithoutTls};
+use revm::primitives::{
+    Address, B256, Log,
+    alloy_primitives::{Bloom, BloomInput},
+};
+
+use crate::{
+    db::{
+        CommitMetadata, CommitReceipts, Error, HashWrapper, InnerStorage, PersistentDB, TablesTxn,
+        read_commit_metadata,
+    },
+    receipt::TxReceipt,
+};
+
+// Upper bound of logs returned by a single `get_logs` call.
+pub const MAX_LOGS_LIMIT: u64 = 10_000;
+
+// A log carries at most four topics.
+const MAX_TOPICS: usize = 4;
+
+// Position of a log, logs are ordered by block and by their index within the block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LogPosition {
+    pub block_number: u64,
+    pub log_index: u64,
+}
+
+impl LogPosition {
+    fn to_be_bytes(self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&self.block_number.to_be_bytes());
+        bytes[8..].copy_from_slice(&self.log_index.to_be_bytes());
+        bytes
+    }
+
+    fn from_be_slice(bytes: &[u8]) -> Self {
+        LogPosition {
+            block_number: u64::from_be_bytes(bytes[..8].try_into().expect("8 bytes")),
+            log_index: u64::from_be_bytes(bytes[8..16].try_into().expect("8 bytes")),
+        }
+    }
+}
+
+// Key of the address index, the position is encoded big endian such that the logs of an address
+// are sorted in chain order.
+#[derive(Debug)]
+pub(crate) struct LogAddressKey(pub(crate) Address, pub(crate) LogPosition);
+impl heed::BytesEncode<'_> for LogAddressKey {
+    type EItem = LogAddressKey;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<[u8]>, heed::BoxedError> {
+        let mut combined = Vec::with_capacity(36);
+        combined.extend_from_slice(item.0.as_slice());
+        combined.extend_from_slice(&item.1.to_be_bytes());
+
+        Ok(Cow::Owned(combined))
+    }
+}
+
+impl heed::BytesDecode<'_> for LogAddressKey {
+    type DItem = LogAddressKey;
+
+    fn bytes_decode(bytes: &'_ [u8]) -> Result<Self::DItem, heed::BoxedError> {
+        Ok(LogAddressKey(
+            Address::from_slice(&bytes[0..20]),
+            LogPosition::from_be_slice(&bytes[20..36]),
+        ))
+    }
+}
+
+// Key of the topic index, a tuple of the topic position within the log, the topic and the position
+// of the log.
+#[derive(Debug)]
+pub(crate) struct LogTopicKey(pub(crate) u8, pub(crate) B256, pub(crate) LogPosition);
+impl heed::BytesEncode<'_> for LogTopicKey {
+    type EItem = LogTopicKey;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<[u8]>, heed::BoxedError> {
+        let mut combined = Vec::with_capacity(49);
+        combined.push(item.0);
+        combined.extend_from_slice(item.1.as_slice());
+        combined.extend_from_slice(&item.2.to_be_bytes());
+
+        Ok(Cow::Owned(combined))
+    }
+}
+
+impl heed::BytesDecode<'_> for LogTopicKey {
+    type DItem = LogTopicKey;
+
+    fn bytes_decode(bytes: &'_ [u8]) -> Result<Self::DItem, heed::BoxedError> {
+        Ok(LogTopicKey(
+            bytes[0],
+            B256::from_slice(&bytes[1..33]),
+            LogPosition::from_be_slice(&bytes[33..49]),
+        ))
+    }
+}
+
+// Tables indexing the logs of the stored receipts by emitting address and topics. The logs
+// themselves remain part of the receipts.
+#[derive(Clone)]
+pub(crate) struct LogIndexTables {
+    pub addresses: heed::Database<LogAddressKey, heed::types::Unit>,
+    pub topics: heed::Database<LogTopicKey, heed::types::Unit>,
+}
+
+impl LogIndexTables {
+    pub const MAX_DBS: u32 = 2;
+
+    pub fn create(env: &heed::Env<WithoutTls>, txn: &mut TablesTxn) -> Result<Self, Error> {
+        let addresses = txn.database::<LogAddressKey, heed::types::Unit>(env, "log_addresses")?;
+        let topics = txn.database::<LogTopicKey, heed::types::Unit>(env, "log_topics")?;
+
+        Ok(Self { addresses, topics })
+    }
+
+    // Indexes the logs of the receipts committed in `block_number`.
+    pub fn insert(
+        &self,
+        txn: &mut RwTxn,
+        block_number: u64,
+        tx_receipts: &[(B256, TxReceipt)],
+    ) -> Result<(), Error> {
+        for (log_index, log) in tx_receipts
+            .iter()
+            .flat_map(|(_, receipt)| receipt_logs(receipt))
+        {
+            let position = LogPosition {
+                block_number,
+                log_index,
+            };
+
+            self.addresses
+                .put(txn, &LogAddressKey(log.address, position), &())?;
+
+            for (topic_index, topic) in log.topics().iter().enumerate() {
+                self.topics
+                    .put(txn, &LogTopicKey(topic_index as u8, *topic, position), &())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Removes the logs of the receipts committed in `block_number` from the index.
+    pub fn delete(
+        &self,
+        txn: &mut RwTxn,
+        block_number: u64,
+        tx_receipts: &[(B256, TxReceipt)],
+    ) -> Result<(), Error> {
+        for (log_index, log) in tx_receipts
+            .iter()
+            .flat_map(|(_, receipt)| receipt_logs(receipt))
+        {
+            let position = LogPosition {
+                block_number,
+                log_index,
+            };
+
+            self.addresses
+                .delete(txn, &LogAddressKey(log.address, position))?;
+
+            for (topic_index, topic) in log.topics().iter().enumerate() {
+                self.topics
+                    .delete(txn, &LogTopicKey(topic_index as u8, *topic, position))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Logs of a receipt together with their index within the block.
+fn receipt_logs(receipt: &TxReceipt) -> impl Iterator<Item = (u64, &Log)> {
+    receipt
+        .logs
+        .iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, log)| (receipt.first_log_index + index as u64, log))
+}
+
+// Filter of `eth_getLogs`, the block range is inclusive.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    pub from_block: u64,
+    pub to_block: u64,
+    // Matches logs emitted by any of the addresses, empty matches all logs
+    pub addresses: Vec<Address>,
+    // Topics by position, a log matches if its topic equals any of the topics at that position.
+    // Empty positions match any topic.
+    pub topics: Vec<Vec<B256>>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, log: &Log) -> bool {
+        if !self.addresses.is_empty() && !self.addresses.contains(&log.address) {
+            return false;
+        }
+
+        self.topics.iter().enumerate().all(|(position, topics)| {
+            topics.is_empty()
+                || log
+                    .topics()
+                    .get(position)
+                    .is_some_and(|topic| topics.contains(topic))
+        })
+    }
+
+    // Whether a block with the given logs bloom may contain matching logs. Blocks with an
+    // unknown bloom always need to be read.
+    pub fn may_match(&self, logs_bloom: &Option<Bloom>) -> bool {
+        let Some(logs_bloom) = logs_bloom else {
+            return true;
+        };
+
+        if logs_bloom.is_zero() {
+            return false;
+        }
+
+        (self.addresses.is_empty()
+            || bloom_contains_any(logs_bloom, self.addresses.iter().map(|a| a.as_slice())))
+            && self.topics.iter().all(|topics| {
+                topics.is_empty()
+                    || bloom_contains_any(logs_bloom, topics.iter().map(|t| t.as_slice()))
+            })
+    }
+}
+
+fn bloom_contains_any<'a>(logs_bloom: &Bloom, mut values: impl Iterator<Item = &'a [u8]>) -> bool {
+    values.any(|value| logs_bloom.contains_input(BloomInput::Raw(value)))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedLog {
+    pub log: Log,
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub transaction_hash: B256,
+    pub transaction_index: u64,
+    pub log_index: u64,
+}
+
+type Positions<'a> = Box<dyn Iterator<Item = Result<LogPosition, Error>> + 'a>;
+
+// Merges the ascending positions of multiple index ranges. The ranges are disjoint since a log has
+// a single address and a single topic per position.
+struct MergedPositions<'a> {
+    ranges: Vec<Peekable<Positions<'a>>>,
+}
+
+impl Iterator for MergedPositions<'_> {
+    type Item = Result<LogPosition, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut next: Option<(usize, LogPosition)> = None;
+
+        for (index, range) in self.ranges.iter_mut().enumerate() {
+            match range.peek() {
+                Some(Ok(position)) => {
+                    if next.is_none_or(|(_, next_position)| *position < next_position) {
+                        next = Some((index, *position));
+                    }
+                }
+                Some(Err(_)) => return range.next(),
+                None => (),
+            }
+        }
+
+        self.ranges[next?.0].next()
+    }
+}
+
+impl PersistentDB {
+    // Returns the logs matching `filter` in chain order starting at `cursor`. At most `limit` logs
+    // are returned together with the position of the next matching log, if any.
+    pub fn get_logs(
+        &self,
+        filter: &LogFilter,
+        cursor: Option<LogPosition>,
+        limit: u64,
+    ) -> Result<(Option<LogPosition>, Vec<IndexedLog>), Error> {
+        if filter.topics.len() > MAX_TOPICS {
+            return Err(Error::InvalidLogFilter(format!(
+                "at most {} topic positions are supported",
+                MAX_TOPICS
+            )));
+        }
+
+        if filter.from_block > filter.to_block {
+            return Err(Error::InvalidLogFilter(format!(
+                "from block {} is after to block {}",
+                filter.from_block, filter.to_block
+            )));
+        }
+
+        let first = LogPosition {
+            block_number: filter.from_block,
+            log_index: 0,
+        };
+        let start = cursor.map_or(first, |cursor| cursor.max(first));
+        let end = LogPosition {
+            block_number: filter.to_block,
+            log_index: u64::MAX,
+        };
+
+        let limit = limit.min(MAX_LOGS_LIMIT) as usize;
+        let mut logs = Vec::with_capacity(limit.min(1000));
+
+        if start > end {
+            return Ok((None, logs));
+        }
+
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        let mut addresses = filter.addresses.clone();
+        addresses.sort();
+        addresses.dedup();
+
+        let topic_position = filter.topics.iter().position(|topics| !topics.is_empty());
+
+        // Logs are looked up via the address index if possible, as addresses are usually more
+        // selective than topics. Without any address or topic all logs in range are scanned.
+        let mut ranges: Vec<Peekable<Positions>> = Vec::new();
+
+        if !addresses.is_empty() {
+            for address in addresses {
+                let range = LogAddressKey(address, start)..=LogAddressKey(address, end);
+                let positions: Positions = Box::new(
+                    inner
+                        .log_index
+                        .addresses
+                        .range(&rtxn, &range)?
+                        .map(|item| -> Result<LogPosition, Error> { Ok(item?.0.1) }),
+                );
+
+                ranges.push(positions.peekable());
+            }
+        } else if let Some(topic_position) = topic_position {
+            let mut topics = filter.topics[topic_position].clone();
+            topics.sort();
+            topics.dedup();
+
+            for topic in topics {
+                let range = LogTopicKey(topic_position as u8, topic, start)
+                    ..=LogTopicKey(topic_position as u8, topic, end);
+                let positions: Positions = Box::new(
+                    inner
+                        .log_index
+                        .topics
+                        .range(&rtxn, &range)?
+                        .map(|item| -> Result<LogPosition, Error> { Ok(item?.0.2) }),
+                );
+
+                ranges.push(positions.peekable());
+            }
+        } else {
+            let next = scan_logs(&rtxn, inner, filter, start, end, limit, &mut logs)?;
+            return Ok((next, logs));
+        }
+
+        let positions = MergedPositions { ranges };
+
+        // The block of the current position, `None` if the block cannot contain matching logs
+        let mut current: Option<(u64, Option<(B256, CommitReceipts)>)> = None;
+
+        for position in positions {
+            let position = position?;
+
+            if current
+                .as_ref()
+                .is_none_or(|(block_number, _)| *block_number != position.block_number)
+            {
+                current = Some((
+                    position.block_number,
+                    read_matching_commit(&rtxn, inner, filter, position.block_number)?,
+                ));
+            }
+
+            let Some((block_number, Some((block_hash, commit)))) = current.as_ref() else {
+                continue;
+            };
+
+            let Some((transaction_hash, receipt, log)) = find_log(commit, position.log_index)
+            else {
+                continue;
+            };
+
+            if !filter.matches(log) {
+                continue;
+            }
+
+            if logs.len() == limit {
+                return Ok((Some(position), logs));
+            }
+
+            logs.push(IndexedLog {
+                log: log.clone(),
+                block_number: *block_number,
+                block_hash: *block_hash,
+                transaction_hash: *transaction_hash,
+                transaction_index: receipt.transaction_index,
+                log_index: position.log_index,
+            });
+        }
+
+        Ok((None, logs))
+    }
+}
+
+// Reads all logs between `start` and `end` from the stored receipts, used for filters which
+// cannot make use of the index.
+fn scan_logs(
+    txn: &RoTxn,
+    inner: &InnerStorage,
+    filter: &LogFilter,
+    start: LogPosition,
+    end: LogPosition,
+    limit: usize,
+    logs: &mut Vec<IndexedLog>,
+) -> Result<Option<LogPosition>, Error> {
+    // Only the metadata is decoded upfront, receipts are read for blocks whose bloom matches
+    for item in inner
+        .commits
+        .remap_data_type::<heed::types::SerdeBincode<CommitMetadata>>()
+        .range(txn, &(start.block_number..=end.block_number))?
+    {
+        let (block_number, metadata) = item?;
+        if !filter.may_match(&metadata.logs_bloom) {
+            continue;
+        }
+
+        let Some(commit) = inner.commits.get(txn, &block_number)? else {
+            continue;
+        };
+
+        let mut cached_block_hash = None;
+
+        for (transaction_hash, receipt) in commit.tx_receipts.iter() {
+            for (log_index, log) in receipt_logs(receipt) {
+                let position = LogPosition {
+                    block_number,
+                    log_index,
+                };
+
+                if position < start || !filter.matches(log) {
+                    continue;
+                }
+
+                if logs.len() == limit {
+                    return Ok(Some(position));
+                }
+
+                let block_hash = match cached_block_hash {
+                    Some(block_hash) => block_hash,
+                    None => *cached_block_hash.insert(read_block_hash(txn, inner, &commit)?),
+                };
+
+                logs.push(IndexedLog {
+                    log: log.clone(),
+                    block_number,
+                    block_hash,
+                    transaction_hash: *transaction_hash,
+                    transaction_index: receipt.transaction_index,
+                    log_index,
+                });
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// Reads the commit of `block_number` and its block hash unless its bloom rules out any match.
+fn read_matching_commit(
+    txn: &RoTxn,
+    inner: &InnerStorage,
+    filter: &LogFilter,
+    block_number: u64,
+) -> Result<Option<(B256, CommitReceipts)>, Error> {
+    let Some(metadata) = read_commit_metadata(txn, inner, block_number)? else {
+        return Ok(None);
+    };
+
+    if !filter.may_match(&metadata.logs_bloom) {
+        return Ok(None);
+    }
+
+    let Some(commit) = inner.commits.get(txn, &block_number)? else {
+        return Ok(None);
+    };
+
+    Ok(Some((read_block_hash(txn, inner, &commit)?, commit)))
+}
+
+// The receipt locations also cover commits without block data.
+fn read_block_hash(
+    txn: &RoTxn,
+    inner: &InnerStorage,
+    commit: &CommitReceipts,
+) -> Result<B256, Error> {
+    let Some(tx_hash) = commit.tx_hashes().next() else {
+        return Ok(B256::ZERO);
+    };
+
+    Ok(inner
+        .receipt_locations
+        .get(txn, &HashWrapper(*tx_hash))?
+        .map(|location| location.block_hash)
+        .unwrap_or_default())
+}
+
+// Finds the log with the given index within the block.
+fn find_log(commit: &CommitReceipts, log_index: u64) -> Option<(&B256, &TxReceipt, &Log)> {
+    // Receipts without logs share the first log index with the following receipt, thus the log
+    // belongs to the last receipt starting at or before it.
+    let receipts = &commit.tx_receipts;
+    let index = receipts.partition_point(|(_, receipt)| receipt.first_log_index <= log_index);
+    let (tx_hash, receipt) = receipts.get(index.checked_sub(1)?)?;
+
+    let log = receipt
+        .logs
+        .as_ref()?
+        .get((log_index - receipt.first_log_index) as usize)?;
+
+    Some((tx_hash, receipt, log))
+}
+
+#[test]
+fn test_get_logs() {
+    use revm::{
+        context::result::{ExecutionResult, Output, SuccessReason},
+        primitives::{Bytes, U256, address},
+    };
+
+    use crate::{
+        db::PersistentDBOptions, receipt::executed_transaction, state_commit::commit_to_db,
+        trie::pending_commit,
+    };
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+
+    let contract1 = address!("1000000000000000000000000000000000000001");
+    let contract2 = address!("2000000000000000000000000000000000000002");
+    let transfer = B256::repeat_byte(0xaa);
+    let approval = B256::repeat_byte(0xbb);
+
+    let log =
+        |address: Address, topics: Vec<B256>| Log::new_unchecked(address, topics, Bytes::new());
+
+    let success = |logs: Vec<Log>| ExecutionResult::Success {
+        reason: SuccessReason::Return,
+        gas_used: 21000,
+        gas_refunded: 0,
+        logs,
+        output: Output::Call(Bytes::new()),
+    };
+
+    // Every block emits a transfer and an approval of contract1 and a transfer of contract2, the
+    // second topic is the block number
+    for block_number in 1..=5u64 {
+        let sender = B256::from(U256::from(block_number));
+
+        let mut pending = pending_commit(block_number, vec![]);
+        pending.results.insert(
+            B256::repeat_byte(block_number as u8),
+            executed_transaction(
+                0,
+                success(vec![
+                    log(contract1, vec![transfer, sender]),
+                    log(contract1, vec![approval, sender]),
+                ]),
+            ),
+        );
+        pending.results.insert(
+            B256::repeat_byte(0x10 + block_number as u8),
+            executed_transaction(
+                1,
+                ExecutionResult::Revert {
+                    gas_used: 21000,
+                    output: Bytes::new(),
+                },
+            ),
+        );
+        pending.results.insert(
+            B256::repeat_byte(0x20 + block_number as u8),
+            executed_transaction(2, success(vec![log(contract2, vec![transfer, sender])])),
+        );
+
+        commit_to_db(&mut db, pending, None).expect("commit");
+    }
+
+    let positions = |filter: &LogFilter, limit: u64| {
+        let mut positions = Vec::new();
+        let mut cursor = None;
+
+        loop {
+            let (next, logs) = db.get_logs(filter, cursor, limit).expect("logs");
+            assert!(logs.len() <= limit as usize);
+
+            for log in logs {
+                assert!(filter.matches(&log.log));
+                positions.push((log.block_number, log.log_index, log.transaction_index));
+            }
+
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break positions,
+            }
+        }
+    };
+
+    // All logs within the range
+    let filter = LogFilter {
+        from_block: 2,
+        to_block: 3,
+        ..Default::default()
+    };
+    let expected = vec![
+        (2, 0, 0),
+        (2, 1, 0),
+        (2, 2, 2),
+        (3, 0, 0),
+        (3, 1, 0),
+        (3, 2, 2),
+    ];
+    assert_eq!(positions(&filter, 100), expected);
+    assert_eq!(positions(&filter, 1), expected);
+
+    // By address
+    let filter = LogFilter {
+        from_block: 1,
+        to_block: 5,
+        addresses: vec![contract2],
+        ..Default::default()
+    };
+    assert_eq!(
+        positions(&filter, 2),
+        (1..=5).map(|block| (block, 2, 2)).collect::<Vec<_>>()
+    );
+
+    // By address and topic
+    let filter = LogFilter {
+        from_block: 1,
+        to_block: 5,
+        addresses: vec![contract1, contract2],
+        topics: vec![vec![transfer]],
+    };
+    assert_eq!(
+        positions(&filter, 3),
+        (1..=5)
+            .flat_map(|block| [(block, 0, 0), (block, 2, 2)])
+            .collect::<Vec<_>>()
+    );
+
+    // By topics only, the first position matches any topic
+    let filter = LogFilter {
+        from_block: 1,
+        to_block: 5,
+        topics: vec![
+            vec![],
+            vec![B256::from(U256::from(2)), B256::from(U256::from(4))],
+        ],
+        ..Default::default()
+    };
+    assert_eq!(
+        positions(&filter, 4),
+        vec![
+            (2, 0, 0),
+            (2, 1, 0),
+            (2, 2, 2),
+            (4, 0, 0),
+            (4, 1, 0),
+            (4, 2, 2)
+        ]
+    );
+
+    let (next, indexed) = db
+        .get_logs(
+            &filter,
+            Some(LogPosition {
+                block_number: 4,
+                log_index: 1,
+            }),
+            1,
+        )
+        .expect("logs");
+    assert_eq!(
+        next,
+        Some(LogPosition {
+            block_number: 4,
+            log_index: 2
+        })
+    );
+    assert_eq!(
+        indexed[0].log,
+        log(contract1, vec![approval, B256::from(U256::from(4))])
+    );
+    assert_eq!(indexed[0].transaction_hash, B256::repeat_byte(4));
+
+    // Unwinding removes the logs from the index
+    db.unwind_to(3).expect("unwind");
+    assert_eq!(positions(&filter, 4), vec![(2, 0, 0), (2, 1, 0), (2, 2, 2)]);
+
+    let rtxn = db.env.read_txn().unwrap();
+    assert_eq!(db.inner.log_index.addresses.len(&rtxn).unwrap(), 9);
+    assert_eq!(db.inner.log_index.topics.len(&rtxn).unwrap(), 18);
+    drop(rtxn);
+
+    // The stored bloom rules out blocks without matching logs
+    let logs_bloom = db.get_logs_bloom(2).unwrap();
+    let matching = LogFilter {
+        addresses: vec![contract2],
+        topics: vec![vec![transfer], vec![B256::from(U256::from(2))]],
+        ..Default::default()
+    };
+    assert!(matching.may_match(&logs_bloom));
+    assert!(
+        !LogFilter {
+            topics: vec![vec![], vec![B256::from(U256::from(3))]],
+            ..matching.clone()
+        }
+        .may_match(&logs_bloom)
+    );
+    assert!(LogFilter::default().may_match(&None));
+    assert!(!LogFilter::default().may_match(&Some(Default::default())));
+
+    // Invalid filters
+    assert!(
+        db.get_logs(
+            &LogFilter {
+                from_block: 2,
+                to_block: 1,
+                ..Default::default()
+            },
+            None,
+            10
+        )
+        .is_err()
+    );
+    assert!(
+        db.get_logs(
+            &LogFilter {
+                from_block: 1,
+                to_block: 2,
+                topics: vec![vec![]; 5],
+                ..Default::default()
+            },
+            None,
+            10
+        )
+        .is_err()
+    );
+}
@@ -1,6 +1,6 @@
-use revm::primitives::alloy_primitives::Bloom;
+use revm::primitives::{B256, alloy_primitives::Bloom};
 
-use crate::db::PendingCommit;
+use crate::{db::PendingCommit, receipt::TxReceipt};
 
 pub fn calculate(pending_commit: &PendingCommit) -> Result<Bloom, crate::db::Error> {
     let results = match pending_commit.built_commit.as_ref() {
@@ -10,7 +10,7 @@ pub fn calculate(pending_commit: &PendingCommit) -> Result<Bloom, crate::db::Err
 
     let receipt_blooms = results
         .values()
-        .map(|r| Bloom::from_iter(r.logs()))
+        .map(|r| Bloom::from_iter(r.result.logs()))
         .collect::<Vec<Bloom>>();
 
     let logs_bloom = receipt_blooms
@@ -20,6 +20,15 @@ pub fn calculate(pending_commit: &PendingCommit) -> Result<Bloom, crate::db::Err
     Ok(logs_bloom)
 }
 
+// Bloom over the logs of committed receipts.
+pub(crate) fn from_receipts(tx_receipts: &[(B256, TxReceipt)]) -> Bloom {
+    Bloom::from_iter(
+        tx_receipts
+            .iter()
+            .flat_map(|(_, receipt)| receipt.logs.iter().flatten()),
+    )
+}
+
 #[test]
 fn test_calculate_empty_logs_bloom() {
     let result = calculate(&Default::default()).expect("ok");
@@ -88,7 +97,7 @@ fn test_calculate_logs_bloom() {
             revm::primitives::b256!(
                 "0000000000000000000000000000000000000000000000000000000000000001"
             ),
-            _logs(test_case.logs),
+            crate::receipt::executed_transaction(0, _logs(test_case.logs)),
         );
 
         let result = calculate(&pending).expect("ok");
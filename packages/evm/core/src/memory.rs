@@ -0,0 +1,724 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use alloy_trie::{
+    TrieAccount,
+    root::{state_root_unhashed, storage_root_unhashed},
+};
+use revm::{
+    DatabaseRef,
+    primitives::{Address, B256, BLOCK_HASH_HISTORY, Bytes, U256},
+    state::{AccountInfo, Bytecode},
+};
+
+use crate::{
+    backend::{StorageBackend, StorageTxn},
+    db::{CommitData, CommitKey, CommitReceipts, Error, GenesisInfo},
+    historical::HistoricalAccountData,
+    legacy::{LegacyAccountAttributes, LegacyAddress, LegacyColdWallet},
+    logger::Logger,
+    pruning::Retained,
+    receipt::TxReceipt,
+    state_commit::{StateCommit, present_slots},
+    state_root::StateRootMode,
+};
+
+#[derive(Clone, Default)]
+struct MemoryState {
+    accounts: BTreeMap<Address, AccountInfo>,
+    storage: BTreeMap<Address, BTreeMap<U256, U256>>,
+    contracts: HashMap<B256, Bytecode>,
+    legacy_attributes: BTreeMap<Address, LegacyAccountAttributes>,
+    legacy_cold_wallets: BTreeMap<LegacyAddress, LegacyColdWallet>,
+    commits: BTreeMap<u64, CommitReceipts>,
+    accounts_history: BTreeMap<u64, BTreeMap<Address, HistoricalAccountData>>,
+    storage_history: BTreeMap<u64, BTreeMap<Address, BTreeMap<U256, U256>>>,
+    blocks: BTreeMap<u64, Bytes>,
+    block_hashes: BTreeMap<u64, B256>,
+    block_numbers: HashMap<B256, u64>,
+    proofs: BTreeMap<u64, Bytes>,
+    transactions: HashMap<String, Bytes>,
+    transaction_keys: HashMap<B256, String>,
+    total_round: u64,
+}
+
+// Storage backend which keeps the entire state in memory and never touches the disk. Blocks are
+// kept forever, unwinding, pruning and the secondary indexes are only provided by `PersistentDB`.
+#[derive(Default)]
+pub struct MemoryDB {
+    state: RwLock<MemoryState>,
+    history_size: Option<u64>,
+    state_root_mode: StateRootMode,
+    genesis_info: Option<GenesisInfo>,
+    logger: Logger,
+}
+
+impl MemoryDB {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_logger(mut self, logger: Logger) -> Self {
+        self.logger = logger;
+        self
+    }
+
+    pub fn with_history_size(mut self, history_size: u64) -> Self {
+        self.history_size = Some(history_size).filter(|history_size| *history_size > 0);
+        self
+    }
+
+    pub fn with_state_root_mode(mut self, state_root_mode: StateRootMode) -> Self {
+        self.state_root_mode = state_root_mode;
+        self
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, MemoryState>, Error> {
+        self.state.read().map_err(|_| Error::Lock)
+    }
+
+    fn write_state(&self) -> Result<RwLockWriteGuard<'_, MemoryState>, Error> {
+        self.state.write().map_err(|_| Error::Lock)
+    }
+
+    fn account_or_default(&self, address: Address, account: Option<AccountInfo>) -> AccountInfo {
+        match account {
+            Some(account) => account,
+            None => match &self.genesis_info {
+                Some(genesis) if genesis.account == address => AccountInfo {
+                    balance: genesis.initial_supply,
+                    ..Default::default()
+                },
+                _ => AccountInfo::default(),
+            },
+        }
+    }
+}
+
+impl MemoryState {
+    // Builds the account trie from scratch, which is fine for the amount of state kept in memory.
+    fn trie_root(&self) -> B256 {
+        state_root_unhashed(self.accounts.iter().map(|(address, info)| {
+            let storage = self.storage.get(address).into_iter().flatten();
+
+            (
+                *address,
+                TrieAccount {
+                    nonce: info.nonce,
+                    balance: info.balance,
+                    storage_root: storage_root_unhashed(
+                        storage.map(|(slot, value)| (B256::from(*slot), *value)),
+                    ),
+                    code_hash: info.code_hash,
+                },
+            )
+        }))
+    }
+}
+
+// Same retention as `AccountHistory` and `StorageHistory`.
+fn insert_history<T>(
+    history: &mut BTreeMap<u64, BTreeMap<Address, T>>,
+    capacity: u64,
+    block_number: u64,
+    changes: BTreeMap<Address, T>,
+) {
+    if history.len() as u64 >= capacity {
+        *history = history.split_off(&(block_number.saturating_sub(capacity) + 1));
+    }
+
+    history.insert(block_number, changes);
+}
+
+// Looks up the most recent change up to `block_number`, see `AccountHistory`.
+fn find_history<T, V>(
+    history: &BTreeMap<u64, T>,
+    block_number: u64,
+    find: impl Fn(&T) -> Option<V>,
+) -> (Option<V>, bool) {
+    let mut missing_fallback = false;
+
+    for (_, changes) in history.range(..=block_number).rev() {
+        if let Some(value) = find(changes) {
+            return (Some(value), false);
+        }
+
+        missing_fallback = true;
+    }
+
+    (None, missing_fallback)
+}
+
+fn retained<T>(value: Option<T>) -> Retained<T> {
+    match value {
+        Some(value) => Retained::Present(value),
+        None => Retained::Absent,
+    }
+}
+
+// Write transaction of `MemoryDB`, which works on a copy of the state, see `MemoryDB::write`.
+pub struct MemoryTxn<'a> {
+    db: &'a MemoryDB,
+    state: MemoryState,
+}
+
+impl StorageTxn for MemoryTxn<'_> {
+    fn put_account(
+        &mut self,
+        _block_number: u64,
+        address: Address,
+        account: Option<&AccountInfo>,
+    ) -> Result<(), Error> {
+        match account {
+            Some(account) => self.state.accounts.insert(address, account.clone()),
+            None => self.state.accounts.remove(&address),
+        };
+
+        Ok(())
+    }
+
+    fn put_contract(
+        &mut self,
+        _block_number: u64,
+        code_hash: B256,
+        bytecode: &Bytecode,
+    ) -> Result<(), Error> {
+        self.state.contracts.insert(code_hash, bytecode.clone());
+
+        Ok(())
+    }
+
+    fn write_storage(
+        &mut self,
+        address: Address,
+        wipe: bool,
+        slots: &[(U256, U256)],
+    ) -> Result<Vec<U256>, Error> {
+        let storage = self.state.storage.entry(address).or_default();
+
+        let wiped_slots = match wipe {
+            true => std::mem::take(storage).into_keys().collect(),
+            false => Vec::new(),
+        };
+
+        for (slot, value) in slots.iter() {
+            match value.is_zero() {
+                true => storage.remove(slot),
+                false => storage.insert(*slot, *value),
+            };
+        }
+
+        if storage.is_empty() {
+            self.state.storage.remove(&address);
+        }
+
+        Ok(wiped_slots)
+    }
+
+    fn get_legacy_attributes(
+        &self,
+        address: Address,
+    ) -> Result<Option<LegacyAccountAttributes>, Error> {
+        Ok(self.state.legacy_attributes.get(&address).cloned())
+    }
+
+    fn put_legacy_attributes(
+        &mut self,
+        address: Address,
+        legacy_attributes: &LegacyAccountAttributes,
+    ) -> Result<(), Error> {
+        self.state
+            .legacy_attributes
+            .insert(address, legacy_attributes.clone());
+
+        Ok(())
+    }
+
+    fn get_legacy_cold_wallet(
+        &self,
+        address: LegacyAddress,
+    ) -> Result<Option<LegacyColdWallet>, Error> {
+        Ok(self.state.legacy_cold_wallets.get(&address).cloned())
+    }
+
+    fn put_legacy_cold_wallet(
+        &mut self,
+        address: LegacyAddress,
+        legacy_cold_wallet: &LegacyColdWallet,
+    ) -> Result<(), Error> {
+        self.state
+            .legacy_cold_wallets
+            .insert(address, legacy_cold_wallet.clone());
+
+        Ok(())
+    }
+
+    fn keeps_history(&self) -> bool {
+        self.db.history_size.is_some()
+    }
+
+    fn insert_account_history(
+        &mut self,
+        block_number: u64,
+        accounts: Vec<(Address, AccountInfo)>,
+    ) -> Result<(), Error> {
+        if let Some(history_size) = self.db.history_size {
+            insert_history(
+                &mut self.state.accounts_history,
+                history_size,
+                block_number,
+                accounts
+                    .into_iter()
+                    .map(|(address, account)| (address, account.into()))
+                    .collect(),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn insert_storage_history(
+        &mut self,
+        block_number: u64,
+        storage: BTreeMap<Address, BTreeMap<U256, U256>>,
+    ) -> Result<(), Error> {
+        if let Some(history_size) = self.db.history_size {
+            insert_history(
+                &mut self.state.storage_history,
+                history_size,
+                block_number,
+                storage,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn put_block(&mut self, block_number: u64, commit_data: &CommitData) -> Result<(), Error> {
+        let state = &mut self.state;
+
+        state.blocks.insert(block_number, commit_data.block.clone());
+        state
+            .block_hashes
+            .insert(block_number, commit_data.block_hash);
+        state
+            .block_numbers
+            .insert(commit_data.block_hash, block_number);
+        state.proofs.insert(block_number, commit_data.proof.clone());
+
+        for (sequence, transaction) in commit_data.transactions.iter().enumerate() {
+            let key = format!("{}-{}", block_number, sequence);
+
+            state
+                .transaction_keys
+                .insert(commit_data.transaction_hashes[sequence], key.clone());
+            state.transactions.insert(key, transaction.clone());
+        }
+
+        state.total_round += commit_data.commit_round + 1;
+
+        Ok(())
+    }
+
+    fn update_trie(&mut self, _state_commit: &StateCommit) -> Result<Option<B256>, Error> {
+        Ok(match self.db.state_root_mode {
+            StateRootMode::MerklePatriciaTrie => Some(self.state.trie_root()),
+            StateRootMode::Legacy => None,
+        })
+    }
+
+    fn put_commit(
+        &mut self,
+        key: &CommitKey,
+        _commit_data: &Option<CommitData>,
+        receipts: CommitReceipts,
+    ) -> Result<(), Error> {
+        self.state.commits.insert(key.0, receipts);
+
+        Ok(())
+    }
+}
+
+impl DatabaseRef for MemoryDB {
+    type Error = Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let account = self.read()?.accounts.get(&address).cloned();
+
+        Ok(self.account_or_default(address, account).into())
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        Ok(self
+            .read()?
+            .contracts
+            .get(&code_hash)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        Ok(self
+            .read()?
+            .storage
+            .get(&address)
+            .and_then(|storage| storage.get(&index))
+            .copied()
+            .unwrap_or_default())
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        let state = self.read()?;
+
+        let Some((latest_block_number, _)) = state.block_hashes.last_key_value() else {
+            return Ok(B256::ZERO);
+        };
+
+        // Only the hashes of the most recent 256 committed blocks are accessible
+        if number > *latest_block_number || latest_block_number - number >= BLOCK_HASH_HISTORY {
+            return Ok(B256::ZERO);
+        }
+
+        Ok(state.block_hashes.get(&number).copied().unwrap_or_default())
+    }
+}
+
+impl StorageBackend for MemoryDB {
+    type Txn<'a> = MemoryTxn<'a>;
+
+    fn state_root_mode(&self) -> StateRootMode {
+        self.state_root_mode
+    }
+
+    fn genesis_info(&self) -> Option<GenesisInfo> {
+        self.genesis_info.clone()
+    }
+
+    fn set_genesis_info(&mut self, genesis_info: GenesisInfo) {
+        self.genesis_info.replace(genesis_info);
+    }
+
+    fn logger(&self) -> &Logger {
+        &self.logger
+    }
+
+    // Works on a copy of the state, which replaces the state once `apply` succeeded.
+    fn write<T>(
+        &self,
+        apply: impl FnOnce(&mut MemoryTxn<'_>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut state = self.write_state()?;
+        let mut txn = MemoryTxn {
+            db: self,
+            state: state.clone(),
+        };
+
+        let result = apply(&mut txn)?;
+        *state = txn.state;
+
+        Ok(result)
+    }
+
+    // Writes the accounts and storage of the commit to a copy of the state.
+    fn calculate_trie_root(&self, state_commit: &mut StateCommit) -> Result<B256, Error> {
+        if self.state_root_mode != StateRootMode::MerklePatriciaTrie {
+            return Err(Error::StateRootModeMismatch(self.state_root_mode));
+        }
+
+        let mut txn = MemoryTxn {
+            db: self,
+            state: self.read()?.clone(),
+        };
+
+        let StateCommit {
+            key, change_set, ..
+        } = state_commit;
+
+        for (address, account) in change_set.accounts.iter() {
+            txn.put_account(key.0, *address, account.as_ref())?;
+        }
+
+        for changeset in change_set.storage.iter() {
+            txn.write_storage(
+                changeset.address,
+                changeset.wipe_storage,
+                &present_slots(changeset),
+            )?;
+        }
+
+        Ok(txn.state.trie_root())
+    }
+
+    // The state grows on demand.
+    fn resize(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn is_block_committed(&self, block_number: u64) -> bool {
+        self.read()
+            .is_ok_and(|state| state.commits.contains_key(&block_number))
+    }
+
+    fn get_committed_receipt(
+        &self,
+        block_number: u64,
+        tx_hash: B256,
+    ) -> Result<(bool, Option<TxReceipt>), Error> {
+        match self.read()?.commits.get(&block_number) {
+            Some(receipts) => Ok((
+                true,
+                receipts
+                    .tx_receipts
+                    .iter()
+                    .find(|(hash, _)| *hash == tx_hash)
+                    .map(|(_, receipt)| receipt.clone()),
+            )),
+            None => Ok((false, None)),
+        }
+    }
+
+    fn get_committed_hashes(&self, block_number: u64) -> Result<Option<(B256, B256, B256)>, Error> {
+        Ok(self.read()?.commits.get(&block_number).map(|receipts| {
+            (
+                receipts.accounts_hash,
+                receipts.contracts_hash,
+                receipts.storage_hash,
+            )
+        }))
+    }
+
+    fn get_state_root(&self, block_number: u64) -> Result<Option<B256>, Error> {
+        Ok(self
+            .read()?
+            .commits
+            .get(&block_number)
+            .and_then(|receipts| receipts.state_root))
+    }
+
+    fn get_historical_account_info(
+        &self,
+        block_number: u64,
+        address: Address,
+    ) -> Result<(Option<AccountInfo>, bool), Error> {
+        if self.history_size.is_none() {
+            return Ok((None, false));
+        }
+
+        Ok(find_history(
+            &self.read()?.accounts_history,
+            block_number,
+            |changes| {
+                changes.get(&address).map(|data| AccountInfo {
+                    balance: data.balance,
+                    nonce: data.nonce,
+                    code_hash: data.code_hash,
+                    ..Default::default()
+                })
+            },
+        ))
+    }
+
+    fn get_historical_storage(
+        &self,
+        block_number: u64,
+        address: Address,
+        slot: U256,
+    ) -> Result<(Option<U256>, bool), Error> {
+        if self.history_size.is_none() {
+            return Ok((None, false));
+        }
+
+        Ok(find_history(
+            &self.read()?.storage_history,
+            block_number,
+            |changes| {
+                changes
+                    .get(&address)
+                    .and_then(|storage| storage.get(&slot))
+                    .copied()
+            },
+        ))
+    }
+
+    fn get_legacy_attributes(
+        &self,
+        address: Address,
+    ) -> Result<Option<LegacyAccountAttributes>, Error> {
+        Ok(self.read()?.legacy_attributes.get(&address).cloned())
+    }
+
+    fn get_legacy_cold_wallet(
+        &self,
+        address: LegacyAddress,
+    ) -> Result<Option<LegacyColdWallet>, Error> {
+        Ok(self.read()?.legacy_cold_wallets.get(&address).cloned())
+    }
+
+    fn get_state(&self) -> Result<(u64, u64), Error> {
+        let state = self.read()?;
+
+        let block_number = match state.blocks.last_key_value() {
+            Some((block_number, _)) => *block_number,
+            None => 0,
+        };
+
+        Ok((block_number, state.total_round))
+    }
+
+    fn get_block_header_bytes(&self, block_number: u64) -> Result<Retained<Bytes>, Error> {
+        Ok(retained(self.read()?.blocks.get(&block_number).cloned()))
+    }
+
+    fn get_block_hash(&self, block_number: u64) -> Result<Option<B256>, Error> {
+        Ok(self.read()?.block_hashes.get(&block_number).copied())
+    }
+
+    fn get_block_number_by_hash(&self, block_hash: B256) -> Result<Option<u64>, Error> {
+        Ok(self.read()?.block_numbers.get(&block_hash).copied())
+    }
+
+    fn get_proof_bytes(&self, block_number: u64) -> Result<Retained<Bytes>, Error> {
+        Ok(retained(self.read()?.proofs.get(&block_number).cloned()))
+    }
+
+    fn get_transaction_bytes(&self, key: String) -> Result<Retained<Bytes>, Error> {
+        Ok(retained(self.read()?.transactions.get(&key).cloned()))
+    }
+
+    fn get_transaction_key_by_hash(&self, tx_hash: B256) -> Result<Option<String>, Error> {
+        Ok(self.read()?.transaction_keys.get(&tx_hash).cloned())
+    }
+}
+
+#[test]
+fn test_memory_db() {
+    use revm::primitives::address;
+
+    use crate::{
+        db::{PersistentDB, PersistentDBOptions},
+        state_commit::commit_to_db,
+        trie::pending_commit,
+    };
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    // Both backends are fed the same commits and have to end up in the same state
+    let mut persistent = PersistentDB::new(
+        PersistentDBOptions::new(path.path().to_path_buf())
+            .with_history_size(10)
+            .with_state_root_mode(StateRootMode::MerklePatriciaTrie),
+    )
+    .expect("database");
+    let mut memory = MemoryDB::new()
+        .with_history_size(10)
+        .with_state_root_mode(StateRootMode::MerklePatriciaTrie);
+
+    let alice = address!("1000000000000000000000000000000000000001");
+    let bob = address!("2000000000000000000000000000000000000002");
+
+    let commits = || {
+        vec![
+            (
+                pending_commit(
+                    1,
+                    vec![(alice, 100, vec![(1, 0, 10), (2, 0, 20)]), (bob, 5, vec![])],
+                ),
+                Some(CommitData {
+                    commit_round: 2,
+                    block_hash: B256::repeat_byte(1),
+                    block: Bytes::from_static(&[1]),
+                    proof: Bytes::from_static(&[2]),
+                    transaction_hashes: vec![B256::repeat_byte(0x11)],
+                    transactions: vec![Bytes::from_static(&[3])],
+                }),
+            ),
+            (
+                pending_commit(2, vec![(alice, 50, vec![(1, 10, 0), (3, 0, 30)])]),
+                None,
+            ),
+        ]
+    };
+
+    for (mut pending, commit_data) in commits() {
+        let block_number = pending.key.0;
+        let state_root =
+            crate::state_root::calculate(&mut memory, &mut pending, B256::ZERO).unwrap();
+
+        commit_to_db(&mut memory, pending, commit_data).expect("commit");
+        assert_eq!(
+            memory.get_state_root(block_number).unwrap(),
+            Some(state_root)
+        );
+    }
+
+    for (pending, commit_data) in commits() {
+        commit_to_db(&mut persistent, pending, commit_data).expect("commit");
+    }
+
+    for block_number in 1..=2 {
+        assert_eq!(
+            memory.get_state_root(block_number).unwrap(),
+            persistent.get_state_root(block_number).unwrap()
+        );
+        assert_eq!(
+            memory.get_committed_hashes(block_number).unwrap(),
+            persistent.get_committed_hashes(block_number).unwrap()
+        );
+    }
+
+    for address in [alice, bob] {
+        assert_eq!(
+            memory.basic_ref(address).unwrap(),
+            persistent.basic_ref(address).unwrap()
+        );
+
+        for slot in 1..=3 {
+            let slot = U256::from(slot);
+            assert_eq!(
+                memory.storage_ref(address, slot).unwrap(),
+                persistent.storage_ref(address, slot).unwrap()
+            );
+            assert_eq!(
+                memory.get_historical_storage(1, address, slot).unwrap(),
+                persistent.get_historical_storage(1, address, slot).unwrap()
+            );
+        }
+
+        assert_eq!(
+            memory.get_historical_account_info(1, address).unwrap(),
+            persistent.get_historical_account_info(1, address).unwrap()
+        );
+    }
+
+    assert_eq!(memory.get_state().unwrap(), (1, 3));
+    assert_eq!(memory.get_state().unwrap(), persistent.get_state().unwrap());
+    assert_eq!(memory.block_hash_ref(1).unwrap(), B256::repeat_byte(1));
+    assert_eq!(
+        memory
+            .get_block_number_by_hash(B256::repeat_byte(1))
+            .unwrap(),
+        Some(1)
+    );
+    assert_eq!(
+        memory
+            .get_transaction_key_by_hash(B256::repeat_byte(0x11))
+            .unwrap(),
+        Some("1-0".to_owned())
+    );
+    assert_eq!(
+        memory
+            .get_transaction_bytes("1-0".to_owned())
+            .unwrap()
+            .present(),
+        Some(Bytes::from_static(&[3]))
+    );
+    assert!(matches!(
+        memory.get_proof_bytes(2).unwrap(),
+        Retained::Absent
+    ));
+}
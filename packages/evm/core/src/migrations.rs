@@ -0,0 +1,928 @@
+use std::{collections::HashMap, ops::Bound};
+
+use heed::{RwTxn, types::SerdeBincode};
+use revm::{
+    primitives::{Address, B256, Bytes, Log},
+    state::AccountInfo,
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    db::{
+        AddressWrapper, CommitReceipts, Error, HashWrapper, InnerStorage, PersistentDB,
+        StaticStringWrapper, map_db_full,
+    },
+    deployment,
+    logger::LogLevel,
+    logs_bloom,
+    pruning::{self, PrunedTable},
+    receipt::{ReceiptLocation, RevertReason, TxReceipt},
+};
+
+// Version of the database layout, i.e. the named databases and the encoding of their values.
+// Databases created before the version got introduced are treated as version 0.
+pub const SCHEMA_VERSION: u32 = 10;
+
+const SCHEMA_VERSION_KEY: StaticStringWrapper = StaticStringWrapper("schema_version");
+const MIGRATION_CURSOR_KEY: StaticStringWrapper = StaticStringWrapper("migration_cursor");
+
+// Number of commits migrated per migration step.
+const COMMITS_BATCH_SIZE: usize = 1000;
+
+// Number of block hashes migrated per migration step.
+const BLOCK_HASHES_BATCH_SIZE: usize = 10_000;
+
+// Number of accounts migrated per migration step.
+const ACCOUNTS_BATCH_SIZE: usize = 10_000;
+
+// Number of contracts migrated per migration step.
+const CONTRACTS_BATCH_SIZE: usize = 10_000;
+
+// Upgrades the database from `version - 1` to `version`. `apply` is called repeatedly with the
+// cursor returned by the previous call until it returns `None`. Every call runs in its own write
+// transaction together with the progress, thus an interrupted migration resumes where it stopped.
+pub(crate) struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub apply: fn(&mut RwTxn, &InnerStorage, Option<Bytes>) -> Result<Option<Bytes>, Error>,
+}
+
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "index block hashes by number",
+        apply: index_block_hashes,
+    },
+    Migration {
+        version: 2,
+        description: "index receipts by transaction hash",
+        apply: index_receipt_locations,
+    },
+    Migration {
+        version: 3,
+        description: "store receipts in execution order",
+        apply: order_receipts,
+    },
+    Migration {
+        version: 4,
+        description: "store revert reasons",
+        apply: decode_revert_reasons,
+    },
+    Migration {
+        version: 5,
+        description: "index logs by address and topic",
+        apply: index_logs,
+    },
+    Migration {
+        version: 6,
+        description: "store logs bloom and state root",
+        apply: store_commit_metadata,
+    },
+    Migration {
+        version: 7,
+        description: "index accounts by balance",
+        apply: index_balances,
+    },
+    Migration {
+        version: 8,
+        description: "register contract deployments",
+        apply: index_deployments,
+    },
+    Migration {
+        version: 9,
+        description: "count code references",
+        apply: count_code_references,
+    },
+    Migration {
+        version: 10,
+        description: "track unreferenced code",
+        apply: track_orphaned_code,
+    },
+];
+
+// Encoding of the commits before schema version 3.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CommitReceiptsV2 {
+    pub accounts_hash: B256,
+    pub storage_hash: B256,
+    pub contracts_hash: B256,
+    pub tx_receipts: HashMap<B256, TxReceiptV2>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TxReceiptV2 {
+    pub gas_used: u64,
+    pub gas_refunded: u64,
+    pub success: u8,
+    pub contract_address: Option<String>,
+    pub logs: Option<Vec<Log>>,
+    pub output: Option<Bytes>,
+}
+
+// Encoding of the commits before schema version 4.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CommitReceiptsV3 {
+    pub accounts_hash: B256,
+    pub storage_hash: B256,
+    pub contracts_hash: B256,
+    pub tx_receipts: Vec<(B256, TxReceiptV3)>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TxReceiptV3 {
+    pub gas_used: u64,
+    pub gas_refunded: u64,
+    pub success: u8,
+    pub contract_address: Option<String>,
+    pub logs: Option<Vec<Log>>,
+    pub output: Option<Bytes>,
+    pub transaction_index: u64,
+    pub cumulative_gas_used: u64,
+    pub first_log_index: u64,
+    pub from: Option<Address>,
+    pub to: Option<Address>,
+    pub effective_gas_price: Option<u128>,
+}
+
+// Encoding of the commits before schema version 6.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CommitReceiptsV5 {
+    pub accounts_hash: B256,
+    pub storage_hash: B256,
+    pub contracts_hash: B256,
+    pub tx_receipts: Vec<(B256, TxReceipt)>,
+}
+
+// Reads the next batch of commits encoded as `T`. The cursor is the next block number to migrate.
+fn read_commits<T>(
+    txn: &RwTxn,
+    inner: &InnerStorage,
+    cursor: Option<Bytes>,
+) -> Result<(Vec<(u64, T)>, Option<Bytes>), Error>
+where
+    T: Serialize + DeserializeOwned + 'static,
+{
+    let first = match cursor {
+        Some(cursor) => match <[u8; 8]>::try_from(cursor.as_ref()) {
+            Ok(cursor) => u64::from_le_bytes(cursor),
+            Err(_) => {
+                return Err(Error::UnknownSchema(format!(
+                    "malformed migration cursor {}",
+                    cursor
+                )));
+            }
+        },
+        None => 0,
+    };
+
+    let mut commits = inner
+        .commits
+        .remap_data_type::<SerdeBincode<T>>()
+        .range(txn, &(first..))?
+        .take(COMMITS_BATCH_SIZE + 1)
+        .collect::<Result<Vec<_>, heed::Error>>()?;
+
+    let next = match commits.len() > COMMITS_BATCH_SIZE {
+        true => commits.pop().map(|(block_number, _)| block_number),
+        false => None,
+    };
+
+    Ok((
+        commits,
+        next.map(|block_number| Bytes::from_iter(block_number.to_le_bytes())),
+    ))
+}
+
+// Fills the block number to hash index from the hash to number index, which is the only one
+// written by older versions. The cursor is the next block hash to migrate.
+fn index_block_hashes(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    cursor: Option<Bytes>,
+) -> Result<Option<Bytes>, Error> {
+    let first = match cursor {
+        Some(cursor) if cursor.len() == B256::len_bytes() => {
+            Bound::Included(HashWrapper(B256::from_slice(&cursor)))
+        }
+        Some(cursor) => {
+            return Err(Error::UnknownSchema(format!(
+                "malformed migration cursor {}",
+                cursor
+            )));
+        }
+        None => Bound::Unbounded,
+    };
+
+    let mut block_hashes = inner
+        .blocks_hash_number
+        .range(txn, &(first, Bound::Unbounded))?
+        .take(BLOCK_HASHES_BATCH_SIZE + 1)
+        .collect::<Result<Vec<_>, heed::Error>>()?;
+
+    let next = match block_hashes.len() > BLOCK_HASHES_BATCH_SIZE {
+        true => block_hashes
+            .pop()
+            .map(|(block_hash, _)| Bytes::copy_from_slice(block_hash.0.as_slice())),
+        false => None,
+    };
+
+    for (block_hash, block_number) in block_hashes {
+        inner
+            .blocks_number_hash
+            .put(txn, &block_number, &block_hash)?;
+    }
+
+    Ok(next)
+}
+
+// Position of a stored transaction within its block.
+fn read_transaction_index(
+    txn: &RwTxn,
+    inner: &InnerStorage,
+    tx_hash: B256,
+) -> Result<Option<u64>, Error> {
+    // transaction keys have the format "{block_number}-{sequence}"
+    Ok(inner
+        .transactions_hash_key
+        .get(txn, &HashWrapper(tx_hash))?
+        .and_then(|key| key.rsplit_once('-')?.1.parse().ok()))
+}
+
+// Fills the receipt index from the stored commits.
+fn index_receipt_locations(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    cursor: Option<Bytes>,
+) -> Result<Option<Bytes>, Error> {
+    let (commits, next) = read_commits::<CommitReceiptsV2>(txn, inner, cursor)?;
+
+    for (block_number, commit) in commits {
+        let block_hash = inner
+            .blocks_number_hash
+            .get(txn, &block_number)?
+            .map(|block_hash| block_hash.0)
+            .unwrap_or_default();
+
+        for tx_hash in commit.tx_receipts.keys() {
+            let transaction_index = read_transaction_index(txn, inner, *tx_hash)?;
+
+            inner.receipt_locations.put(
+                txn,
+                &HashWrapper(*tx_hash),
+                &ReceiptLocation {
+                    block_number,
+                    block_hash,
+                    transaction_index,
+                },
+            )?;
+        }
+    }
+
+    Ok(next)
+}
+
+// Re-encodes the receipts of the stored commits as an ordered list. The order is taken from the
+// stored transactions, receipts of pruned transactions are ordered by hash. Sender, recipient and
+// gas price were never stored and remain unknown.
+fn order_receipts(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    cursor: Option<Bytes>,
+) -> Result<Option<Bytes>, Error> {
+    let (commits, next) = read_commits::<CommitReceiptsV2>(txn, inner, cursor)?;
+
+    for (block_number, commit) in commits {
+        let mut receipts = Vec::with_capacity(commit.tx_receipts.len());
+        for (tx_hash, receipt) in commit.tx_receipts {
+            let transaction_index = read_transaction_index(txn, inner, tx_hash)?;
+            receipts.push((transaction_index.unwrap_or(u64::MAX), tx_hash, receipt));
+        }
+
+        receipts.sort_by_key(|(transaction_index, tx_hash, _)| (*transaction_index, *tx_hash));
+
+        let mut cumulative_gas_used = 0;
+        let mut log_index = 0;
+
+        let tx_receipts = receipts
+            .into_iter()
+            .enumerate()
+            .map(|(position, (transaction_index, tx_hash, receipt))| {
+                cumulative_gas_used += receipt.gas_used;
+
+                let first_log_index = log_index;
+                log_index += receipt.logs.as_ref().map_or(0, |logs| logs.len() as u64);
+
+                let receipt = TxReceiptV3 {
+                    gas_used: receipt.gas_used,
+                    gas_refunded: receipt.gas_refunded,
+                    success: receipt.success,
+                    contract_address: receipt.contract_address,
+                    logs: receipt.logs,
+                    output: receipt.output,
+                    transaction_index: match transaction_index {
+                        u64::MAX => position as u64,
+                        transaction_index => transaction_index,
+                    },
+                    cumulative_gas_used,
+                    first_log_index,
+                    from: None,
+                    to: None,
+                    effective_gas_price: None,
+                };
+
+                (tx_hash, receipt)
+            })
+            .collect();
+
+        inner
+            .commits
+            .remap_data_type::<SerdeBincode<CommitReceiptsV3>>()
+            .put(
+                txn,
+                &block_number,
+                &CommitReceiptsV3 {
+                    accounts_hash: commit.accounts_hash,
+                    storage_hash: commit.storage_hash,
+                    contracts_hash: commit.contracts_hash,
+                    tx_receipts,
+                },
+            )?;
+    }
+
+    Ok(next)
+}
+
+// Decodes the revert reasons from the stored output of reverted transactions. Halted transactions
+// have no output and their halt reason remains unknown.
+fn decode_revert_reasons(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    cursor: Option<Bytes>,
+) -> Result<Option<Bytes>, Error> {
+    let (commits, next) = read_commits::<CommitReceiptsV3>(txn, inner, cursor)?;
+
+    for (block_number, commit) in commits {
+        let tx_receipts = commit
+            .tx_receipts
+            .into_iter()
+            .map(|(tx_hash, receipt)| {
+                let revert_reason = match (receipt.success, &receipt.output) {
+                    (0, Some(output)) => Some(RevertReason::decode(output)),
+                    _ => None,
+                };
+
+                let receipt = TxReceipt {
+                    gas_used: receipt.gas_used,
+                    gas_refunded: receipt.gas_refunded,
+                    success: receipt.success,
+                    contract_address: receipt.contract_address,
+                    logs: receipt.logs,
+                    output: receipt.output,
+                    transaction_index: receipt.transaction_index,
+                    cumulative_gas_used: receipt.cumulative_gas_used,
+                    first_log_index: receipt.first_log_index,
+                    from: receipt.from,
+                    to: receipt.to,
+                    effective_gas_price: receipt.effective_gas_price,
+                    revert_reason,
+                    halt_reason: None,
+                };
+
+                (tx_hash, receipt)
+            })
+            .collect();
+
+        inner
+            .commits
+            .remap_data_type::<SerdeBincode<CommitReceiptsV5>>()
+            .put(
+                txn,
+                &block_number,
+                &CommitReceiptsV5 {
+                    accounts_hash: commit.accounts_hash,
+                    storage_hash: commit.storage_hash,
+                    contracts_hash: commit.contracts_hash,
+                    tx_receipts,
+                },
+            )?;
+    }
+
+    Ok(next)
+}
+
+// Fills the log index from the stored receipts.
+fn index_logs(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    cursor: Option<Bytes>,
+) -> Result<Option<Bytes>, Error> {
+    let (commits, next) = read_commits::<CommitReceiptsV5>(txn, inner, cursor)?;
+
+    for (block_number, commit) in commits {
+        inner
+            .log_index
+            .insert(txn, block_number, &commit.tx_receipts)?;
+    }
+
+    Ok(next)
+}
+
+// Stores the logs bloom of the commits and the state root if the Merkle Patricia Trie is in use.
+// Blooms of pruned receipts and legacy state roots cannot be restored and remain unknown.
+fn store_commit_metadata(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    cursor: Option<Bytes>,
+) -> Result<Option<Bytes>, Error> {
+    let (commits, next) = read_commits::<CommitReceiptsV5>(txn, inner, cursor)?;
+
+    for (block_number, commit) in commits {
+        let logs_bloom = match pruning::is_pruned(txn, inner, PrunedTable::Receipts, block_number)?
+        {
+            true => None,
+            false => Some(logs_bloom::from_receipts(&commit.tx_receipts)),
+        };
+
+        let state_root = match &inner.trie {
+            Some(trie) => trie
+                .state_roots
+                .get(txn, &block_number)?
+                .map(|state_root| state_root.0),
+            None => None,
+        };
+
+        inner.commits.put(
+            txn,
+            &block_number,
+            &CommitReceipts {
+                accounts_hash: commit.accounts_hash,
+                storage_hash: commit.storage_hash,
+                contracts_hash: commit.contracts_hash,
+                logs_bloom,
+                state_root,
+                tx_receipts: commit.tx_receipts,
+            },
+        )?;
+    }
+
+    Ok(next)
+}
+
+// Reads the next batch of accounts. The cursor is the next address to migrate.
+fn read_accounts(
+    txn: &RwTxn,
+    inner: &InnerStorage,
+    cursor: Option<Bytes>,
+) -> Result<(Vec<(AddressWrapper, AccountInfo)>, Option<Bytes>), Error> {
+    let first = match cursor {
+        Some(cursor) if cursor.len() == Address::len_bytes() => {
+            Bound::Included(AddressWrapper(Address::from_slice(&cursor)))
+        }
+        Some(cursor) => {
+            return Err(Error::UnknownSchema(format!(
+                "malformed migration cursor {}",
+                cursor
+            )));
+        }
+        None => Bound::Unbounded,
+    };
+
+    let mut accounts = inner
+        .accounts
+        .range(txn, &(first, Bound::Unbounded))?
+        .take(ACCOUNTS_BATCH_SIZE + 1)
+        .collect::<Result<Vec<_>, heed::Error>>()?;
+
+    let next = match accounts.len() > ACCOUNTS_BATCH_SIZE {
+        true => accounts
+            .pop()
+            .map(|(address, _)| Bytes::copy_from_slice(address.0.as_slice())),
+        false => None,
+    };
+
+    Ok((accounts, next))
+}
+
+// Fills the balance index from the accounts.
+fn index_balances(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    cursor: Option<Bytes>,
+) -> Result<Option<Bytes>, Error> {
+    let (accounts, next) = read_accounts(txn, inner, cursor)?;
+
+    for (address, info) in accounts {
+        inner
+            .balance_index
+            .update(txn, address.0, None, Some(&info))?;
+    }
+
+    Ok(next)
+}
+
+// Registers the contracts deployed by the committed receipts. Deployments within pruned receipts
+// cannot be restored and remain unknown.
+fn index_deployments(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    cursor: Option<Bytes>,
+) -> Result<Option<Bytes>, Error> {
+    let (commits, next) = read_commits::<CommitReceipts>(txn, inner, cursor)?;
+
+    for (block_number, commit) in commits {
+        deployment::insert(txn, inner, block_number, &commit.tx_receipts)?;
+    }
+
+    Ok(next)
+}
+
+// Counts the accounts referencing each code hash.
+fn count_code_references(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    cursor: Option<Bytes>,
+) -> Result<Option<Bytes>, Error> {
+    let (accounts, next) = read_accounts(txn, inner, cursor)?;
+
+    // Only references are added, the block number is used when releasing one
+    for (_, info) in accounts {
+        inner.code_references.update(txn, 0, None, Some(&info))?;
+    }
+
+    Ok(next)
+}
+
+// Marks the stored bytecode without references as orphaned. When the last reference got released
+// is unknown, thus the latest commit is assumed. The cursor is the next code hash to migrate.
+fn track_orphaned_code(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    cursor: Option<Bytes>,
+) -> Result<Option<Bytes>, Error> {
+    let first = match cursor {
+        Some(cursor) if cursor.len() == B256::len_bytes() => {
+            Bound::Included(HashWrapper(B256::from_slice(&cursor)))
+        }
+        Some(cursor) => {
+            return Err(Error::UnknownSchema(format!(
+                "malformed migration cursor {}",
+                cursor
+            )));
+        }
+        None => Bound::Unbounded,
+    };
+
+    let mut code_hashes = inner
+        .contracts
+        .remap_data_type::<heed::types::DecodeIgnore>()
+        .range(txn, &(first, Bound::Unbounded))?
+        .take(CONTRACTS_BATCH_SIZE + 1)
+        .map(|item| item.map(|(code_hash, _)| code_hash.0))
+        .collect::<Result<Vec<_>, heed::Error>>()?;
+
+    let next = match code_hashes.len() > CONTRACTS_BATCH_SIZE {
+        true => code_hashes
+            .pop()
+            .map(|code_hash| Bytes::copy_from_slice(code_hash.as_slice())),
+        false => None,
+    };
+
+    let latest_block_number = inner
+        .commits
+        .remap_data_type::<heed::types::DecodeIgnore>()
+        .last(txn)?
+        .map(|(block_number, _)| block_number)
+        .unwrap_or_default();
+
+    for code_hash in code_hashes {
+        inner
+            .code_references
+            .insert_code(txn, latest_block_number, code_hash)?;
+    }
+
+    Ok(next)
+}
+
+pub(crate) fn read_schema_version(
+    txn: &heed::RoTxn,
+    state: &heed::Database<StaticStringWrapper, heed::types::SerdeBincode<Bytes>>,
+) -> Result<Option<u32>, Error> {
+    match state.get(txn, &SCHEMA_VERSION_KEY)? {
+        Some(version) => match <[u8; 4]>::try_from(version.as_ref()) {
+            Ok(version) => Ok(Some(u32::from_le_bytes(version))),
+            Err(_) => Err(Error::UnknownSchema(format!(
+                "malformed schema version {}",
+                version
+            ))),
+        },
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn write_schema_version(
+    txn: &mut RwTxn,
+    state: &heed::Database<StaticStringWrapper, heed::types::SerdeBincode<Bytes>>,
+    version: u32,
+) -> Result<(), Error> {
+    state.put(
+        txn,
+        &SCHEMA_VERSION_KEY,
+        &Bytes::from_iter(version.to_le_bytes()),
+    )?;
+
+    Ok(())
+}
+
+impl PersistentDB {
+    pub fn schema_version(&self) -> Result<u32, Error> {
+        let rtxn = self.env.read_txn()?;
+        let inner = &self.inner;
+
+        Ok(read_schema_version(&rtxn, &inner.state)?.unwrap_or_default())
+    }
+
+    // Applies all migrations newer than the stored schema version in order.
+    pub(crate) fn run_migrations(&self, migrations: &[Migration]) -> Result<(), Error> {
+        let Some(target_version) = migrations.last().map(|migration| migration.version) else {
+            return Ok(());
+        };
+
+        loop {
+            let inner = &self.inner;
+            let mut wtxn = self.write_txn()?;
+
+            let version = read_schema_version(&wtxn, &inner.state)?.unwrap_or_default();
+            if version >= target_version {
+                return Ok(());
+            }
+
+            let migration = migrations
+                .iter()
+                .find(|migration| migration.version == version + 1)
+                .expect("migration to next schema version");
+
+            let cursor = inner.state.get(&wtxn, &MIGRATION_CURSOR_KEY)?;
+            if cursor.is_none() {
+                self.logger.log(
+                    LogLevel::Info,
+                    format!(
+                        "migrating database schema {} -> {} ({})",
+                        version, migration.version, migration.description
+                    ),
+                );
+            }
+
+            let result = (migration.apply)(&mut wtxn, inner, cursor).and_then(|cursor| {
+                match cursor {
+                    Some(cursor) => inner.state.put(&mut wtxn, &MIGRATION_CURSOR_KEY, &cursor)?,
+                    None => {
+                        inner.state.delete(&mut wtxn, &MIGRATION_CURSOR_KEY)?;
+                        write_schema_version(&mut wtxn, &inner.state, migration.version)?;
+                    }
+                }
+
+                Ok(())
+            });
+
+            match result.map_err(map_db_full) {
+                Ok(()) => wtxn.commit().map_err(|err| map_db_full(err.into()))?,
+                Err(Error::DbFull) => {
+                    wtxn.abort();
+                    self.resize()?;
+                }
+                Err(err) => {
+                    wtxn.abort();
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_schema_version() {
+    use alloy_sol_types::SolError;
+    use revm::DatabaseRef;
+
+    use crate::{
+        db::{PersistentDBOptions, StaticStringWrapper},
+        log_index::LogFilter,
+    };
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+    assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+
+    let block_hash = B256::repeat_byte(1);
+    let tx_hashes = [B256::repeat_byte(2), B256::repeat_byte(3)];
+    let contract = Address::repeat_byte(4);
+
+    // A database which predates versioning gets migrated
+    {
+        let mut wtxn = db.env.write_txn().unwrap();
+        let inner = &db.inner;
+
+        let receipt = |gas_used| TxReceiptV2 {
+            gas_used,
+            gas_refunded: 0,
+            success: 1,
+            contract_address: None,
+            logs: Some(vec![Log::default()]),
+            output: None,
+        };
+
+        inner
+            .commits
+            .remap_data_type::<SerdeBincode<CommitReceiptsV2>>()
+            .put(
+                &mut wtxn,
+                &1,
+                &CommitReceiptsV2 {
+                    accounts_hash: B256::ZERO,
+                    storage_hash: B256::ZERO,
+                    contracts_hash: B256::ZERO,
+                    tx_receipts: HashMap::from([
+                        (
+                            tx_hashes[0],
+                            TxReceiptV2 {
+                                contract_address: Some(contract.to_string()),
+                                ..receipt(100)
+                            },
+                        ),
+                        (
+                            tx_hashes[1],
+                            TxReceiptV2 {
+                                success: 0,
+                                logs: None,
+                                output: Some(Bytes::from(
+                                    alloy_sol_types::Revert {
+                                        reason: "failed".to_owned(),
+                                    }
+                                    .abi_encode(),
+                                )),
+                                ..receipt(200)
+                            },
+                        ),
+                    ]),
+                },
+            )
+            .unwrap();
+        // older versions only indexed the block number by hash
+        inner
+            .blocks_hash_number
+            .put(&mut wtxn, &HashWrapper(block_hash), &1)
+            .unwrap();
+        inner.blocks_number_hash.clear(&mut wtxn).unwrap();
+
+        // the second transaction hash got executed first
+        inner
+            .transactions_hash_key
+            .put(&mut wtxn, &HashWrapper(tx_hashes[0]), &"1-1".to_owned())
+            .unwrap();
+        inner
+            .transactions_hash_key
+            .put(&mut wtxn, &HashWrapper(tx_hashes[1]), &"1-0".to_owned())
+            .unwrap();
+
+        inner.state.delete(&mut wtxn, &SCHEMA_VERSION_KEY).unwrap();
+        wtxn.commit().unwrap();
+    }
+    assert_eq!(db.schema_version().unwrap(), 0);
+
+    let db = PersistentDB::new_with_env(
+        db.env.clone(),
+        PersistentDBOptions::new(path.path().to_path_buf()),
+    )
+    .expect("database");
+    assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+    assert_eq!(db.get_block_hash(1).unwrap(), Some(block_hash));
+    assert_eq!(db.block_hash_ref(1).unwrap(), block_hash);
+
+    let (_, receipts) = db.get_receipts(None, 1).unwrap();
+    assert_eq!(
+        receipts[0]
+            .1
+            .iter()
+            .map(|(tx_hash, receipt)| (
+                *tx_hash,
+                receipt.transaction_index,
+                receipt.cumulative_gas_used,
+                receipt.first_log_index
+            ))
+            .collect::<Vec<_>>(),
+        vec![(tx_hashes[1], 0, 200, 0), (tx_hashes[0], 1, 300, 0)]
+    );
+    assert_eq!(
+        receipts[0].1[0].1.revert_reason,
+        Some(RevertReason::Error("failed".to_owned()))
+    );
+    assert_eq!(receipts[0].1[1].1.revert_reason, None);
+    assert_eq!(
+        db.get_receipt_by_hash(tx_hashes[0])
+            .unwrap()
+            .unwrap()
+            .location,
+        ReceiptLocation {
+            block_number: 1,
+            block_hash,
+            transaction_index: Some(1),
+        }
+    );
+
+    let (_, logs) = db
+        .get_logs(
+            &LogFilter {
+                from_block: 1,
+                to_block: 1,
+                addresses: vec![Address::ZERO],
+                ..Default::default()
+            },
+            None,
+            10,
+        )
+        .unwrap();
+    assert_eq!(
+        logs.iter()
+            .map(|log| (log.transaction_hash, log.transaction_index, log.log_index))
+            .collect::<Vec<_>>(),
+        vec![(tx_hashes[0], 1, 0)]
+    );
+    assert_eq!(
+        db.get_logs_bloom(1).unwrap(),
+        Some(crate::logs_bloom::from_receipts(&receipts[0].1))
+    );
+    assert_eq!(db.get_state_root(1).unwrap(), None);
+    assert_eq!(
+        db.get_contract_deployment(contract).unwrap(),
+        Some(crate::deployment::ContractDeployment {
+            deployer: None,
+            transaction_hash: tx_hashes[0],
+            block_number: 1,
+            code_hash: revm::primitives::KECCAK_EMPTY,
+        })
+    );
+
+    // Migrations are applied in batches and resumed from the stored cursor
+    let migrations = [
+        Migration {
+            version: SCHEMA_VERSION + 1,
+            description: "count to three",
+            apply: |wtxn, inner, cursor| {
+                let count = cursor.map(|cursor| cursor[0]).unwrap_or_default() + 1;
+                inner.state.put(
+                    wtxn,
+                    &StaticStringWrapper("count"),
+                    &Bytes::from_iter([count]),
+                )?;
+
+                Ok((count < 3).then(|| Bytes::from_iter([count])))
+            },
+        },
+        Migration {
+            version: SCHEMA_VERSION + 2,
+            description: "fail",
+            apply: |_, _, _| Err(Error::UnknownSchema("failed migration".to_owned())),
+        },
+    ];
+
+    assert!(db.run_migrations(&migrations).is_err());
+    assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION + 1);
+    {
+        let rtxn = db.env.read_txn().unwrap();
+        let inner = &db.inner;
+        assert_eq!(
+            inner
+                .state
+                .get(&rtxn, &StaticStringWrapper("count"))
+                .unwrap(),
+            Some(Bytes::from_iter([3]))
+        );
+        assert!(
+            inner
+                .state
+                .get(&rtxn, &MIGRATION_CURSOR_KEY)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    // Newer layouts are refused
+    {
+        let mut wtxn = db.env.write_txn().unwrap();
+        write_schema_version(&mut wtxn, &db.inner.state, SCHEMA_VERSION + 1).unwrap();
+        wtxn.commit().unwrap();
+    }
+
+    assert!(matches!(
+        PersistentDB::new_with_env(
+            db.env.clone(),
+            PersistentDBOptions::new(path.path().to_path_buf())
+        ),
+        Err(Error::UnknownSchema(_))
+    ));
+}
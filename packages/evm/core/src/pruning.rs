@@ -0,0 +1,518 @@
+use heed::RwTxn;
+use revm::primitives::Bytes;
+
+use crate::db::{
+    CommitReceipts, Error, HashWrapper, InnerStorage, PersistentDB, StaticStringWrapper,
+    StringWrapper,
+};
+
+// Upper bound of blocks pruned per table in a single commit. Enabling pruning on an existing
+// database thus catches up over multiple commits instead of stalling a single one.
+const PRUNE_BATCH_SIZE: u64 = 100;
+
+// Number of most recent blocks for which the data is kept, `None` keeps the data forever.
+// Receipts are kept at least as long as transactions, their hashes are required to prune the
+// transaction hash index.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub blocks: Option<u64>,
+    pub proofs: Option<u64>,
+    pub transactions: Option<u64>,
+    pub receipts: Option<u64>,
+}
+
+// Result of reading data which is subject to pruning.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Retained<T> {
+    Present(T),
+    Pruned,
+    // Never existed
+    Absent,
+}
+
+impl<T> Retained<T> {
+    pub fn present(self) -> Option<T> {
+        match self {
+            Retained::Present(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn is_pruned(&self) -> bool {
+        matches!(self, Retained::Pruned)
+    }
+}
+
+// Inclusive range of block numbers which have been pruned from a table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrunedRange {
+    pub first: u64,
+    pub last: u64,
+}
+
+impl PrunedRange {
+    pub fn contains(&self, block_number: u64) -> bool {
+        (self.first..=self.last).contains(&block_number)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PruneState {
+    pub blocks: Option<PrunedRange>,
+    pub proofs: Option<PrunedRange>,
+    pub transactions: Option<PrunedRange>,
+    pub receipts: Option<PrunedRange>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum PrunedTable {
+    Blocks,
+    Proofs,
+    Transactions,
+    Receipts,
+}
+
+impl PrunedTable {
+    fn key(self) -> StaticStringWrapper {
+        StaticStringWrapper(match self {
+            PrunedTable::Blocks => "pruned_blocks",
+            PrunedTable::Proofs => "pruned_proofs",
+            PrunedTable::Transactions => "pruned_transactions",
+            PrunedTable::Receipts => "pruned_receipts",
+        })
+    }
+}
+
+pub(crate) fn read_pruned_range(
+    txn: &heed::RoTxn,
+    inner: &InnerStorage,
+    table: PrunedTable,
+) -> Result<Option<PrunedRange>, Error> {
+    Ok(inner.state.get(txn, &table.key())?.map(|range| {
+        assert_eq!(range.len(), 16);
+        PrunedRange {
+            first: u64::from_le_bytes(range[..8].try_into().expect("8 bytes")),
+            last: u64::from_le_bytes(range[8..].try_into().expect("8 bytes")),
+        }
+    }))
+}
+
+fn write_pruned_range(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    table: PrunedTable,
+    range: PrunedRange,
+) -> Result<(), Error> {
+    let mut value = Vec::with_capacity(16);
+    value.extend_from_slice(&range.first.to_le_bytes());
+    value.extend_from_slice(&range.last.to_le_bytes());
+
+    inner.state.put(txn, &table.key(), &Bytes::from(value))?;
+
+    Ok(())
+}
+
+// Returns whether `block_number` lies in the pruned range of `table`.
+pub(crate) fn is_pruned(
+    txn: &heed::RoTxn,
+    inner: &InnerStorage,
+    table: PrunedTable,
+    block_number: u64,
+) -> Result<bool, Error> {
+    Ok(read_pruned_range(txn, inner, table)?.is_some_and(|range| range.contains(block_number)))
+}
+
+impl PersistentDB {
+    pub fn prune_state(&self) -> Result<PruneState, Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        Ok(PruneState {
+            blocks: read_pruned_range(&rtxn, inner, PrunedTable::Blocks)?,
+            proofs: read_pruned_range(&rtxn, inner, PrunedTable::Proofs)?,
+            transactions: read_pruned_range(&rtxn, inner, PrunedTable::Transactions)?,
+            receipts: read_pruned_range(&rtxn, inner, PrunedTable::Receipts)?,
+        })
+    }
+
+    // Prunes the oldest data outside of the retention window of `block_number`, at most
+    // `PRUNE_BATCH_SIZE` blocks per table.
+    pub(crate) fn prune(
+        &self,
+        txn: &mut RwTxn,
+        inner: &InnerStorage,
+        block_number: u64,
+    ) -> Result<(), Error> {
+        let retention = self.retention;
+
+        if let Some(range) = next_prune_range(
+            txn,
+            inner,
+            PrunedTable::Blocks,
+            retention.blocks,
+            block_number,
+        )? {
+            inner.blocks.delete_range(txn, &(range.0..=range.1))?;
+            extend_pruned_range(txn, inner, PrunedTable::Blocks, range)?;
+        }
+
+        if let Some(range) = next_prune_range(
+            txn,
+            inner,
+            PrunedTable::Proofs,
+            retention.proofs,
+            block_number,
+        )? {
+            inner.proofs.delete_range(txn, &(range.0..=range.1))?;
+            extend_pruned_range(txn, inner, PrunedTable::Proofs, range)?;
+        }
+
+        if let Some(range) = next_prune_range(
+            txn,
+            inner,
+            PrunedTable::Transactions,
+            retention.transactions,
+            block_number,
+        )? {
+            for block_number in range.0..=range.1 {
+                if let Some(commit) = inner.commits.get(txn, &block_number)? {
+                    for transaction_hash in commit.tx_hashes() {
+                        inner
+                            .transactions_hash_key
+                            .delete(txn, &HashWrapper(*transaction_hash))?;
+                    }
+                }
+
+                let mut sequence = 0;
+                while inner.transactions.delete(
+                    txn,
+                    &StringWrapper(format!("{}-{}", block_number, sequence)),
+                )? {
+                    sequence += 1;
+                }
+            }
+
+            extend_pruned_range(txn, inner, PrunedTable::Transactions, range)?;
+        }
+
+        // Receipts of blocks whose transactions are still present cannot be pruned yet
+        let receipts_block_number = match retention.transactions.filter(|retention| *retention > 0)
+        {
+            Some(_) => match read_pruned_range(txn, inner, PrunedTable::Transactions)? {
+                Some(transactions) => {
+                    block_number.min(transactions.last + retention.receipts.unwrap_or_default())
+                }
+                None => 0,
+            },
+            None => block_number,
+        };
+
+        if let Some(range) = next_prune_range(
+            txn,
+            inner,
+            PrunedTable::Receipts,
+            retention.receipts,
+            receipts_block_number,
+        )? {
+            let mut commits = Vec::new();
+            for item in inner.commits.range(txn, &(range.0..=range.1))? {
+                let (block_number, commit) = item?;
+                if !commit.tx_receipts.is_empty() {
+                    commits.push((block_number, commit));
+                }
+            }
+
+            // Only the receipts are dropped, the commit itself marks the block as committed
+            for (block_number, commit) in commits {
+                for tx_hash in commit.tx_hashes() {
+                    inner
+                        .receipt_locations
+                        .delete(txn, &HashWrapper(*tx_hash))?;
+                }
+
+                inner
+                    .log_index
+                    .delete(txn, block_number, &commit.tx_receipts)?;
+
+                inner.commits.put(
+                    txn,
+                    &block_number,
+                    &CommitReceipts {
+                        tx_receipts: Default::default(),
+                        ..commit
+                    },
+                )?;
+            }
+
+            extend_pruned_range(txn, inner, PrunedTable::Receipts, range)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Clamps the pruned ranges to `block_number` after unwinding, the blocks above it get committed
+// again and are pruned once they leave the retention window.
+pub(crate) fn unwind_pruned_ranges(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    block_number: u64,
+) -> Result<(), Error> {
+    for table in [
+        PrunedTable::Blocks,
+        PrunedTable::Proofs,
+        PrunedTable::Transactions,
+        PrunedTable::Receipts,
+    ] {
+        match read_pruned_range(txn, inner, table)? {
+            Some(range) if range.first > block_number => {
+                inner.state.delete(txn, &table.key())?;
+            }
+            Some(range) if range.last > block_number => write_pruned_range(
+                txn,
+                inner,
+                table,
+                PrunedRange {
+                    first: range.first,
+                    last: block_number,
+                },
+            )?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+// Returns the next inclusive range of block numbers to prune from `table` once `block_number` got
+// committed.
+fn next_prune_range(
+    txn: &RwTxn,
+    inner: &InnerStorage,
+    table: PrunedTable,
+    retention: Option<u64>,
+    block_number: u64,
+) -> Result<Option<(u64, u64)>, Error> {
+    let Some(retention) = retention.filter(|retention| *retention > 0) else {
+        return Ok(None);
+    };
+
+    let Some(target) = block_number.checked_sub(retention) else {
+        return Ok(None);
+    };
+
+    let first = match read_pruned_range(txn, inner, table)? {
+        Some(range) => range.last + 1,
+        None => {
+            // the pruned range starts at the oldest stored block
+            let first = match table {
+                PrunedTable::Blocks => inner.blocks.first(txn)?.map(|(number, _)| number),
+                PrunedTable::Proofs => inner.proofs.first(txn)?.map(|(number, _)| number),
+                PrunedTable::Transactions | PrunedTable::Receipts => {
+                    inner.commits.first(txn)?.map(|(number, _)| number)
+                }
+            };
+
+            match first {
+                Some(first) => first,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    if first > target {
+        return Ok(None);
+    }
+
+    Ok(Some((first, target.min(first + PRUNE_BATCH_SIZE - 1))))
+}
+
+fn extend_pruned_range(
+    txn: &mut RwTxn,
+    inner: &InnerStorage,
+    table: PrunedTable,
+    range: (u64, u64),
+) -> Result<(), Error> {
+    let first = match read_pruned_range(txn, inner, table)? {
+        Some(pruned) => pruned.first,
+        None => range.0,
+    };
+
+    write_pruned_range(
+        txn,
+        inner,
+        table,
+        PrunedRange {
+            first,
+            last: range.1,
+        },
+    )
+}
+
+#[test]
+fn test_pruning() {
+    use revm::{
+        context::result::{ExecutionResult, Output, SuccessReason},
+        primitives::{B256, U256},
+    };
+
+    use crate::{
+        db::{CommitData, PersistentDBOptions},
+        receipt::executed_transaction,
+        trie::pending_commit,
+    };
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db = PersistentDB::new(
+        PersistentDBOptions::new(path.path().to_path_buf()).with_retention(RetentionPolicy {
+            blocks: Some(10),
+            proofs: Some(5),
+            transactions: Some(20),
+            receipts: Some(1),
+        }),
+    )
+    .expect("database");
+
+    let transaction_hash = |block_number: u64| B256::from(U256::from(block_number));
+
+    for block_number in 1..=300 {
+        let mut commit = pending_commit(block_number, vec![]);
+        commit.results.insert(
+            transaction_hash(block_number),
+            executed_transaction(
+                0,
+                ExecutionResult::Success {
+                    reason: SuccessReason::Stop,
+                    gas_used: 21000,
+                    gas_refunded: 0,
+                    logs: vec![],
+                    output: Output::Call(Bytes::new()),
+                },
+            ),
+        );
+
+        crate::state_commit::commit_to_db(
+            &mut db,
+            commit,
+            Some(CommitData {
+                block_hash: transaction_hash(block_number),
+                block: Bytes::from_static(&[1]),
+                proof: Bytes::from_static(&[2]),
+                transactions: vec![Bytes::from_static(&[3])],
+                transaction_hashes: vec![transaction_hash(block_number)],
+                ..Default::default()
+            }),
+        )
+        .expect("ok");
+    }
+
+    // Data outside of the retention windows is pruned
+    assert!(db.get_block_header_bytes(1).unwrap().is_pruned());
+    assert!(db.get_block_header_bytes(290).unwrap().is_pruned());
+    assert!(matches!(
+        db.get_block_header_bytes(291).unwrap(),
+        Retained::Present(_)
+    ));
+    assert_eq!(db.get_block_header_bytes(301).unwrap(), Retained::Absent);
+
+    assert!(db.get_proof_bytes(295).unwrap().is_pruned());
+    assert!(matches!(
+        db.get_proof_bytes(296).unwrap(),
+        Retained::Present(_)
+    ));
+
+    assert!(
+        db.get_transaction_bytes("280-0".to_owned())
+            .unwrap()
+            .is_pruned()
+    );
+    assert!(matches!(
+        db.get_transaction_bytes("281-0".to_owned()).unwrap(),
+        Retained::Present(_)
+    ));
+    assert_eq!(
+        db.get_transaction_key_by_hash(transaction_hash(280))
+            .unwrap(),
+        None
+    );
+
+    // Receipts follow the transactions
+    assert!(
+        db.get_receipt(280, transaction_hash(280))
+            .unwrap()
+            .is_pruned()
+    );
+    assert!(matches!(
+        db.get_receipt(281, transaction_hash(281)).unwrap(),
+        Retained::Present(_)
+    ));
+    assert!(
+        db.get_receipt_by_hash(transaction_hash(280))
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        db.get_receipt_by_hash(transaction_hash(281))
+            .unwrap()
+            .is_some()
+    );
+    assert!(db.is_block_committed(1));
+
+    assert_eq!(
+        db.prune_state().unwrap(),
+        PruneState {
+            blocks: Some(PrunedRange {
+                first: 1,
+                last: 290
+            }),
+            proofs: Some(PrunedRange {
+                first: 1,
+                last: 295
+            }),
+            transactions: Some(PrunedRange {
+                first: 1,
+                last: 280
+            }),
+            receipts: Some(PrunedRange {
+                first: 1,
+                last: 280
+            }),
+        }
+    );
+
+    let report = db.verify().expect("verify");
+    assert!(report.is_ok(), "{:?}", report.inconsistencies);
+
+    // Blocks with pruned receipts are not listed as blocks without receipts
+    let (_, receipts) = db.get_receipts(None, 1).unwrap();
+    assert_eq!(receipts[0].0, 281);
+
+    // Unwinding into the pruned ranges clamps them
+    db.unwind_to(285).expect("unwind");
+    assert_eq!(
+        db.prune_state().unwrap(),
+        PruneState {
+            blocks: Some(PrunedRange {
+                first: 1,
+                last: 285
+            }),
+            proofs: Some(PrunedRange {
+                first: 1,
+                last: 285
+            }),
+            transactions: Some(PrunedRange {
+                first: 1,
+                last: 280
+            }),
+            receipts: Some(PrunedRange {
+                first: 1,
+                last: 280
+            }),
+        }
+    );
+    assert_eq!(db.get_block_header_bytes(286).unwrap(), Retained::Absent);
+}
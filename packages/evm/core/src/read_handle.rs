@@ -0,0 +1,436 @@
+use std::{
+    ops::Deref,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+};
+
+use heed::{RoTxn, WithoutTls};
+use revm::{
+    DatabaseRef,
+    primitives::{Address, B256, Bytes, U256, alloy_primitives::Bloom},
+    state::{AccountInfo, Bytecode},
+};
+
+use crate::{
+    account::AccountInfoExtended,
+    balance_index::BalanceFilter,
+    db::{Error, GenesisInfo, PersistentDB},
+    deployment::ContractDeployment,
+    historical::HistoricalState,
+    legacy::{LegacyAccountAttributes, LegacyAddress, LegacyColdWallet},
+    log_index::{IndexedLog, LogFilter, LogPosition},
+    pruning::{PruneState, Retained},
+    receipt::{IndexedReceipt, TxReceipt},
+    state_root::StateRootMode,
+    trie::AccountProof,
+};
+
+// Read transaction backing a single read of the database. Read handles lock their pinned
+// transaction, any other database opens a new one.
+pub(crate) enum ReadTxn<'a> {
+    Owned(RoTxn<'a, WithoutTls>),
+    Pinned(MutexGuard<'a, RoTxn<'static, WithoutTls>>),
+}
+
+impl<'a> Deref for ReadTxn<'a> {
+    type Target = RoTxn<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            ReadTxn::Owned(txn) => txn,
+            ReadTxn::Pinned(txn) => txn,
+        }
+    }
+}
+
+// Resizing the map invalidates the memory of all open transactions. The gate keeps track of the
+// open read handles, resizing waits until all of them are dropped and no new handles are opened
+// in the meantime. Running backups hold off resizes as well, but new read handles can still be
+// opened until the last backup is done.
+#[derive(Default)]
+pub(crate) struct ResizeGate {
+    state: Mutex<GateState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct GateState {
+    open_handles: usize,
+    running_backups: usize,
+    resizing: bool,
+}
+
+impl ResizeGate {
+    fn lock(&self) -> MutexGuard<'_, GateState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait_while<'a>(
+        &self,
+        state: MutexGuard<'a, GateState>,
+        condition: impl FnMut(&mut GateState) -> bool,
+    ) -> MutexGuard<'a, GateState> {
+        self.changed
+            .wait_while(state, condition)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn enter(&self) {
+        let mut state = self.wait_while(self.lock(), |state| state.resizing);
+        state.open_handles += 1;
+    }
+
+    fn leave(&self) {
+        self.lock().open_handles -= 1;
+        self.changed.notify_all();
+    }
+
+    pub(crate) fn backup(&self) -> BackupGuard<'_> {
+        let mut state = self.wait_while(self.lock(), |state| state.resizing);
+        state.running_backups += 1;
+
+        BackupGuard { gate: self }
+    }
+
+    pub(crate) fn exclusive<T>(&self, resize: impl FnOnce() -> T) -> T {
+        let mut state = self.wait_while(self.lock(), |state| {
+            state.resizing || state.running_backups > 0
+        });
+        state.resizing = true;
+
+        let mut state = self.wait_while(state, |state| state.open_handles > 0);
+        let result = resize();
+        state.resizing = false;
+
+        drop(state);
+        self.changed.notify_all();
+
+        result
+    }
+}
+
+// Leaves the gate once the backup is done.
+pub(crate) struct BackupGuard<'a> {
+    gate: &'a ResizeGate,
+}
+
+impl Drop for BackupGuard<'_> {
+    fn drop(&mut self) {
+        self.gate.lock().running_backups -= 1;
+        self.gate.changed.notify_all();
+    }
+}
+
+// Opens read handles, it can be shared with other threads independently of the `PersistentDB`.
+#[derive(Clone)]
+pub struct ReadHandles {
+    db: Arc<PersistentDB>,
+}
+
+impl ReadHandles {
+    pub fn open(&self) -> Result<ReadHandle, Error> {
+        self.db.read_handle()
+    }
+}
+
+// Read-only view of the database pinned to a single read transaction. All reads of a handle are
+// served from the same consistent snapshot, commits made after it got opened are not visible.
+//
+// Handles are `Send + Sync`, concurrent reads on the same handle are serialized though. Open
+// transactions prevent LMDB from reusing the pages freed by later commits and resizing the
+// database waits until all handles are dropped, thus handles are meant to be short-lived and
+// must not be held by the thread committing to the database.
+pub struct ReadHandle {
+    db: PersistentDB,
+}
+
+impl PersistentDB {
+    pub fn read_handles(&self) -> ReadHandles {
+        ReadHandles {
+            db: Arc::new(self.read_view(None)),
+        }
+    }
+
+    pub fn read_handle(&self) -> Result<ReadHandle, Error> {
+        loop {
+            self.resize_gate.enter();
+
+            match self.env.clone().static_read_txn() {
+                Ok(txn) => {
+                    return Ok(ReadHandle {
+                        db: self.read_view(Some(Mutex::new(txn))),
+                    });
+                }
+                Err(err) => {
+                    self.resize_gate.leave();
+
+                    match err {
+                        heed::Error::Mdb(heed::MdbError::MapResized) => self.adopt_map_size()?,
+                        err => return Err(err.into()),
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn read_txn(&self) -> Result<ReadTxn<'_>, Error> {
+        match &self.pinned_txn {
+            Some(txn) => Ok(ReadTxn::Pinned(txn.lock().map_err(|_| Error::Lock)?)),
+            None => loop {
+                match self.env.read_txn() {
+                    Ok(txn) => return Ok(ReadTxn::Owned(txn)),
+                    // The map got grown by the process writing to the database
+                    Err(heed::Error::Mdb(heed::MdbError::MapResized)) => self.adopt_map_size()?,
+                    Err(err) => return Err(err.into()),
+                }
+            },
+        }
+    }
+
+    pub(crate) fn read_view(
+        &self,
+        pinned_txn: Option<Mutex<RoTxn<'static, WithoutTls>>>,
+    ) -> PersistentDB {
+        PersistentDB {
+            env: self.env.clone(),
+            inner: self.inner.clone(),
+            accounts_history: self.accounts_history.clone(),
+            storage_history: self.storage_history.clone(),
+            unwind_depth: self.unwind_depth,
+            retention: self.retention,
+            state_root_mode: self.state_root_mode,
+            logger: self.logger.clone(),
+            genesis_info: self.genesis_info.clone(),
+            read_only: true,
+            pinned_txn,
+            resize_gate: self.resize_gate.clone(),
+            resize_count: self.resize_count.clone(),
+            temp_dir: self.temp_dir.clone(),
+        }
+    }
+}
+
+impl Drop for ReadHandle {
+    fn drop(&mut self) {
+        // The transaction has to be closed before a pending resize may continue
+        drop(self.db.pinned_txn.take());
+        self.db.resize_gate.leave();
+    }
+}
+
+macro_rules! forward {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            pub fn $name(&self $(, $arg: $ty)*) -> $ret {
+                self.db.$name($($arg),*)
+            }
+        )*
+    };
+}
+
+impl ReadHandle {
+    forward! {
+        fn state_root_mode(&self) -> StateRootMode;
+        fn genesis_info(&self) -> Option<GenesisInfo>;
+        fn get_accounts(
+            &self,
+            cursor: Option<Bytes>,
+            limit: u64
+        ) -> Result<(Option<Bytes>, Vec<AccountInfoExtended>), Error>;
+        fn get_accounts_by_balance(
+            &self,
+            filter: &BalanceFilter,
+            cursor: Option<Bytes>,
+            limit: u64
+        ) -> Result<(Option<Bytes>, Vec<AccountInfoExtended>), Error>;
+        fn get_storage_range(
+            &self,
+            address: Address,
+            start: Option<U256>,
+            limit: u64
+        ) -> Result<(Option<U256>, Vec<(U256, U256)>), Error>;
+        fn get_historical_account_info(
+            &self,
+            block_number: u64,
+            address: Address
+        ) -> Result<(Option<AccountInfo>, bool), Error>;
+        fn get_historical_storage(
+            &self,
+            block_number: u64,
+            address: Address,
+            slot: U256
+        ) -> Result<(Option<U256>, bool), Error>;
+        fn historical_state(&self, block_number: u64) -> Result<HistoricalState<'_>, Error>;
+        fn get_proof(
+            &self,
+            address: Address,
+            slots: &[U256],
+            block_number: u64
+        ) -> Result<AccountProof, Error>;
+        fn get_legacy_attributes(
+            &self,
+            address: Address
+        ) -> Result<Option<LegacyAccountAttributes>, Error>;
+        fn get_legacy_cold_wallet(
+            &self,
+            address: LegacyAddress
+        ) -> Result<Option<LegacyColdWallet>, Error>;
+        fn get_legacy_cold_wallets(
+            &self,
+            cursor: Option<Bytes>,
+            limit: u64
+        ) -> Result<(Option<Bytes>, Vec<LegacyColdWallet>), Error>;
+        fn get_receipts(
+            &self,
+            cursor: Option<Bytes>,
+            limit: u64
+        ) -> Result<(Option<Bytes>, Vec<(u64, Vec<(B256, TxReceipt)>)>), Error>;
+        fn get_receipt(&self, block_number: u64, tx_hash: B256) -> Result<Retained<TxReceipt>, Error>;
+        fn get_receipt_by_hash(&self, tx_hash: B256) -> Result<Option<IndexedReceipt>, Error>;
+        fn get_logs(
+            &self,
+            filter: &LogFilter,
+            cursor: Option<LogPosition>,
+            limit: u64
+        ) -> Result<(Option<LogPosition>, Vec<IndexedLog>), Error>;
+        fn get_contract_deployment(
+            &self,
+            address: Address
+        ) -> Result<Option<ContractDeployment>, Error>;
+        fn get_contract_deployments(
+            &self,
+            cursor: Option<Bytes>,
+            limit: u64
+        ) -> Result<(Option<Bytes>, Vec<(Address, ContractDeployment)>), Error>;
+        fn is_empty(&self) -> Result<bool, Error>;
+        fn get_state(&self) -> Result<(u64, u64), Error>;
+        fn get_state_root(&self, block_number: u64) -> Result<Option<B256>, Error>;
+        fn get_logs_bloom(&self, block_number: u64) -> Result<Option<Bloom>, Error>;
+        fn get_block_header_bytes(&self, block_number: u64) -> Result<Retained<Bytes>, Error>;
+        fn get_block_hash(&self, block_number: u64) -> Result<Option<B256>, Error>;
+        fn get_block_number_by_hash(&self, block_hash: B256) -> Result<Option<u64>, Error>;
+        fn get_proof_bytes(&self, block_number: u64) -> Result<Retained<Bytes>, Error>;
+        fn get_transaction_bytes(&self, key: String) -> Result<Retained<Bytes>, Error>;
+        fn get_transaction_key_by_hash(&self, tx_hash: B256) -> Result<Option<String>, Error>;
+        fn prune_state(&self) -> Result<PruneState, Error>;
+    }
+}
+
+impl DatabaseRef for ReadHandle {
+    type Error = Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.db.basic_ref(address)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.db.code_by_hash_ref(code_hash)
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.db.storage_ref(address, index)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        self.db.block_hash_ref(number)
+    }
+}
+
+#[test]
+fn test_read_handle() {
+    use revm::primitives::address;
+
+    use crate::{db::PersistentDBOptions, state_commit::commit_to_db, trie::pending_commit};
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db = PersistentDB::new(
+        PersistentDBOptions::new(path.path().to_path_buf()).with_history_size(10),
+    )
+    .expect("database");
+
+    let alice = address!("1000000000000000000000000000000000000001");
+    let bob = address!("2000000000000000000000000000000000000002");
+
+    commit_to_db(
+        &mut db,
+        pending_commit(1, vec![(alice, 100, vec![(1, 0, 10)])]),
+        None,
+    )
+    .expect("commit");
+
+    let handles = db.read_handles();
+    let handle = handles.open().expect("handle");
+    assert_send_sync(&handles);
+    assert_send_sync(&handle);
+
+    commit_to_db(
+        &mut db,
+        pending_commit(2, vec![(alice, 200, vec![(1, 10, 20)])]),
+        None,
+    )
+    .expect("commit");
+
+    // The handle keeps reading the state it got opened at, also from other threads
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            let info = handle.basic_ref(alice).unwrap().unwrap();
+            assert_eq!(info.balance, U256::from(100));
+            assert_eq!(
+                handle.storage_ref(alice, U256::from(1)).unwrap(),
+                U256::from(10)
+            );
+            assert_eq!(handle.get_state().unwrap().0, 1);
+            assert!(handle.get_block_hash(2).unwrap().is_none());
+        });
+    });
+    drop(handle);
+
+    let handle = handles.open().expect("handle");
+    assert_eq!(
+        handle.basic_ref(alice).unwrap().unwrap().balance,
+        U256::from(200)
+    );
+    assert_eq!(handle.get_state().unwrap().0, 2);
+
+    // Historical reads fall back to the pinned state without locking its transaction twice
+    let state = handle.historical_state(1).expect("state");
+    assert_eq!(
+        state.basic_ref(alice).unwrap().unwrap().balance,
+        U256::from(100)
+    );
+    assert_eq!(state.basic_ref(bob).unwrap(), Some(AccountInfo::default()));
+    assert_eq!(state.storage_ref(bob, U256::from(1)).unwrap(), U256::ZERO);
+    drop(state);
+    drop(handle);
+
+    // Resizing waits for open handles and proceeds once they are dropped
+    let handle = db.read_handle().expect("handle");
+    std::thread::scope(|scope| {
+        let resize = scope.spawn(|| db.resize());
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!resize.is_finished());
+
+        drop(handle);
+        resize.join().unwrap().expect("resize");
+    });
+
+    let handle = handles.open().expect("handle");
+    assert_eq!(handle.get_state().unwrap().0, 2);
+
+    // Databases opened from the same path share the gate
+    let other =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+    std::thread::scope(|scope| {
+        let resize = scope.spawn(|| other.resize());
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!resize.is_finished());
+
+        drop(handle);
+        resize.join().unwrap().expect("resize");
+    });
+}
@@ -1,9 +1,14 @@
+use std::collections::BTreeMap;
+
+use alloy_sol_types::{Panic, Revert, SolError};
 use revm::{
     context::result::{ExecutionResult, Output},
-    primitives::{Bytes, Log},
+    primitives::{Address, B256, Bytes, Log, U256},
 };
 use serde::{Deserialize, Serialize};
 
+use crate::errors::KNOWN_ERRORS;
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct TxReceipt {
     pub gas_used: u64,
@@ -12,6 +17,85 @@ pub struct TxReceipt {
     pub contract_address: Option<String>,
     pub logs: Option<Vec<Log>>,
     pub output: Option<Bytes>,
+
+    // Position within the block and block-wide totals, only set for committed receipts
+    pub transaction_index: u64,
+    pub cumulative_gas_used: u64,
+    // Index of the first log within the block, the following logs are numbered consecutively
+    pub first_log_index: u64,
+
+    // Unknown for receipts committed before these fields were introduced
+    pub from: Option<Address>,
+    // None when deploying a contract
+    pub to: Option<Address>,
+    pub effective_gas_price: Option<u128>,
+
+    // Why a failed transaction reverted or halted
+    pub revert_reason: Option<RevertReason>,
+    pub halt_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RevertReason {
+    // `Error(string)`, raised by `revert("...")` and `require(..., "...")`
+    Error(String),
+    // `Panic(uint256)`, raised by failed assertions, arithmetic overflows and alike
+    Panic(U256),
+    // Signature of a custom error of the Mainsail contracts, the arguments remain ABI encoded in
+    // the output
+    Custom(String),
+    // Output which does not match any known error, e.g. a plain `revert()`
+    Unknown,
+}
+
+impl RevertReason {
+    pub fn decode(output: &[u8]) -> Self {
+        if let Ok(revert) = Revert::abi_decode(output) {
+            return RevertReason::Error(revert.reason);
+        }
+
+        if let Ok(panic) = Panic::abi_decode(output) {
+            return RevertReason::Panic(panic.code);
+        }
+
+        let known_error = output.get(..4).and_then(|selector| {
+            KNOWN_ERRORS
+                .iter()
+                .find(|(known_selector, _)| known_selector == selector)
+        });
+
+        match known_error {
+            Some((_, signature)) => RevertReason::Custom((*signature).to_owned()),
+            None => RevertReason::Unknown,
+        }
+    }
+}
+
+// A transaction executed as part of a pending commit, the receipt is derived from it once the
+// commit gets written.
+#[derive(Clone, Debug)]
+pub struct ExecutedTransaction {
+    // Position within the block, receipts are stored in this order
+    pub index: u64,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub effective_gas_price: u128,
+    pub result: ExecutionResult,
+}
+
+// Where the receipt of a transaction is stored. The transaction index is only known for commits
+// which include the block data.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptLocation {
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub transaction_index: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexedReceipt {
+    pub receipt: TxReceipt,
+    pub location: ReceiptLocation,
 }
 
 pub fn map_execution_result(result: ExecutionResult) -> TxReceipt {
@@ -30,6 +114,7 @@ pub fn map_execution_result(result: ExecutionResult) -> TxReceipt {
                 contract_address: None,
                 logs: Some(logs),
                 output: Some(output),
+                ..Default::default()
             },
             Output::Create(output, address) => TxReceipt {
                 gas_used,
@@ -38,6 +123,7 @@ pub fn map_execution_result(result: ExecutionResult) -> TxReceipt {
                 contract_address: address.map(|address| address.to_string()),
                 logs: Some(logs),
                 output: Some(output),
+                ..Default::default()
             },
         },
         ExecutionResult::Revert { gas_used, output } => TxReceipt {
@@ -46,15 +132,158 @@ pub fn map_execution_result(result: ExecutionResult) -> TxReceipt {
             gas_refunded: 0,
             contract_address: None,
             logs: None,
+            revert_reason: Some(RevertReason::decode(&output)),
             output: Some(output),
+            ..Default::default()
         },
-        ExecutionResult::Halt { gas_used, .. } => TxReceipt {
+        ExecutionResult::Halt { gas_used, reason } => TxReceipt {
             gas_used,
             success: 0,
             gas_refunded: 0,
             contract_address: None,
             logs: None,
             output: None,
+            halt_reason: Some(format!("{:?}", reason)),
+            ..Default::default()
         },
     }
 }
+
+#[cfg(test)]
+pub(crate) fn executed_transaction(index: u64, result: ExecutionResult) -> ExecutedTransaction {
+    ExecutedTransaction {
+        index,
+        from: Address::ZERO,
+        to: Some(Address::ZERO),
+        effective_gas_price: 0,
+        result,
+    }
+}
+
+// Maps the executed transactions of a commit to receipts in execution order.
+pub fn build_receipts(results: &BTreeMap<B256, ExecutedTransaction>) -> Vec<(B256, TxReceipt)> {
+    let mut executed = results.iter().collect::<Vec<_>>();
+    executed.sort_by_key(|(tx_hash, executed)| (executed.index, **tx_hash));
+
+    let mut cumulative_gas_used = 0;
+    let mut log_index = 0;
+
+    executed
+        .into_iter()
+        .map(|(tx_hash, executed)| {
+            let mut receipt = map_execution_result(executed.result.clone());
+
+            cumulative_gas_used += receipt.gas_used;
+
+            receipt.transaction_index = executed.index;
+            receipt.cumulative_gas_used = cumulative_gas_used;
+            receipt.first_log_index = log_index;
+            receipt.from = Some(executed.from);
+            receipt.to = executed.to;
+            receipt.effective_gas_price = Some(executed.effective_gas_price);
+
+            log_index += receipt.logs.as_ref().map_or(0, |logs| logs.len() as u64);
+
+            (*tx_hash, receipt)
+        })
+        .collect()
+}
+
+#[test]
+fn test_build_receipts() {
+    use revm::context::result::{HaltReason, OutOfGasError, SuccessReason};
+
+    let success = |gas_used, logs: usize| ExecutionResult::Success {
+        reason: SuccessReason::Stop,
+        gas_used,
+        gas_refunded: 0,
+        logs: vec![Log::default(); logs],
+        output: Output::Call(Bytes::new()),
+    };
+
+    let results = BTreeMap::from([
+        (
+            B256::repeat_byte(1),
+            executed_transaction(2, success(300, 1)),
+        ),
+        (
+            B256::repeat_byte(2),
+            executed_transaction(
+                1,
+                ExecutionResult::Halt {
+                    reason: HaltReason::OutOfGas(OutOfGasError::Basic),
+                    gas_used: 200,
+                },
+            ),
+        ),
+        (
+            B256::repeat_byte(3),
+            executed_transaction(0, success(100, 2)),
+        ),
+    ]);
+
+    let receipts = build_receipts(&results);
+
+    assert_eq!(
+        receipts
+            .iter()
+            .map(|(tx_hash, receipt)| (
+                *tx_hash,
+                receipt.transaction_index,
+                receipt.cumulative_gas_used,
+                receipt.first_log_index
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (B256::repeat_byte(3), 0, 100, 0),
+            (B256::repeat_byte(2), 1, 300, 2),
+            (B256::repeat_byte(1), 2, 600, 2),
+        ]
+    );
+    assert_eq!(receipts[0].1.from, Some(Address::ZERO));
+    assert_eq!(receipts[0].1.effective_gas_price, Some(0));
+}
+
+#[test]
+fn test_revert_reason() {
+    assert_eq!(
+        RevertReason::decode(
+            &Revert {
+                reason: "not allowed".to_owned()
+            }
+            .abi_encode()
+        ),
+        RevertReason::Error("not allowed".to_owned())
+    );
+    assert_eq!(
+        RevertReason::decode(
+            &Panic {
+                code: U256::from(0x11)
+            }
+            .abi_encode()
+        ),
+        RevertReason::Panic(U256::from(0x11))
+    );
+    assert_eq!(
+        RevertReason::decode(
+            &crate::errors::InvalidRange {
+                min: U256::from(1),
+                max: U256::from(2)
+            }
+            .abi_encode()
+        ),
+        RevertReason::Custom("InvalidRange(uint256,uint256)".to_owned())
+    );
+    assert_eq!(RevertReason::decode(&[]), RevertReason::Unknown);
+    assert_eq!(
+        RevertReason::decode(&[0xde, 0xad, 0xbe, 0xef]),
+        RevertReason::Unknown
+    );
+
+    let receipt = map_execution_result(ExecutionResult::Halt {
+        reason: revm::context::result::HaltReason::OpcodeNotFound,
+        gas_used: 100,
+    });
+    assert_eq!(receipt.halt_reason, Some("OpcodeNotFound".to_owned()));
+    assert_eq!(receipt.revert_reason, None);
+}
@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+
+use revm::{
+    primitives::{Address, B256, Bytes, U256},
+    state::AccountInfo,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::legacy::{LegacyAccountAttributes, LegacyAddress, LegacyColdWallet};
+
+// Reverse changeset of a single commit. Contains the pre-images of everything that got
+// overwritten, which allows restoring the database to the state before the commit.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BlockRevert {
+    // Previous account info, `None` if the account did not exist before.
+    pub accounts: BTreeMap<Address, Option<AccountInfo>>,
+
+    pub storage: Vec<StorageRevert>,
+
+    // Code hashes which have been inserted for the first time.
+    pub contracts: Vec<B256>,
+
+    pub legacy_attributes: BTreeMap<Address, Option<LegacyAccountAttributes>>,
+
+    // Includes the cold wallets which got merged in the commit.
+    pub legacy_cold_wallets: BTreeMap<LegacyAddress, Option<LegacyColdWallet>>,
+
+    pub block: Option<BlockDataRevert>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StorageRevert {
+    pub address: Address,
+
+    // If the storage was wiped, `slots` contains the entire previous storage of the address.
+    pub wiped: bool,
+
+    // Previous slot values, a zero value means the slot did not exist before.
+    pub slots: Vec<(U256, U256)>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BlockDataRevert {
+    pub block_hash: B256,
+    pub transaction_hashes: Vec<B256>,
+
+    // Raw value of the `total_round` state prior to the commit.
+    pub total_round: Option<Bytes>,
+}
@@ -0,0 +1,718 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use revm::{
+    primitives::{Address, B256, BLOCK_HASH_HISTORY, Bytes, U256},
+    state::{AccountInfo, Bytecode},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    db::{
+        AddressWrapper, CommitReceipts, Error, HashWrapper, LegacyAddressWrapper, PersistentDB,
+        StorageEntryWrapper, StringWrapper, map_db_full,
+    },
+    deployment::ContractDeployment,
+    legacy::{LegacyAccountAttributes, LegacyAddress, LegacyColdWallet},
+    migrations::SCHEMA_VERSION,
+    receipt::ReceiptLocation,
+    state_root::StateRootMode,
+    trie::StorageTrieUpdate,
+};
+
+// Snapshot file layout:
+//
+//   MAGIC | (u32 LE length | bincode(SnapshotRecord))* | sha256 checksum
+//
+// The first record is always the header and the last record is `End`. The checksum covers
+// everything preceding it, including the magic bytes.
+const MAGIC: &[u8; 8] = b"MSEVMSNP";
+const SNAPSHOT_VERSION: u32 = 1;
+
+// Guards against allocating absurd amounts of memory when reading a corrupted length prefix.
+const MAX_RECORD_SIZE: usize = 256 * 1024 * 1024;
+
+// Records imported per write transaction.
+const SNAPSHOT_BATCH_SIZE: usize = 10_000;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotHeader {
+    pub version: u32,
+    pub block_number: u64,
+    pub include_blocks: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    pub block_number: u64,
+    pub records: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SnapshotRecord {
+    Header(SnapshotHeader),
+    Account(Address, AccountInfo),
+    Storage(Address, U256, U256),
+    Contract(B256, Bytecode),
+    LegacyAttributes(Address, LegacyAccountAttributes),
+    LegacyColdWallet(LegacyAddress, LegacyColdWallet),
+    State(String, Bytes),
+    Commit(u64, CommitReceipts),
+    Block(u64, Bytes),
+    BlockHash(u64, B256),
+    Proof(u64, Bytes),
+    Transaction(String, Bytes),
+    TransactionHash(B256, String),
+    ReceiptLocation(B256, ReceiptLocation),
+    Deployment(Address, ContractDeployment),
+    End(u64),
+}
+
+struct SnapshotWriter<W: Write> {
+    writer: W,
+    hasher: Sha256,
+    records: u64,
+}
+
+impl<W: Write> SnapshotWriter<W> {
+    fn new(writer: W) -> Result<Self, Error> {
+        let mut snapshot_writer = Self {
+            writer,
+            hasher: Sha256::new(),
+            records: 0,
+        };
+
+        snapshot_writer.write_bytes(MAGIC)?;
+
+        Ok(snapshot_writer)
+    }
+
+    fn write(&mut self, record: &SnapshotRecord) -> Result<(), Error> {
+        let data = bincode::serialize(record)?;
+
+        self.write_bytes(&(data.len() as u32).to_le_bytes())?;
+        self.write_bytes(&data)?;
+        self.records += 1;
+
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.writer.write_all(data)?;
+        self.hasher.update(data);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<u64, Error> {
+        let records = self.records + 1;
+        self.write(&SnapshotRecord::End(records))?;
+
+        let checksum = self.hasher.finalize();
+        self.writer.write_all(&checksum)?;
+        self.writer.flush()?;
+
+        Ok(records)
+    }
+}
+
+struct SnapshotReader<R: Read> {
+    reader: R,
+    hasher: Sha256,
+    records: u64,
+}
+
+impl<R: Read> SnapshotReader<R> {
+    fn new(reader: R) -> Result<Self, Error> {
+        let mut snapshot_reader = Self {
+            reader,
+            hasher: Sha256::new(),
+            records: 0,
+        };
+
+        let mut magic = [0u8; 8];
+        snapshot_reader.read_bytes(&mut magic)?;
+
+        if &magic != MAGIC {
+            return Err(Error::InvalidSnapshot("unknown file format".into()));
+        }
+
+        Ok(snapshot_reader)
+    }
+
+    fn read(&mut self) -> Result<SnapshotRecord, Error> {
+        let mut length = [0u8; 4];
+        self.read_bytes(&mut length)?;
+
+        let length = u32::from_le_bytes(length) as usize;
+        if length > MAX_RECORD_SIZE {
+            return Err(Error::InvalidSnapshot("record exceeds maximum size".into()));
+        }
+
+        let mut data = vec![0u8; length];
+        self.read_bytes(&mut data)?;
+        self.records += 1;
+
+        Ok(bincode::deserialize(&data)?)
+    }
+
+    fn read_bytes(&mut self, data: &mut [u8]) -> Result<(), Error> {
+        self.reader.read_exact(data)?;
+        self.hasher.update(&*data);
+        Ok(())
+    }
+
+    fn verify_checksum(mut self) -> Result<(), Error> {
+        let mut checksum = [0u8; 32];
+        self.reader.read_exact(&mut checksum)?;
+
+        if checksum[..] != self.hasher.finalize()[..] {
+            return Err(Error::InvalidSnapshot("checksum mismatch".into()));
+        }
+
+        if self.reader.read(&mut [0u8; 1])? != 0 {
+            return Err(Error::InvalidSnapshot("trailing data".into()));
+        }
+
+        Ok(())
+    }
+}
+
+impl PersistentDB {
+    // Writes the state of the latest committed block to `path`. All records are read from a
+    // single read transaction, so commits happening meanwhile are not part of the snapshot.
+    // Without `include_blocks` only the latest block and commit and the hashes needed for
+    // BLOCKHASH are kept.
+    pub fn export_snapshot(
+        &self,
+        path: &Path,
+        include_blocks: bool,
+    ) -> Result<SnapshotInfo, Error> {
+        let txn = self.read_txn()?;
+        let inner = &self.inner;
+
+        let Some((block_number, _)) = inner
+            .commits
+            .remap_data_type::<heed::types::DecodeIgnore>()
+            .last(&txn)?
+        else {
+            return Err(Error::InvalidSnapshot("nothing has been committed".into()));
+        };
+
+        let mut writer = SnapshotWriter::new(BufWriter::new(File::create(path)?))?;
+
+        writer.write(&SnapshotRecord::Header(SnapshotHeader {
+            version: SNAPSHOT_VERSION,
+            block_number,
+            include_blocks,
+        }))?;
+
+        for item in inner.accounts.iter(&txn)? {
+            let (address, info) = item?;
+            writer.write(&SnapshotRecord::Account(address.0, info))?;
+        }
+
+        for item in inner.storage.iter(&txn)? {
+            let (address, entry) = item?;
+            writer.write(&SnapshotRecord::Storage(address.0, entry.0, entry.1))?;
+        }
+
+        // Unreferenced bytecode is only kept for unwinding and the history, neither is exported
+        for item in inner.contracts.iter(&txn)? {
+            let (hash, bytecode) = item?;
+            if inner.code_references.orphans.get(&txn, &hash)?.is_some() {
+                continue;
+            }
+
+            writer.write(&SnapshotRecord::Contract(hash.0, bytecode))?;
+        }
+
+        for item in inner.legacy_attributes.iter(&txn)? {
+            let (address, legacy_attributes) = item?;
+            writer.write(&SnapshotRecord::LegacyAttributes(
+                address.0,
+                legacy_attributes,
+            ))?;
+        }
+
+        for item in inner.legacy_cold_wallets.iter(&txn)? {
+            let (address, legacy_cold_wallet) = item?;
+            writer.write(&SnapshotRecord::LegacyColdWallet(
+                address.0,
+                legacy_cold_wallet,
+            ))?;
+        }
+
+        for item in inner.deployments.iter(&txn)? {
+            let (address, deployment) = item?;
+            writer.write(&SnapshotRecord::Deployment(address.0, deployment))?;
+        }
+
+        for item in inner
+            .state
+            .remap_key_type::<heed::types::Str>()
+            .iter(&txn)?
+        {
+            let (key, value) = item?;
+            writer.write(&SnapshotRecord::State(key.to_owned(), value))?;
+        }
+
+        let (first_block_number, first_block_hash_number) = if include_blocks {
+            (0, 0)
+        } else {
+            (
+                block_number,
+                block_number.saturating_sub(BLOCK_HASH_HISTORY - 1),
+            )
+        };
+
+        // The commits of the exported blocks together with the locations of their receipts
+        for item in inner.commits.range(&txn, &(first_block_number..))? {
+            let (block_number, commit_receipts) = item?;
+
+            for tx_hash in commit_receipts.tx_hashes() {
+                if let Some(location) = inner.receipt_locations.get(&txn, &HashWrapper(*tx_hash))? {
+                    writer.write(&SnapshotRecord::ReceiptLocation(*tx_hash, location))?;
+                }
+            }
+
+            writer.write(&SnapshotRecord::Commit(block_number, commit_receipts))?;
+        }
+
+        for item in inner.blocks.range(&txn, &(first_block_number..))? {
+            let (block_number, block) = item?;
+            writer.write(&SnapshotRecord::Block(block_number, block))?;
+        }
+
+        for item in inner
+            .blocks_number_hash
+            .range(&txn, &(first_block_hash_number..))?
+        {
+            let (block_number, block_hash) = item?;
+            writer.write(&SnapshotRecord::BlockHash(block_number, block_hash.0))?;
+        }
+
+        for item in inner.proofs.range(&txn, &(first_block_number..))? {
+            let (block_number, proof) = item?;
+            writer.write(&SnapshotRecord::Proof(block_number, proof))?;
+        }
+
+        if include_blocks {
+            for item in inner
+                .transactions
+                .remap_key_type::<heed::types::Str>()
+                .iter(&txn)?
+            {
+                let (key, transaction) = item?;
+                writer.write(&SnapshotRecord::Transaction(key.to_owned(), transaction))?;
+            }
+
+            for item in inner.transactions_hash_key.iter(&txn)? {
+                let (hash, key) = item?;
+                writer.write(&SnapshotRecord::TransactionHash(hash.0, key))?;
+            }
+        }
+
+        let records = writer.finish()?;
+
+        Ok(SnapshotInfo {
+            block_number,
+            records,
+        })
+    }
+
+    // Loads a snapshot created by `export_snapshot` into an empty database. The whole file is
+    // verified before anything is written, the records are then imported in batches of separate
+    // write transactions so that the map can grow in between. A failed import leaves a partially
+    // imported database behind.
+    pub fn import_snapshot(&self, path: &Path) -> Result<SnapshotInfo, Error> {
+        self.import_snapshot_file(path, SNAPSHOT_BATCH_SIZE)
+    }
+
+    fn import_snapshot_file(&self, path: &Path, batch_size: usize) -> Result<SnapshotInfo, Error> {
+        let header = self.verify_snapshot(path)?;
+
+        {
+            let txn = self.read_txn()?;
+            if !self.inner.commits.is_empty(&txn)? || !self.inner.accounts.is_empty(&txn)? {
+                return Err(Error::InvalidSnapshot("database is not empty".into()));
+            }
+        }
+
+        let mut reader = SnapshotReader::new(BufReader::new(File::open(path)?))?;
+        reader.read()?;
+
+        let mut records = Vec::with_capacity(batch_size);
+        loop {
+            records.clear();
+            while records.len() < batch_size
+                && !matches!(records.last(), Some(SnapshotRecord::End(_)))
+            {
+                records.push(reader.read()?);
+            }
+
+            loop {
+                match self.import_records(&header, &records) {
+                    Err(Error::DbFull) => self.resize()?,
+                    result => break result?,
+                }
+            }
+
+            if matches!(records.last(), Some(SnapshotRecord::End(_))) {
+                break;
+            }
+        }
+
+        Ok(SnapshotInfo {
+            block_number: header.block_number,
+            records: reader.records,
+        })
+    }
+
+    // Reads the whole snapshot and checks its records and checksum without writing anything.
+    fn verify_snapshot(&self, path: &Path) -> Result<SnapshotHeader, Error> {
+        let mut reader = SnapshotReader::new(BufReader::new(File::open(path)?))?;
+
+        let header = match reader.read()? {
+            SnapshotRecord::Header(header) if header.version == SNAPSHOT_VERSION => header,
+            SnapshotRecord::Header(header) => {
+                return Err(Error::InvalidSnapshot(format!(
+                    "unsupported version {}",
+                    header.version
+                )));
+            }
+            _ => return Err(Error::InvalidSnapshot("missing header".into())),
+        };
+
+        loop {
+            match reader.read()? {
+                SnapshotRecord::Header(_) => {
+                    return Err(Error::InvalidSnapshot("unexpected header".into()));
+                }
+                SnapshotRecord::State(key, value) => {
+                    if key == "state_root_mode" {
+                        let state_root_mode = StateRootMode::from_bytes(&value);
+                        if state_root_mode != self.state_root_mode() {
+                            return Err(Error::StateRootModeMismatch(state_root_mode));
+                        }
+                    }
+
+                    if key == "schema_version" && value.as_ref() != SCHEMA_VERSION.to_le_bytes() {
+                        return Err(Error::InvalidSnapshot(format!(
+                            "schema version {} differs from {}",
+                            value, SCHEMA_VERSION
+                        )));
+                    }
+                }
+                SnapshotRecord::End(records) => {
+                    if records != reader.records {
+                        return Err(Error::InvalidSnapshot("record count mismatch".into()));
+                    }
+
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        reader.verify_checksum()?;
+
+        Ok(header)
+    }
+
+    // Writes a batch of verified records in a single write transaction. The tries are updated
+    // with the accounts and slots of the batch, the state root is stored with the last batch.
+    fn import_records(
+        &self,
+        header: &SnapshotHeader,
+        records: &[SnapshotRecord],
+    ) -> Result<(), Error> {
+        let mut rwtxn = self.write_txn()?;
+        let inner = &self.inner;
+
+        let mut apply_records = |rwtxn: &mut heed::RwTxn| -> Result<(), Error> {
+            let mut trie_accounts = BTreeSet::new();
+            let mut trie_storage = BTreeMap::<Address, Vec<(U256, U256)>>::new();
+
+            for record in records {
+                match record {
+                    SnapshotRecord::Header(_) => {
+                        return Err(Error::InvalidSnapshot("unexpected header".into()));
+                    }
+                    SnapshotRecord::Account(address, info) => {
+                        inner.accounts.put(rwtxn, &AddressWrapper(*address), info)?;
+                        inner
+                            .balance_index
+                            .update(rwtxn, *address, None, Some(info))?;
+                        inner.code_references.update(
+                            rwtxn,
+                            header.block_number,
+                            None,
+                            Some(info),
+                        )?;
+                        trie_accounts.insert(*address);
+                    }
+                    SnapshotRecord::Storage(address, slot, value) => {
+                        inner.storage.put(
+                            rwtxn,
+                            &AddressWrapper(*address),
+                            &StorageEntryWrapper(*slot, *value),
+                        )?;
+                        trie_storage
+                            .entry(*address)
+                            .or_default()
+                            .push((*slot, *value));
+                    }
+                    SnapshotRecord::Contract(hash, bytecode) => {
+                        inner.contracts.put(rwtxn, &HashWrapper(*hash), bytecode)?;
+                        inner
+                            .code_references
+                            .insert_code(rwtxn, header.block_number, *hash)?;
+                    }
+                    SnapshotRecord::LegacyAttributes(address, legacy_attributes) => {
+                        inner.legacy_attributes.put(
+                            rwtxn,
+                            &AddressWrapper(*address),
+                            legacy_attributes,
+                        )?;
+                    }
+                    SnapshotRecord::LegacyColdWallet(address, legacy_cold_wallet) => {
+                        inner.legacy_cold_wallets.put(
+                            rwtxn,
+                            &LegacyAddressWrapper(*address),
+                            legacy_cold_wallet,
+                        )?;
+                    }
+                    SnapshotRecord::State(key, value) => {
+                        inner.state.remap_key_type::<heed::types::Str>().put(
+                            rwtxn,
+                            key.as_str(),
+                            value,
+                        )?;
+                    }
+                    SnapshotRecord::Commit(block_number, commit_receipts) => {
+                        inner.log_index.insert(
+                            rwtxn,
+                            *block_number,
+                            &commit_receipts.tx_receipts,
+                        )?;
+                        inner.commits.put(rwtxn, block_number, commit_receipts)?;
+                    }
+                    SnapshotRecord::Block(block_number, block) => {
+                        inner.blocks.put(rwtxn, block_number, block)?;
+                    }
+                    SnapshotRecord::BlockHash(block_number, block_hash) => {
+                        inner.blocks_number_hash.put(
+                            rwtxn,
+                            block_number,
+                            &HashWrapper(*block_hash),
+                        )?;
+                        inner.blocks_hash_number.put(
+                            rwtxn,
+                            &HashWrapper(*block_hash),
+                            block_number,
+                        )?;
+                    }
+                    SnapshotRecord::Proof(block_number, proof) => {
+                        inner.proofs.put(rwtxn, block_number, proof)?;
+                    }
+                    SnapshotRecord::Transaction(key, transaction) => {
+                        inner
+                            .transactions
+                            .put(rwtxn, &StringWrapper(key.clone()), transaction)?;
+                    }
+                    SnapshotRecord::TransactionHash(hash, key) => {
+                        inner
+                            .transactions_hash_key
+                            .put(rwtxn, &HashWrapper(*hash), key)?;
+                    }
+                    SnapshotRecord::ReceiptLocation(hash, location) => {
+                        inner
+                            .receipt_locations
+                            .put(rwtxn, &HashWrapper(*hash), location)?;
+                    }
+                    SnapshotRecord::Deployment(address, deployment) => {
+                        inner
+                            .deployments
+                            .put(rwtxn, &AddressWrapper(*address), deployment)?;
+                    }
+                    SnapshotRecord::End(_) => {}
+                }
+            }
+
+            // Accounts are exported before their storage, thus the slots are added to existing
+            // accounts
+            if let Some(trie) = &inner.trie {
+                let state_root = trie.update(
+                    rwtxn,
+                    inner,
+                    trie_accounts,
+                    trie_storage
+                        .into_iter()
+                        .map(|(address, slots)| (address, StorageTrieUpdate::Slots(slots)))
+                        .collect(),
+                )?;
+
+                if matches!(records.last(), Some(SnapshotRecord::End(_))) {
+                    trie.state_roots
+                        .put(rwtxn, &header.block_number, &HashWrapper(state_root))?;
+                }
+            }
+
+            Ok(())
+        };
+
+        if let Err(err) = apply_records(&mut rwtxn) {
+            rwtxn.abort();
+            return Err(map_db_full(err));
+        }
+
+        rwtxn.commit().map_err(|err| map_db_full(err.into()))?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_snapshot() {
+    use revm::{DatabaseRef, primitives::address};
+
+    use crate::{
+        db::{CommitData, PersistentDBOptions},
+        pruning::Retained,
+        trie::{TrieChanges, pending_commit},
+    };
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let options = |name: &str| {
+        PersistentDBOptions::new(path.path().join(name))
+            .with_state_root_mode(StateRootMode::MerklePatriciaTrie)
+    };
+
+    let mut db = PersistentDB::new(options("source")).expect("database");
+
+    let address1 = address!("bd6f65c58a46427af4b257cbe231d0ed69ed5508");
+    let address2 = address!("0000000000000000000000000000000000000002");
+
+    for block_number in 1..=3 {
+        crate::state_commit::commit_to_db(
+            &mut db,
+            pending_commit(
+                block_number,
+                vec![
+                    (address1, 100 * block_number, vec![(block_number, 0, 10)]),
+                    (address2, 5, vec![]),
+                ],
+            ),
+            Some(CommitData {
+                block_hash: B256::from(U256::from(1000 + block_number)),
+                block: Bytes::from_static(&[1]),
+                proof: Bytes::from_static(&[2]),
+                transaction_hashes: vec![B256::from(U256::from(2000 + block_number))],
+                transactions: vec![Bytes::from_static(&[3])],
+                ..Default::default()
+            }),
+        )
+        .expect("ok");
+    }
+
+    let file = path.path().join("snapshot.bin");
+
+    // Only the latest block
+    let info = db.export_snapshot(&file, false).expect("export");
+    assert_eq!(info.block_number, 3);
+
+    // Imported in several batches
+    let imported = PersistentDB::new(options("latest")).expect("database");
+    assert_eq!(
+        imported.import_snapshot_file(&file, 4).expect("import"),
+        info
+    );
+
+    assert_eq!(imported.get_state().unwrap(), db.get_state().unwrap());
+    assert_eq!(
+        imported.get_state_root(3).unwrap(),
+        db.get_state_root(3).unwrap()
+    );
+    assert_eq!(
+        imported.basic_ref(address1).unwrap(),
+        db.basic_ref(address1).unwrap()
+    );
+    for slot in 1..=3 {
+        assert_eq!(
+            imported.storage_ref(address1, U256::from(slot)).unwrap(),
+            U256::from(10)
+        );
+    }
+    assert_eq!(
+        imported.get_block_hash(1).unwrap(),
+        Some(B256::from(U256::from(1001)))
+    );
+    {
+        // the tries built batch by batch match the state root
+        let rtxn = imported.read_txn().unwrap();
+        let trie = imported.inner.trie.as_ref().expect("trie");
+        assert_eq!(
+            Some(
+                trie.calculate(&rtxn, TrieChanges::default())
+                    .unwrap()
+                    .state_root
+            ),
+            db.get_state_root(3).unwrap()
+        );
+    }
+    assert!(imported.is_block_committed(3));
+    assert!(!imported.is_block_committed(2));
+    assert_eq!(
+        imported.get_block_header_bytes(2).unwrap(),
+        Retained::Absent
+    );
+    assert_eq!(
+        imported
+            .get_transaction_key_by_hash(B256::from(U256::from(2003)))
+            .unwrap(),
+        None
+    );
+
+    // Importing into a non-empty database fails
+    assert!(matches!(
+        imported.import_snapshot(&file),
+        Err(Error::InvalidSnapshot(_))
+    ));
+
+    // Including all blocks
+    db.export_snapshot(&file, true).expect("export");
+
+    let imported = PersistentDB::new(options("full")).expect("database");
+    imported.import_snapshot(&file).expect("import");
+
+    assert_eq!(
+        imported.get_block_header_bytes(2).unwrap(),
+        Retained::Present(Bytes::from_static(&[1]))
+    );
+    assert!((1..=3).all(|block_number| imported.is_block_committed(block_number)));
+    assert_eq!(
+        imported
+            .get_transaction_key_by_hash(B256::from(U256::from(2002)))
+            .unwrap(),
+        Some("2-0".into())
+    );
+
+    // Corrupted snapshots are rejected without writing anything
+    let mut data = std::fs::read(&file).unwrap();
+    let index = data.len() / 2;
+    data[index] ^= 0xff;
+    std::fs::write(&file, data).unwrap();
+
+    let imported = PersistentDB::new(options("corrupted")).expect("database");
+    assert!(imported.import_snapshot(&file).is_err());
+    assert!(imported.is_empty().unwrap());
+}
@@ -1,22 +1,33 @@
 use std::collections::{BTreeMap, HashMap};
 
 use alloy_sol_types::SolEvent;
+use rayon::slice::ParallelSliceMut;
 use revm::{
     context::result::ExecutionResult,
     database::WrapDatabaseRef,
-    primitives::{Address, B256},
+    primitives::{Address, B256, BLOCK_HASH_HISTORY, U256},
 };
 
 use crate::{
-    db::{CommitData, CommitKey, Error, GenesisInfo, PendingCommit, PersistentDB},
-    state_changes::{self, AccountMergeInfo, AccountUpdate},
+    backend::{StorageBackend, StorageTxn},
+    db::{CommitData, CommitKey, CommitReceipts, Error, GenesisInfo, PendingCommit},
+    logger::{LogLevel, Logger},
+    logs_bloom,
+    receipt::{self, ExecutedTransaction},
+    state_changes::{self, AccountMergeInfo, AccountUpdate, StateChangeset},
+    state_root,
+    trie::TrieUpdates,
 };
 
 #[derive(Clone, Debug, Default)]
 pub struct StateCommit {
     pub key: CommitKey,
     pub change_set: state_changes::StateChangeset,
-    pub results: BTreeMap<B256, ExecutionResult>,
+    pub results: BTreeMap<B256, ExecutedTransaction>,
+    // Legacy state root calculated before committing
+    pub state_root: Option<B256>,
+    // Calculated together with the trie root, written by the commit unless the parent changed
+    pub(crate) trie_updates: Option<TrieUpdates>,
 }
 
 pub fn build_commit(pending_commit: &mut PendingCommit) -> Result<StateCommit, crate::db::Error> {
@@ -44,11 +55,13 @@ pub fn build_commit(pending_commit: &mut PendingCommit) -> Result<StateCommit, c
         key: pending_commit.key,
         change_set,
         results: std::mem::take(&mut pending_commit.results),
+        state_root: None,
+        trie_updates: None,
     })
 }
 
-pub fn apply_rewards(
-    db: &mut PersistentDB,
+pub fn apply_rewards<B: StorageBackend>(
+    db: &mut B,
     pending: &mut PendingCommit,
     rewards: HashMap<Address, u128>,
 ) -> Result<(), crate::db::Error> {
@@ -73,24 +86,24 @@ pub fn apply_rewards(
     Ok(())
 }
 
-pub fn commit_to_db(
-    db: &mut PersistentDB,
+pub fn commit_to_db<B: StorageBackend>(
+    db: &mut B,
     mut pending_commit: PendingCommit,
     commit_data: Option<CommitData>,
 ) -> Result<Vec<AccountUpdate>, crate::db::Error> {
-    let genesis_info = db.genesis_info.clone();
+    let genesis_info = db.genesis_info();
     let mut commit = match pending_commit.built_commit {
         Some(commit) => commit,
         None => build_commit(&mut pending_commit)?,
     };
 
-    match db.commit(&mut commit, &commit_data) {
+    match write_commit(db, &mut commit, &commit_data) {
         Ok(_) => Ok(collect_dirty_accounts(commit, &genesis_info)),
         Err(err) => match &err {
             Error::DbFull => {
                 // try to resize the db and attempt another commit on success
                 db.resize().and_then(|_| {
-                    db.commit(&mut commit, &commit_data)
+                    write_commit(db, &mut commit, &commit_data)
                         .and_then(|_| Ok(collect_dirty_accounts(commit, &genesis_info)))
                 })
             }
@@ -99,6 +112,187 @@ pub fn commit_to_db(
     }
 }
 
+fn write_commit<B: StorageBackend>(
+    db: &B,
+    commit: &mut StateCommit,
+    commit_data: &Option<CommitData>,
+) -> Result<(), Error> {
+    assert!(!db.is_block_committed(commit.key.0));
+
+    db.write(|txn| apply_commit(txn, db.logger(), commit, commit_data))
+}
+
+// Applies the commit through the table operations of the backend, such that all backends end up
+// with the same state and hashes.
+fn apply_commit<T: StorageTxn>(
+    txn: &mut T,
+    logger: &Logger,
+    state_commit: &mut StateCommit,
+    commit_data: &Option<CommitData>,
+) -> Result<(), Error> {
+    sort_change_set(&mut state_commit.change_set);
+
+    let StateCommit {
+        key,
+        change_set,
+        results,
+        state_root: calculated_state_root,
+        ..
+    } = &*state_commit;
+
+    // Update accounts
+    for (address, account) in change_set.accounts.iter() {
+        txn.put_account(key.0, *address, account.as_ref())?;
+    }
+
+    // Update account history
+    if txn.keeps_history() {
+        txn.insert_account_history(
+            key.0,
+            change_set
+                .accounts
+                .iter()
+                .map(|a| (a.0, a.1.clone().unwrap_or_default()))
+                .collect(),
+        )?;
+    }
+
+    // Update legacy attributes
+    for (address, legacy_attributes) in change_set.legacy_attributes.iter() {
+        txn.put_legacy_attributes(*address, legacy_attributes)?;
+    }
+
+    // Update legacy cold wallets
+    for (address, legacy_cold_wallet) in change_set.legacy_cold_wallets.iter() {
+        txn.put_legacy_cold_wallet(*address, legacy_cold_wallet)?;
+    }
+
+    // Update contracts
+    for (hash, bytecode) in change_set.contracts.iter() {
+        txn.put_contract(key.0, *hash, bytecode)?;
+    }
+
+    // Update storage
+    let mut storage_history = BTreeMap::<Address, BTreeMap<U256, U256>>::new();
+    for changeset in change_set.storage.iter() {
+        let slots = present_slots(changeset);
+        let wiped_slots = txn.write_storage(changeset.address, changeset.wipe_storage, &slots)?;
+
+        if txn.keeps_history() {
+            let history = storage_history.entry(changeset.address).or_default();
+
+            // wiped slots which did not get written again
+            history.extend(wiped_slots.into_iter().map(|slot| (slot, U256::ZERO)));
+            history.extend(slots);
+        }
+    }
+
+    // Update storage history
+    if txn.keeps_history() {
+        txn.insert_storage_history(key.0, storage_history)?;
+    }
+
+    // Mark legacy cold wallets as merged and migrate legacy attributes
+    for (address, (tx_hash, legacy_address)) in change_set.merged_legacy_cold_wallets.iter() {
+        logger.log(
+            LogLevel::Info,
+            format!(
+                "Merging legacy cold wallet '{}' with '{}'",
+                legacy_address, address
+            ),
+        );
+
+        let mut legacy_cold_wallet = txn
+            .get_legacy_cold_wallet(*legacy_address)?
+            .expect("legacy cold wallet to be found");
+
+        assert!(legacy_cold_wallet.merge_info.is_none());
+        legacy_cold_wallet.merge_info.replace((*tx_hash, *address));
+
+        txn.put_legacy_cold_wallet(*legacy_address, &legacy_cold_wallet)?;
+
+        // The legacy balance has already been applied to the `PendingCommit`,
+        // thus only the legacy attributes need to be moved to a different storage.
+        txn.put_legacy_attributes(*address, &legacy_cold_wallet.legacy_attributes)?;
+    }
+
+    if let Some(commit_data) = commit_data {
+        txn.put_block(key.0, commit_data)?;
+    }
+
+    // Update state trie
+    let state_root = txn.update_trie(state_commit)?;
+
+    // Finalize commit
+    let tx_receipts = receipt::build_receipts(results);
+
+    txn.put_commit(
+        key,
+        commit_data,
+        CommitReceipts {
+            accounts_hash: state_root::calculate_accounts_hash(change_set)?,
+            contracts_hash: state_root::calculate_contracts_hash(change_set)?,
+            storage_hash: state_root::calculate_storage_hash(change_set)?,
+            logs_bloom: Some(logs_bloom::from_receipts(&tx_receipts)),
+            // the trie root takes precedence over the legacy state root
+            state_root: state_root.or(*calculated_state_root),
+            tx_receipts,
+        },
+    )
+}
+
+pub(crate) fn present_slots(changeset: &state_changes::StorageChangeset) -> Vec<(U256, U256)> {
+    changeset
+        .storage
+        .iter()
+        .map(|(slot, value)| (*slot, value.present_value()))
+        .collect()
+}
+
+// The hashes of the commit depend on the order of the change set.
+fn sort_change_set(change_set: &mut StateChangeset) {
+    change_set.accounts.par_sort_by_key(|a| a.0);
+    change_set.contracts.par_sort_by_key(|a| a.0);
+    change_set.storage.par_sort_by_key(|a| a.address);
+
+    for storage in change_set.storage.iter_mut() {
+        storage.storage.par_sort_unstable_by_key(|a| a.0);
+    }
+}
+
+// Hashes of the processed blocks among `commit_keys` that are accessible by BLOCKHASH from
+// `block_number`. The persistent db only knows about committed blocks.
+pub fn pending_block_hashes<'a>(
+    commit_keys: impl IntoIterator<Item = &'a CommitKey>,
+    block_number: u64,
+) -> BTreeMap<u64, B256> {
+    let mut block_hashes = BTreeMap::<u64, Option<B256>>::new();
+
+    for commit_key in commit_keys {
+        if commit_key.0 >= block_number
+            || block_number - commit_key.0 > BLOCK_HASH_HISTORY
+            || commit_key.2.is_zero()
+        {
+            continue;
+        }
+
+        // Multiple rounds of the same block are ambiguous, leave those to the persistent db
+        block_hashes
+            .entry(commit_key.0)
+            .and_modify(|block_hash| {
+                if *block_hash != Some(commit_key.2) {
+                    *block_hash = None;
+                }
+            })
+            .or_insert(Some(commit_key.2));
+    }
+
+    block_hashes
+        .into_iter()
+        .filter_map(|(block_number, block_hash)| block_hash.map(|hash| (block_number, hash)))
+        .collect()
+}
+
 fn collect_dirty_accounts(
     commit: StateCommit,
     genesis_info: &Option<GenesisInfo>,
@@ -131,8 +325,8 @@ fn collect_dirty_accounts(
     }
 
     if let Some(info) = genesis_info {
-        for receipt in commit.results.values() {
-            match receipt {
+        for executed in commit.results.values() {
+            match &executed.result {
                 ExecutionResult::Success { logs, .. } => {
                     for log in logs {
                         match log.address {
@@ -214,7 +408,7 @@ fn test_apply_rewards() {
         .tempdir()
         .unwrap();
 
-    let mut db = PersistentDB::new(crate::db::PersistentDBOptions::new(
+    let mut db = crate::db::PersistentDB::new(crate::db::PersistentDBOptions::new(
         path.path().to_path_buf(),
     ))
     .expect("database");
@@ -236,3 +430,36 @@ fn test_apply_rewards() {
     assert!(pending.transitions.transitions.contains_key(&account1));
     assert!(!pending.transitions.transitions.contains_key(&account2));
 }
+
+#[test]
+fn test_pending_block_hashes() {
+    let hash = |byte| B256::repeat_byte(byte);
+
+    let commit_keys = [
+        // out of reach for BLOCKHASH from block 300
+        CommitKey(43, 0, hash(1)),
+        CommitKey(44, 0, hash(2)),
+        // no block hash known yet
+        CommitKey(45, 0, B256::ZERO),
+        // same block in multiple rounds with the same hash
+        CommitKey(46, 0, hash(3)),
+        CommitKey(46, 1, hash(3)),
+        // ambiguous rounds
+        CommitKey(47, 0, hash(4)),
+        CommitKey(47, 1, hash(5)),
+        CommitKey(299, 0, hash(6)),
+        // the block itself and later ones
+        CommitKey(300, 0, hash(7)),
+        CommitKey(301, 0, hash(8)),
+    ];
+
+    assert_eq!(
+        pending_block_hashes(&commit_keys, 300),
+        BTreeMap::from([(44, hash(2)), (46, hash(3)), (299, hash(6))])
+    );
+    assert_eq!(
+        pending_block_hashes(&commit_keys, 301),
+        BTreeMap::from([(46, hash(3)), (299, hash(6)), (300, hash(7))])
+    );
+    assert!(pending_block_hashes(&commit_keys, 1).is_empty());
+}
@@ -1,18 +1,49 @@
 use rayon::slice::ParallelSliceMut;
-use revm::primitives::{B256, keccak256};
+use revm::primitives::{B256, Bytes, keccak256};
 use serde::Serialize;
 
 use crate::{
-    db::{GenesisInfo, PendingCommit, PersistentDB},
+    backend::StorageBackend,
+    db::{GenesisInfo, PendingCommit},
     state_changes::StateChangeset,
     state_commit::{StateCommit, build_commit},
 };
 
-pub fn calculate(
-    db: &mut PersistentDB,
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StateRootMode {
+    // Hash over the block number, genesis info, previous state root and the applied change set.
+    #[default]
+    Legacy,
+    // Root of the Ethereum account trie, see `trie.rs`.
+    MerklePatriciaTrie,
+}
+
+impl StateRootMode {
+    pub(crate) fn from_bytes(bytes: &Bytes) -> Self {
+        match bytes.as_ref() {
+            [0] => StateRootMode::Legacy,
+            [1] => StateRootMode::MerklePatriciaTrie,
+            _ => panic!("invalid state root mode {}", bytes),
+        }
+    }
+
+    pub(crate) fn to_bytes(self) -> Bytes {
+        match self {
+            StateRootMode::Legacy => Bytes::from_static(&[0]),
+            StateRootMode::MerklePatriciaTrie => Bytes::from_static(&[1]),
+        }
+    }
+}
+
+pub fn calculate<B: StorageBackend>(
+    db: &mut B,
     pending_commit: &mut PendingCommit,
     current_hash: B256,
 ) -> Result<B256, crate::db::Error> {
+    if db.state_root_mode() == StateRootMode::MerklePatriciaTrie {
+        return calculate_trie_root(db, pending_commit);
+    }
+
     let committed_hashes = db.get_committed_hashes(pending_commit.key.0)?;
 
     if pending_commit.built_commit.is_none() {
@@ -20,15 +51,43 @@ pub fn calculate(
         pending_commit.built_commit.replace(state_commit);
     };
 
-    calculate_state_root(
+    let state_commit = pending_commit
+        .built_commit
+        .as_mut()
+        .expect("state commit exists");
+
+    let state_root = calculate_state_root(
         current_hash,
-        pending_commit
-            .built_commit
-            .as_ref()
-            .expect("state commit exists"),
+        state_commit,
         committed_hashes,
-        &db.genesis_info,
-    )
+        &db.genesis_info(),
+    )?;
+
+    // Stored together with the commit
+    state_commit.state_root.replace(state_root);
+
+    Ok(state_root)
+}
+
+fn calculate_trie_root<B: StorageBackend>(
+    db: &mut B,
+    pending_commit: &mut PendingCommit,
+) -> Result<B256, crate::db::Error> {
+    if let Some(state_root) = db.get_state_root(pending_commit.key.0)? {
+        return Ok(state_root);
+    }
+
+    if pending_commit.built_commit.is_none() {
+        let state_commit = build_commit(pending_commit)?;
+        pending_commit.built_commit.replace(state_commit);
+    };
+
+    let state_commit = pending_commit
+        .built_commit
+        .as_mut()
+        .expect("state commit exists");
+
+    db.calculate_trie_root(state_commit)
 }
 
 fn calculate_state_root(
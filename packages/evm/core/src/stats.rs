@@ -0,0 +1,181 @@
+use std::sync::atomic;
+
+use heed::RoTxn;
+
+use crate::db::{Error, PersistentDB};
+
+// Size and shape of a single named database within `evm.mdb`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableStats {
+    pub name: &'static str,
+    pub entries: u64,
+    pub depth: u32,
+    pub branch_pages: u64,
+    pub leaf_pages: u64,
+    pub overflow_pages: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageStats {
+    pub map_size: u64,
+    // Size up to the last page in use, free pages which are reused by later commits included
+    pub used_size: u64,
+    pub real_disk_size: u64,
+    // Number of map resizes since the database got opened
+    pub resize_count: u64,
+    pub tables: Vec<TableStats>,
+}
+
+fn table_stats<KC, DC, C, CDUP>(
+    txn: &RoTxn,
+    name: &'static str,
+    database: &heed::Database<KC, DC, C, CDUP>,
+) -> Result<TableStats, Error> {
+    let stat = database.stat(txn)?;
+
+    Ok(TableStats {
+        name,
+        entries: stat.entries as u64,
+        depth: stat.depth,
+        branch_pages: stat.branch_pages as u64,
+        leaf_pages: stat.leaf_pages as u64,
+        overflow_pages: stat.overflow_pages as u64,
+    })
+}
+
+macro_rules! collect_table_stats {
+    ($txn:expr, $($name:literal => $database:expr),* $(,)?) => {
+        vec![$(table_stats($txn, $name, &$database)?),*]
+    };
+}
+
+impl PersistentDB {
+    // Reports the size of each table next to the size of the map and file, optional tables are
+    // only listed if enabled.
+    pub fn storage_stats(&self) -> Result<StorageStats, Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        let page_size = inner.accounts.stat(&rtxn)?.page_size as u64;
+
+        let mut tables = collect_table_stats!(
+            &rtxn,
+            "accounts" => inner.accounts,
+            "commits" => inner.commits,
+            "contracts" => inner.contracts,
+            "legacy_attributes" => inner.legacy_attributes,
+            "legacy_cold_wallets" => inner.legacy_cold_wallets,
+            "storage" => inner.storage,
+            "state" => inner.state,
+            "proofs" => inner.proofs,
+            "blocks" => inner.blocks,
+            "blocks_hash_number" => inner.blocks_hash_number,
+            "blocks_number_hash" => inner.blocks_number_hash,
+            "transactions" => inner.transactions,
+            "transactions_hash_key" => inner.transactions_hash_key,
+            "receipt_locations" => inner.receipt_locations,
+            "reverts" => inner.reverts,
+            "contract_deployments" => inner.deployments,
+            "log_addresses" => inner.log_index.addresses,
+            "log_topics" => inner.log_index.topics,
+            "account_balances" => inner.balance_index.balances,
+            "code_references" => inner.code_references.references,
+            "orphaned_code" => inner.code_references.orphans,
+        );
+
+        if let Some(database) = &inner.accounts_history {
+            tables.push(table_stats(&rtxn, "accounts_history", database)?);
+        }
+        if let Some(database) = &inner.storage_history {
+            tables.push(table_stats(&rtxn, "storage_history", database)?);
+        }
+        if let Some(trie) = &inner.trie {
+            tables.extend(collect_table_stats!(
+                &rtxn,
+                "hashed_accounts" => trie.hashed_accounts,
+                "hashed_storage" => trie.hashed_storage,
+                "state_roots" => trie.state_roots,
+                "account_trie_nodes" => trie.account_nodes,
+                "storage_trie_nodes" => trie.storage_nodes,
+            ));
+        }
+
+        drop(rtxn);
+
+        let info = self.env.info();
+
+        Ok(StorageStats {
+            map_size: info.map_size as u64,
+            used_size: (info.last_page_number as u64 + 1) * page_size,
+            real_disk_size: self.env.real_disk_size()?,
+            resize_count: self.resize_count.load(atomic::Ordering::Relaxed),
+            tables,
+        })
+    }
+}
+
+#[test]
+fn test_storage_stats() {
+    use revm::primitives::address;
+
+    use crate::{db::PersistentDBOptions, state_commit::commit_to_db, trie::pending_commit};
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+
+    let alice = address!("1000000000000000000000000000000000000001");
+    let bob = address!("2000000000000000000000000000000000000002");
+
+    commit_to_db(
+        &mut db,
+        pending_commit(1, vec![(alice, 100, vec![(1, 0, 1)]), (bob, 200, vec![])]),
+        None,
+    )
+    .expect("commit");
+
+    let stats = db.storage_stats().unwrap();
+    let table = |name: &str| {
+        stats
+            .tables
+            .iter()
+            .find(|table| table.name == name)
+            .cloned()
+            .expect("table")
+    };
+
+    assert_eq!(table("accounts").entries, 2);
+    assert_eq!(table("storage").entries, 1);
+    assert_eq!(table("commits").entries, 1);
+    assert!(table("accounts").depth > 0);
+    assert!(table("accounts").leaf_pages > 0);
+
+    // Optional tables are only listed if enabled
+    assert!(
+        stats
+            .tables
+            .iter()
+            .all(|table| table.name != "accounts_history")
+    );
+    assert!(
+        stats
+            .tables
+            .iter()
+            .all(|table| table.name != "hashed_accounts")
+    );
+
+    assert!(stats.used_size > 0);
+    assert!(stats.used_size <= stats.map_size);
+    assert!(stats.real_disk_size > 0);
+    assert_eq!(stats.resize_count, 0);
+
+    db.resize().expect("resize");
+
+    let resized = db.storage_stats().unwrap();
+    assert_eq!(resized.resize_count, 1);
+    assert!(resized.map_size > stats.map_size);
+}
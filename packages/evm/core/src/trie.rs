@@ -0,0 +1,1386 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    ops::Bound,
+};
+
+use alloy_trie::{
+    BranchNodeCompact, EMPTY_ROOT_HASH, HashBuilder, HashMap, KECCAK_EMPTY, Nibbles, TrieAccount,
+    proof::{ProofNodes, ProofRetainer},
+};
+use heed::{RoTxn, RwTxn, WithoutTls};
+use revm::{
+    primitives::{Address, B256, Bytes, U256, keccak256},
+    state::AccountInfo,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::{
+        AddressWrapper, Error, HashWrapper, InnerStorage, StorageEntryDupSortCmp, TablesTxn,
+        open_table,
+    },
+    state_changes::StateChangeset,
+};
+
+// Leaf of the account trie, keyed by `keccak256(address)`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashedAccount {
+    pub nonce: u64,
+    pub balance: U256,
+    pub code_hash: B256,
+    pub storage_root: B256,
+}
+
+impl From<&HashedAccount> for TrieAccount {
+    fn from(value: &HashedAccount) -> Self {
+        TrieAccount {
+            nonce: value.nonce,
+            balance: value.balance,
+            storage_root: value.storage_root,
+            code_hash: value.code_hash,
+        }
+    }
+}
+
+// Proof of an account against the state root, as returned by `eth_getProof`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountProof {
+    pub address: Address,
+    pub balance: U256,
+    pub nonce: u64,
+    pub code_hash: B256,
+    pub storage_root: B256,
+    // RLP encoded trie nodes from the root towards the account leaf.
+    pub account_proof: Vec<Bytes>,
+    pub storage_proofs: Vec<StorageProof>,
+}
+
+// Proof of a storage slot against the storage root of the account.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageProof {
+    pub key: U256,
+    pub value: U256,
+    pub proof: Vec<Bytes>,
+}
+
+// Leaf of a storage trie, a tuple of `keccak256(slot)` and the slot value. Uses the same
+// layout as `StorageEntryWrapper` so that duplicates are sorted by the hashed slot.
+#[derive(Debug)]
+pub(crate) struct HashedStorageEntryWrapper(pub(crate) B256, pub(crate) U256);
+impl heed::BytesEncode<'_> for HashedStorageEntryWrapper {
+    type EItem = HashedStorageEntryWrapper;
+
+    fn bytes_encode(item: &Self::EItem) -> Result<Cow<[u8]>, heed::BoxedError> {
+        let mut combined = Vec::with_capacity(64);
+        combined.extend_from_slice(item.0.as_slice());
+        combined.extend_from_slice(&item.1.to_be_bytes::<32>());
+
+        Ok(Cow::Owned(combined))
+    }
+}
+
+impl heed::BytesDecode<'_> for HashedStorageEntryWrapper {
+    type DItem = HashedStorageEntryWrapper;
+
+    fn bytes_decode(bytes: &'_ [u8]) -> Result<Self::DItem, heed::BoxedError> {
+        let a = B256::from_slice(&bytes[0..32]);
+        let b = U256::from_be_slice(&bytes[32..64]);
+        Ok(HashedStorageEntryWrapper(a, b))
+    }
+}
+
+pub(crate) enum StorageTrieUpdate {
+    // Rebuilds the storage trie from the plain storage, used after a wipe or an unwind.
+    Rebuild,
+    // Updated slots with their new values, a zero value removes the slot.
+    Slots(Vec<(U256, U256)>),
+}
+
+// Branch node of a trie as produced by the `HashBuilder`. Only branches with at least one child
+// branch are stored, their hashes allow skipping the unchanged subtries when updating the root.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct StoredBranchNode {
+    pub state_mask: u16,
+    // Children with stored branch nodes below them.
+    pub tree_mask: u16,
+    // Children which are branch nodes, their hashes are kept in `hashes`.
+    pub hash_mask: u16,
+    pub hashes: Vec<B256>,
+}
+
+impl From<&BranchNodeCompact> for StoredBranchNode {
+    fn from(value: &BranchNodeCompact) -> Self {
+        StoredBranchNode {
+            state_mask: value.state_mask.get(),
+            tree_mask: value.tree_mask.get(),
+            hash_mask: value.hash_mask.get(),
+            hashes: value.hashes.iter().copied().collect(),
+        }
+    }
+}
+
+impl StoredBranchNode {
+    fn hash_for_nibble(&self, nibble: u8) -> Option<B256> {
+        if self.hash_mask & (1 << nibble) == 0 {
+            return None;
+        }
+
+        let index = (self.hash_mask & ((1 << nibble) - 1)).count_ones();
+        Some(self.hashes[index as usize])
+    }
+
+    fn in_tree(&self, nibble: u8) -> bool {
+        self.tree_mask & (1 << nibble) != 0
+    }
+}
+
+// Changed leaves of the storage trie of an account, keyed by the hashed slot.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StorageChanges {
+    // The stored slots are discarded.
+    pub wiped: bool,
+    // A zero value removes the slot.
+    pub slots: BTreeMap<B256, U256>,
+}
+
+// Changes of the plain state to apply to the tries, keyed by the hashed address. Accounts whose
+// storage changed but which are missing in `accounts` keep their other fields.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TrieChanges {
+    pub accounts: BTreeMap<B256, Option<AccountInfo>>,
+    pub storage: BTreeMap<B256, StorageChanges>,
+}
+
+impl TrieChanges {
+    pub fn from_change_set(change_set: &StateChangeset) -> Self {
+        let mut changes = TrieChanges::default();
+
+        for (address, account) in change_set.accounts.iter() {
+            changes.accounts.insert(keccak256(address), account.clone());
+        }
+
+        for storage in change_set.storage.iter() {
+            let changed = changes
+                .storage
+                .entry(keccak256(storage.address))
+                .or_default();
+
+            if storage.wipe_storage {
+                changed.wiped = true;
+                changed.slots.clear();
+            }
+
+            for (slot, value) in storage.storage.iter() {
+                changed
+                    .slots
+                    .insert(hash_slot(*slot), value.present_value());
+            }
+        }
+
+        changes
+    }
+
+    // Reads the changes from the plain state, which has already been written.
+    pub fn from_plain_state(
+        txn: &RoTxn,
+        inner: &InnerStorage,
+        accounts: BTreeSet<Address>,
+        storage: Vec<(Address, StorageTrieUpdate)>,
+    ) -> Result<Self, Error> {
+        let mut changes = TrieChanges::default();
+
+        for (address, update) in storage {
+            let changed = changes.storage.entry(keccak256(address)).or_default();
+
+            match update {
+                StorageTrieUpdate::Rebuild => {
+                    changed.wiped = true;
+                    changed.slots.clear();
+
+                    if let Some(iter) = inner
+                        .storage
+                        .get_duplicates(txn, &AddressWrapper(address))?
+                    {
+                        for item in iter {
+                            let (_, entry) = item?;
+                            changed.slots.insert(hash_slot(entry.0), entry.1);
+                        }
+                    }
+                }
+                StorageTrieUpdate::Slots(slots) => {
+                    for (slot, value) in slots {
+                        changed.slots.insert(hash_slot(slot), value);
+                    }
+                }
+            }
+
+            changes.accounts.insert(
+                keccak256(address),
+                inner.accounts.get(txn, &AddressWrapper(address))?,
+            );
+        }
+
+        for address in accounts {
+            changes.accounts.insert(
+                keccak256(address),
+                inner.accounts.get(txn, &AddressWrapper(address))?,
+            );
+        }
+
+        Ok(changes)
+    }
+}
+
+// Stored branch nodes to replace after updating a trie.
+#[derive(Clone, Debug, Default)]
+struct NodeUpdates {
+    // All stored nodes of the trie are removed first.
+    wiped: bool,
+    removed: Vec<Nibbles>,
+    updated: Vec<(Nibbles, StoredBranchNode)>,
+}
+
+impl NodeUpdates {
+    fn new(
+        wiped: bool,
+        visited: Vec<Nibbles>,
+        updated: HashMap<Nibbles, BranchNodeCompact>,
+    ) -> Self {
+        let removed = visited
+            .into_iter()
+            .filter(|path| !updated.contains_key(path))
+            .collect();
+
+        let mut updated = updated
+            .iter()
+            .map(|(path, node)| (path.clone(), StoredBranchNode::from(node)))
+            .collect::<Vec<_>>();
+        updated.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        NodeUpdates {
+            wiped,
+            removed,
+            updated,
+        }
+    }
+}
+
+// Result of applying `TrieChanges` to the tries, calculated without writing anything.
+#[derive(Clone, Debug)]
+pub(crate) struct TrieUpdates {
+    // Latest committed block and its state root the updates are based on.
+    pub parent: Option<(u64, B256)>,
+    pub state_root: B256,
+    accounts: BTreeMap<B256, Option<HashedAccount>>,
+    storage: BTreeMap<B256, StorageChanges>,
+    account_nodes: NodeUpdates,
+    storage_nodes: BTreeMap<B256, NodeUpdates>,
+}
+
+// Tables backing the Merkle Patricia Trie state root. They mirror the plain `accounts` and
+// `storage` tables with hashed keys, which is the order in which the trie is built. The branch
+// nodes are kept such that updating the root only walks the changed paths.
+#[derive(Clone)]
+pub(crate) struct TrieTables {
+    pub hashed_accounts: heed::Database<HashWrapper, heed::types::SerdeBincode<HashedAccount>>,
+    pub hashed_storage: heed::Database<
+        HashWrapper,
+        HashedStorageEntryWrapper,
+        heed::DefaultComparator,
+        StorageEntryDupSortCmp,
+    >,
+    pub state_roots: heed::Database<heed::types::U64<heed::byteorder::BigEndian>, HashWrapper>,
+    // Keyed by `ACCOUNT_NODE_PREFIX` and the nibbles of the path, one byte per nibble.
+    pub account_nodes:
+        heed::Database<heed::types::Bytes, heed::types::SerdeBincode<StoredBranchNode>>,
+    // Keyed by the hashed address and the nibbles of the path, one byte per nibble.
+    pub storage_nodes:
+        heed::Database<heed::types::Bytes, heed::types::SerdeBincode<StoredBranchNode>>,
+}
+
+// LMDB does not support empty keys, which the root node of the account trie would have.
+const ACCOUNT_NODE_PREFIX: u8 = 0;
+
+impl TrieTables {
+    pub const MAX_DBS: u32 = 5;
+
+    pub fn create(env: &heed::Env<WithoutTls>, txn: &mut TablesTxn) -> Result<Self, Error> {
+        let hashed_accounts = txn
+            .database::<HashWrapper, heed::types::SerdeBincode<HashedAccount>>(
+                env,
+                "hashed_accounts",
+            )?;
+        let hashed_storage = open_table!(
+            txn,
+            "hashed_storage",
+            env.database_options()
+                .types::<HashWrapper, HashedStorageEntryWrapper>()
+                .name("hashed_storage")
+                .flags(heed::DatabaseFlags::DUP_SORT)
+                .dup_sort_comparator::<StorageEntryDupSortCmp>()
+        );
+        let state_roots = txn
+            .database::<heed::types::U64<heed::byteorder::BigEndian>, HashWrapper>(
+                env,
+                "state_roots",
+            )?;
+        // Databases created before the nodes were kept start without any, the first commit
+        // walks the whole trie once and stores them.
+        let account_nodes = txn
+            .database::<heed::types::Bytes, heed::types::SerdeBincode<StoredBranchNode>>(
+                env,
+                "account_trie_nodes",
+            )?;
+        let storage_nodes = txn
+            .database::<heed::types::Bytes, heed::types::SerdeBincode<StoredBranchNode>>(
+                env,
+                "storage_trie_nodes",
+            )?;
+
+        Ok(Self {
+            hashed_accounts,
+            hashed_storage,
+            state_roots,
+            account_nodes,
+            storage_nodes,
+        })
+    }
+
+    // Brings the tries in line with the plain state of the given accounts and returns the new
+    // state root. Must be called after the plain state has been written.
+    pub fn update(
+        &self,
+        txn: &mut RwTxn,
+        inner: &InnerStorage,
+        accounts: BTreeSet<Address>,
+        storage: Vec<(Address, StorageTrieUpdate)>,
+    ) -> Result<B256, Error> {
+        let changes = TrieChanges::from_plain_state(txn, inner, accounts, storage)?;
+        let updates = self.calculate(txn, changes)?;
+        self.write(txn, &updates)?;
+
+        Ok(updates.state_root)
+    }
+
+    // Latest committed block and its state root.
+    pub fn parent(&self, txn: &RoTxn) -> Result<Option<(u64, B256)>, Error> {
+        Ok(self
+            .state_roots
+            .last(txn)?
+            .map(|(block_number, state_root)| (block_number, state_root.0)))
+    }
+
+    // Applies the changes on top of the stored tries without writing anything. Only the paths
+    // towards changed leaves are walked, see `TrieWalker`.
+    pub fn calculate(&self, txn: &RoTxn, changes: TrieChanges) -> Result<TrieUpdates, Error> {
+        let TrieChanges { accounts, storage } = changes;
+
+        let mut storage_roots = BTreeMap::new();
+        let mut storage_nodes = BTreeMap::new();
+        let mut changed_storage = BTreeMap::new();
+
+        for (hashed_address, changes) in storage {
+            // deleted accounts lose their storage below
+            if matches!(accounts.get(&hashed_address), Some(None)) {
+                continue;
+            }
+
+            let source = StorageTrieSource {
+                tables: self,
+                txn,
+                hashed_address,
+                changes: &changes,
+            };
+
+            let (storage_root, visited, updated) =
+                TrieWalker::new(&source, changes.slots.keys().copied().collect()).run()?;
+
+            storage_roots.insert(hashed_address, storage_root);
+            storage_nodes.insert(
+                hashed_address,
+                NodeUpdates::new(changes.wiped, visited, updated),
+            );
+            changed_storage.insert(hashed_address, changes);
+        }
+
+        let mut changed_accounts = BTreeMap::new();
+
+        let hashed_addresses = accounts
+            .keys()
+            .chain(storage_roots.keys())
+            .copied()
+            .collect::<BTreeSet<_>>();
+
+        for hashed_address in hashed_addresses {
+            let stored = self
+                .hashed_accounts
+                .get(txn, &HashWrapper(hashed_address))?;
+
+            let account = match (accounts.get(&hashed_address), stored) {
+                (Some(Some(info)), stored) => Some(HashedAccount {
+                    nonce: info.nonce,
+                    balance: info.balance,
+                    code_hash: info.code_hash,
+                    storage_root: match (storage_roots.get(&hashed_address), stored) {
+                        (Some(storage_root), _) => *storage_root,
+                        (None, Some(stored)) => stored.storage_root,
+                        (None, None) => EMPTY_ROOT_HASH,
+                    },
+                }),
+                // only the storage changed
+                (None, Some(stored)) => Some(HashedAccount {
+                    storage_root: storage_roots[&hashed_address],
+                    ..stored
+                }),
+                (Some(None), _) | (None, None) => None,
+            };
+
+            if account.is_none() {
+                changed_storage.insert(
+                    hashed_address,
+                    StorageChanges {
+                        wiped: true,
+                        slots: BTreeMap::new(),
+                    },
+                );
+                storage_nodes.insert(
+                    hashed_address,
+                    NodeUpdates {
+                        wiped: true,
+                        ..Default::default()
+                    },
+                );
+            }
+
+            changed_accounts.insert(hashed_address, account);
+        }
+
+        let source = AccountTrieSource {
+            tables: self,
+            txn,
+            changes: &changed_accounts,
+        };
+
+        let (state_root, visited, updated) =
+            TrieWalker::new(&source, changed_accounts.keys().copied().collect()).run()?;
+
+        Ok(TrieUpdates {
+            parent: self.parent(txn)?,
+            state_root,
+            accounts: changed_accounts,
+            storage: changed_storage,
+            account_nodes: NodeUpdates::new(false, visited, updated),
+            storage_nodes,
+        })
+    }
+
+    pub fn write(&self, txn: &mut RwTxn, updates: &TrieUpdates) -> Result<(), Error> {
+        for (hashed_address, account) in updates.accounts.iter() {
+            let hashed_address = HashWrapper(*hashed_address);
+
+            match account {
+                Some(account) => self.hashed_accounts.put(txn, &hashed_address, account)?,
+                None => {
+                    self.hashed_accounts.delete(txn, &hashed_address)?;
+                }
+            }
+        }
+
+        for (hashed_address, changes) in updates.storage.iter() {
+            let hashed_address = HashWrapper(*hashed_address);
+
+            if changes.wiped {
+                self.hashed_storage.delete(txn, &hashed_address)?;
+            }
+
+            for (hashed_slot, value) in changes.slots.iter() {
+                // the dup sort comparator only looks at the hashed slot, thus this removes the
+                // previous entry regardless of its value
+                let entry = HashedStorageEntryWrapper(*hashed_slot, *value);
+                self.hashed_storage
+                    .delete_one_duplicate(txn, &hashed_address, &entry)?;
+
+                if !value.is_zero() {
+                    self.hashed_storage.put(txn, &hashed_address, &entry)?;
+                }
+            }
+        }
+
+        write_nodes(
+            txn,
+            &self.account_nodes,
+            &[ACCOUNT_NODE_PREFIX],
+            &updates.account_nodes,
+        )?;
+
+        for (hashed_address, nodes) in updates.storage_nodes.iter() {
+            write_nodes(txn, &self.storage_nodes, hashed_address.as_slice(), nodes)?;
+        }
+
+        Ok(())
+    }
+
+    // Generates the proofs of an account and the given storage slots against the current state
+    // root. Only the paths towards the account and the slots are walked, all other subtries are
+    // taken from the stored branch nodes.
+    pub fn proof(
+        &self,
+        txn: &RoTxn,
+        address: Address,
+        slots: &[U256],
+    ) -> Result<AccountProof, Error> {
+        let hashed_address = HashWrapper(keccak256(address));
+
+        let source = AccountTrieSource {
+            tables: self,
+            txn,
+            changes: &BTreeMap::new(),
+        };
+        let account_proof = TrieWalker::with_proof(&source, vec![hashed_address.0])
+            .proof()?
+            .matching_nodes_sorted(&Nibbles::unpack(hashed_address.0))
+            .into_iter()
+            .map(|(_, node)| node)
+            .collect();
+
+        let account = self
+            .hashed_accounts
+            .get(txn, &hashed_address)?
+            .unwrap_or_else(|| HashedAccount {
+                code_hash: KECCAK_EMPTY,
+                storage_root: EMPTY_ROOT_HASH,
+                ..Default::default()
+            });
+
+        let hashed_slots = slots
+            .iter()
+            .map(|slot| hash_slot(*slot))
+            .collect::<Vec<_>>();
+
+        let changes = StorageChanges::default();
+        let source = StorageTrieSource {
+            tables: self,
+            txn,
+            hashed_address: hashed_address.0,
+            changes: &changes,
+        };
+        let proof_nodes = TrieWalker::with_proof(&source, hashed_slots.clone()).proof()?;
+
+        let mut storage_proofs = Vec::with_capacity(slots.len());
+        for (slot, hashed_slot) in slots.iter().zip(hashed_slots) {
+            let mut iter = self.hashed_storage.iter(txn)?;
+            let location = &HashedStorageEntryWrapper(hashed_slot, U256::ZERO);
+
+            let value = match iter.move_on_key_dup(&hashed_address, location)? {
+                Some((_, entry)) if entry.0 == location.0 => entry.1,
+                _ => U256::ZERO,
+            };
+
+            storage_proofs.push(StorageProof {
+                key: *slot,
+                value,
+                proof: proof_nodes
+                    .matching_nodes_sorted(&Nibbles::unpack(hashed_slot))
+                    .into_iter()
+                    .map(|(_, node)| node)
+                    .collect(),
+            });
+        }
+
+        Ok(AccountProof {
+            address,
+            balance: account.balance,
+            nonce: account.nonce,
+            code_hash: account.code_hash,
+            storage_root: account.storage_root,
+            account_proof,
+            storage_proofs,
+        })
+    }
+}
+
+fn write_nodes(
+    txn: &mut RwTxn,
+    database: &heed::Database<heed::types::Bytes, heed::types::SerdeBincode<StoredBranchNode>>,
+    prefix: &[u8],
+    nodes: &NodeUpdates,
+) -> Result<(), Error> {
+    if nodes.wiped {
+        let mut iter = database.prefix_iter_mut(txn, prefix)?;
+        while iter.next().transpose()?.is_some() {
+            // the deleted entry is not borrowed anymore
+            unsafe { iter.del_current()? };
+        }
+    }
+
+    for path in nodes.removed.iter() {
+        database.delete(txn, &node_key(prefix, path))?;
+    }
+
+    for (path, node) in nodes.updated.iter() {
+        database.put(txn, &node_key(prefix, path), node)?;
+    }
+
+    Ok(())
+}
+
+fn node_key(prefix: &[u8], path: &Nibbles) -> Vec<u8> {
+    let mut key = Vec::with_capacity(prefix.len() + path.len());
+    key.extend_from_slice(prefix);
+    key.extend_from_slice(path.as_slice());
+    key
+}
+
+// First stored node at or after `path` within the trie identified by `prefix`.
+fn seek_node(
+    txn: &RoTxn,
+    database: &heed::Database<heed::types::Bytes, heed::types::SerdeBincode<StoredBranchNode>>,
+    prefix: &[u8],
+    path: &Nibbles,
+) -> Result<Option<(Nibbles, StoredBranchNode)>, Error> {
+    let key = node_key(prefix, path);
+
+    match database
+        .range(txn, &(Bound::Included(key.as_slice()), Bound::Unbounded))?
+        .next()
+        .transpose()?
+    {
+        Some((key, node)) if key.starts_with(prefix) => Ok(Some((
+            Nibbles::from_nibbles_unchecked(&key[prefix.len()..]),
+            node,
+        ))),
+        _ => Ok(None),
+    }
+}
+
+// Smallest hashed key below the path.
+fn lower_bound(path: &Nibbles) -> B256 {
+    let mut key = B256::ZERO;
+    for (index, nibble) in path.iter().enumerate() {
+        key[index / 2] |= if index % 2 == 0 { nibble << 4 } else { *nibble };
+    }
+    key
+}
+
+// Smallest hashed key after all keys below the path, `None` past the last key.
+fn upper_bound(path: &Nibbles) -> Option<B256> {
+    next_path(path).map(|path| lower_bound(&path))
+}
+
+// Shortest path after all paths below `path`, `None` if there is none.
+fn next_path(path: &Nibbles) -> Option<Nibbles> {
+    let mut nibbles = path.to_vec();
+
+    while let Some(nibble) = nibbles.pop() {
+        if nibble < 0xf {
+            nibbles.push(nibble + 1);
+            return Some(Nibbles::from_nibbles_unchecked(nibbles));
+        }
+    }
+
+    None
+}
+
+// Leaves and stored branch nodes of a single trie.
+trait TrieSource {
+    fn seek_node(&self, path: &Nibbles) -> Result<Option<(Nibbles, StoredBranchNode)>, Error>;
+
+    // Adds the leaves with hashed keys within `[from, to)` in ascending order.
+    fn add_leaves(
+        &self,
+        from: B256,
+        to: Option<B256>,
+        hash_builder: &mut HashBuilder,
+    ) -> Result<(), Error>;
+}
+
+struct AccountTrieSource<'a, 't> {
+    tables: &'a TrieTables,
+    txn: &'a RoTxn<'t>,
+    changes: &'a BTreeMap<B256, Option<HashedAccount>>,
+}
+
+impl TrieSource for AccountTrieSource<'_, '_> {
+    fn seek_node(&self, path: &Nibbles) -> Result<Option<(Nibbles, StoredBranchNode)>, Error> {
+        seek_node(
+            self.txn,
+            &self.tables.account_nodes,
+            &[ACCOUNT_NODE_PREFIX],
+            path,
+        )
+    }
+
+    fn add_leaves(
+        &self,
+        from: B256,
+        to: Option<B256>,
+        hash_builder: &mut HashBuilder,
+    ) -> Result<(), Error> {
+        let end = match to {
+            Some(to) => Bound::Excluded(HashWrapper(to)),
+            None => Bound::Unbounded,
+        };
+
+        let stored = self
+            .tables
+            .hashed_accounts
+            .range(self.txn, &(Bound::Included(HashWrapper(from)), end))?
+            .map(|item| {
+                item.map(|(hashed_address, account)| (hashed_address.0, account))
+                    .map_err(Error::from)
+            });
+
+        let changed = self
+            .changes
+            .range((
+                Bound::Included(from),
+                to.map_or(Bound::Unbounded, Bound::Excluded),
+            ))
+            .map(|(hashed_address, account)| (*hashed_address, account.clone()));
+
+        merge_leaves(stored, changed, |hashed_address, account| {
+            hash_builder.add_leaf(
+                Nibbles::unpack(hashed_address),
+                &alloy_rlp::encode(TrieAccount::from(&account)),
+            );
+        })
+    }
+}
+
+struct StorageTrieSource<'a, 't> {
+    tables: &'a TrieTables,
+    txn: &'a RoTxn<'t>,
+    hashed_address: B256,
+    changes: &'a StorageChanges,
+}
+
+impl TrieSource for StorageTrieSource<'_, '_> {
+    fn seek_node(&self, path: &Nibbles) -> Result<Option<(Nibbles, StoredBranchNode)>, Error> {
+        // the stored nodes of a wiped trie are outdated
+        if self.changes.wiped {
+            return Ok(None);
+        }
+
+        seek_node(
+            self.txn,
+            &self.tables.storage_nodes,
+            self.hashed_address.as_slice(),
+            path,
+        )
+    }
+
+    fn add_leaves(
+        &self,
+        from: B256,
+        to: Option<B256>,
+        hash_builder: &mut HashBuilder,
+    ) -> Result<(), Error> {
+        let hashed_address = HashWrapper(self.hashed_address);
+        let mut iter = self.tables.hashed_storage.iter(self.txn)?;
+
+        // The cursor is positioned on the first slot, the following items continue from there
+        let first = match self.changes.wiped {
+            true => None,
+            false => iter.move_on_key_dup(
+                &hashed_address,
+                &HashedStorageEntryWrapper(from, U256::ZERO),
+            )?,
+        };
+        let rest = first.is_some().then_some(iter).into_iter().flatten();
+
+        let stored = first
+            .map(Ok)
+            .into_iter()
+            .chain(rest)
+            .map(|item| item.map_err(Error::from))
+            .take_while(|item| match item {
+                Ok((key, entry)) => key.0 == hashed_address.0 && to.is_none_or(|to| entry.0 < to),
+                Err(_) => true,
+            })
+            .map(|item| item.map(|(_, entry)| (entry.0, entry.1)));
+
+        let changed = self
+            .changes
+            .slots
+            .range((
+                Bound::Included(from),
+                to.map_or(Bound::Unbounded, Bound::Excluded),
+            ))
+            .map(|(hashed_slot, value)| (*hashed_slot, (!value.is_zero()).then_some(*value)));
+
+        merge_leaves(stored, changed, |hashed_slot, value| {
+            hash_builder.add_leaf(Nibbles::unpack(hashed_slot), &alloy_rlp::encode(value));
+        })
+    }
+}
+
+// Merges the stored leaves with the changed ones, both sorted by their hashed key. Changed leaves
+// take precedence, `None` removes the stored leaf.
+fn merge_leaves<V>(
+    mut stored: impl Iterator<Item = Result<(B256, V), Error>>,
+    mut changed: impl Iterator<Item = (B256, Option<V>)>,
+    mut add_leaf: impl FnMut(B256, V),
+) -> Result<(), Error> {
+    let mut next_stored = stored.next().transpose()?;
+    let mut next_changed = changed.next();
+
+    loop {
+        match (next_stored.take(), next_changed.take()) {
+            (None, None) => return Ok(()),
+            (Some((key, value)), None) => {
+                add_leaf(key, value);
+                next_stored = stored.next().transpose()?;
+            }
+            (Some((stored_key, stored_value)), Some((changed_key, changed_value)))
+                if stored_key < changed_key =>
+            {
+                add_leaf(stored_key, stored_value);
+                next_stored = stored.next().transpose()?;
+                next_changed = Some((changed_key, changed_value));
+            }
+            (stored_item, Some((changed_key, changed_value))) => {
+                if let Some(value) = changed_value {
+                    add_leaf(changed_key, value);
+                }
+                next_changed = changed.next();
+
+                next_stored = match stored_item {
+                    Some((stored_key, _)) if stored_key == changed_key => {
+                        stored.next().transpose()?
+                    }
+                    stored_item => stored_item,
+                };
+            }
+        }
+    }
+}
+
+// Calculates the root of a trie from its stored branch nodes and leaves. The subtries without
+// changed leaves are added by the hash kept in their parent node, all others are walked down to
+// the next stored nodes or the leaves.
+struct TrieWalker<'a, S> {
+    source: &'a S,
+    changed: BTreeSet<B256>,
+    hash_builder: HashBuilder,
+    // Paths of the stored nodes which got replaced
+    visited: Vec<Nibbles>,
+}
+
+impl<'a, S: TrieSource> TrieWalker<'a, S> {
+    fn new(source: &'a S, changed: BTreeSet<B256>) -> Self {
+        TrieWalker {
+            source,
+            changed,
+            hash_builder: HashBuilder::default().with_updates(true),
+            visited: Vec::new(),
+        }
+    }
+
+    // Walks the paths towards `targets` and retains their nodes.
+    fn with_proof(source: &'a S, targets: Vec<B256>) -> Self {
+        let retainer = ProofRetainer::new(targets.iter().map(Nibbles::unpack).collect());
+
+        TrieWalker {
+            source,
+            changed: targets.into_iter().collect(),
+            hash_builder: HashBuilder::default().with_proof_retainer(retainer),
+            visited: Vec::new(),
+        }
+    }
+
+    fn proof(mut self) -> Result<ProofNodes, Error> {
+        self.walk(&Nibbles::default())?;
+        self.hash_builder.root();
+
+        Ok(self.hash_builder.take_proof_nodes())
+    }
+
+    fn run(mut self) -> Result<(B256, Vec<Nibbles>, HashMap<Nibbles, BranchNodeCompact>), Error> {
+        self.walk(&Nibbles::default())?;
+
+        let root = self.hash_builder.root();
+        let (_, updated) = self.hash_builder.split();
+
+        Ok((root, self.visited, updated))
+    }
+
+    fn is_changed(&self, path: &Nibbles) -> bool {
+        let to = upper_bound(path);
+
+        self.changed
+            .range(lower_bound(path)..)
+            .next()
+            .is_some_and(|key| to.is_none_or(|to| *key < to))
+    }
+
+    fn add_leaves(&mut self, from: B256, to: Option<B256>) -> Result<(), Error> {
+        if to.is_some_and(|to| to <= from) {
+            return Ok(());
+        }
+
+        self.source.add_leaves(from, to, &mut self.hash_builder)
+    }
+
+    fn walk(&mut self, prefix: &Nibbles) -> Result<(), Error> {
+        let end = upper_bound(prefix);
+        let mut from = prefix.clone();
+
+        loop {
+            let node = match self.source.seek_node(&from)? {
+                Some((path, node)) if path.starts_with(prefix) => Some((path, node)),
+                _ => None,
+            };
+
+            let Some((path, node)) = node else {
+                return self.add_leaves(lower_bound(&from), end);
+            };
+
+            self.add_leaves(lower_bound(&from), Some(lower_bound(&path)))?;
+            self.visited.push(path.clone());
+
+            for nibble in 0..16 {
+                let mut child = path.clone();
+                child.push(nibble);
+
+                match node.hash_for_nibble(nibble) {
+                    Some(hash) if !self.is_changed(&child) => {
+                        self.hash_builder
+                            .add_branch(child, hash, node.in_tree(nibble));
+                    }
+                    _ if node.in_tree(nibble) => self.walk(&child)?,
+                    _ => self.add_leaves(lower_bound(&child), upper_bound(&child))?,
+                }
+            }
+
+            // the leaves after the node within the prefix are added by the next iteration
+            match next_path(&path) {
+                Some(next) if next.starts_with(prefix) => from = next,
+                _ => return Ok(()),
+            }
+        }
+    }
+}
+
+pub(crate) fn hash_slot(slot: U256) -> B256 {
+    keccak256(slot.to_be_bytes::<32>())
+}
+
+#[cfg(test)]
+pub(crate) fn pending_commit(
+    block_number: u64,
+    accounts: Vec<(Address, u64, Vec<(u64, u64, u64)>)>,
+) -> crate::db::PendingCommit {
+    use revm::{
+        database::{AccountStatus, TransitionAccount, TransitionState, states::StorageSlot},
+        primitives::HashMap,
+        state::AccountInfo,
+    };
+
+    use crate::db::{CommitKey, PendingCommit};
+
+    let mut transitions = HashMap::default();
+
+    for (address, balance, slots) in accounts {
+        let mut storage = HashMap::default();
+        for (slot, previous_value, value) in slots {
+            storage.insert(
+                U256::from(slot),
+                StorageSlot::new_changed(U256::from(previous_value), U256::from(value)),
+            );
+        }
+
+        transitions.insert(
+            address,
+            TransitionAccount {
+                status: AccountStatus::InMemoryChange,
+                info: Some(AccountInfo {
+                    balance: U256::from(balance),
+                    ..Default::default()
+                }),
+                previous_status: AccountStatus::Loaded,
+                previous_info: None,
+                storage,
+                storage_was_destroyed: false,
+            },
+        );
+    }
+
+    PendingCommit {
+        key: CommitKey(block_number, 0, B256::ZERO),
+        transitions: TransitionState { transitions },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_trie_state_root() {
+    use alloy_trie::root::{state_root_unhashed, storage_root_unhashed};
+    use revm::{primitives::address, state::AccountInfo};
+
+    use crate::{
+        db::{PersistentDB, PersistentDBOptions},
+        state_root::{self, StateRootMode},
+    };
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db = PersistentDB::new(
+        PersistentDBOptions::new(path.path().to_path_buf())
+            .with_state_root_mode(StateRootMode::MerklePatriciaTrie),
+    )
+    .expect("database");
+
+    let address1 = address!("bd6f65c58a46427af4b257cbe231d0ed69ed5508");
+    let address2 = address!("0000000000000000000000000000000000000002");
+
+    let account = |balance: u64, storage_root: B256| TrieAccount {
+        nonce: 0,
+        balance: U256::from(balance),
+        storage_root,
+        code_hash: AccountInfo::default().code_hash,
+    };
+
+    let mut commit = pending_commit(
+        1,
+        vec![
+            (address1, 100, vec![(1, 0, 10), (2, 0, 20)]),
+            (address2, 5, vec![]),
+        ],
+    );
+
+    // calculating the root does not persist anything
+    let state_root = state_root::calculate(&mut db, &mut commit, B256::ZERO).expect("ok");
+    assert_eq!(db.get_state_root(1).unwrap(), None);
+
+    let expected_storage_root = storage_root_unhashed(vec![
+        (B256::from(U256::from(1)), U256::from(10)),
+        (B256::from(U256::from(2)), U256::from(20)),
+    ]);
+    let expected = state_root_unhashed(vec![
+        (address1, account(100, expected_storage_root)),
+        (address2, account(5, EMPTY_ROOT_HASH)),
+    ]);
+    assert_eq!(state_root, expected);
+
+    crate::state_commit::commit_to_db(&mut db, commit, None).expect("ok");
+    assert_eq!(db.get_state_root(1).unwrap(), Some(expected));
+
+    // update and delete a slot
+    let commit = pending_commit(2, vec![(address1, 50, vec![(1, 10, 0), (3, 0, 30)])]);
+    crate::state_commit::commit_to_db(&mut db, commit, None).expect("ok");
+
+    let expected_storage_root = storage_root_unhashed(vec![
+        (B256::from(U256::from(2)), U256::from(20)),
+        (B256::from(U256::from(3)), U256::from(30)),
+    ]);
+    assert_eq!(
+        db.get_state_root(2).unwrap(),
+        Some(state_root_unhashed(vec![
+            (address1, account(50, expected_storage_root)),
+            (address2, account(5, EMPTY_ROOT_HASH)),
+        ]))
+    );
+
+    // unwinding restores the previous root
+    db.unwind_to(1).expect("unwind");
+    assert_eq!(db.get_state_root(2).unwrap(), None);
+
+    let rtxn = db.env.read_txn().unwrap();
+    let inner = &db.inner;
+    let trie = inner.trie.as_ref().expect("trie");
+    assert_eq!(
+        trie.calculate(&rtxn, TrieChanges::default())
+            .unwrap()
+            .state_root,
+        expected
+    );
+}
+
+#[test]
+fn test_incremental_trie() {
+    use alloy_trie::root::{state_root_unhashed, storage_root_unhashed};
+
+    use crate::{
+        db::{PersistentDB, PersistentDBOptions},
+        state_root::{self, StateRootMode},
+    };
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db = PersistentDB::new(
+        PersistentDBOptions::new(path.path().to_path_buf())
+            .with_state_root_mode(StateRootMode::MerklePatriciaTrie),
+    )
+    .expect("database");
+
+    let mut state = BTreeMap::<Address, (u64, BTreeMap<u64, u64>)>::new();
+    let expected_root = |state: &BTreeMap<Address, (u64, BTreeMap<u64, u64>)>| {
+        state_root_unhashed(state.iter().map(|(address, (balance, slots))| {
+            let storage_root = storage_root_unhashed(
+                slots
+                    .iter()
+                    .map(|(slot, value)| (B256::from(U256::from(*slot)), U256::from(*value))),
+            );
+
+            (
+                *address,
+                TrieAccount {
+                    nonce: 0,
+                    balance: U256::from(*balance),
+                    storage_root,
+                    code_hash: KECCAK_EMPTY,
+                },
+            )
+        }))
+    };
+
+    // enough accounts and slots for nested branch nodes
+    let mut accounts = Vec::new();
+    for i in 1..=200u64 {
+        let address = Address::left_padding_from(&i.to_be_bytes());
+        let slots = (1..=i % 40)
+            .map(|slot| (slot, 0, slot * i))
+            .collect::<Vec<_>>();
+
+        state.insert(
+            address,
+            (
+                i,
+                slots
+                    .iter()
+                    .map(|(slot, _, value)| (*slot, *value))
+                    .collect(),
+            ),
+        );
+        accounts.push((address, i, slots));
+    }
+
+    crate::state_commit::commit_to_db(&mut db, pending_commit(1, accounts), None).expect("ok");
+    let root1 = expected_root(&state);
+    assert_eq!(db.get_state_root(1).unwrap(), Some(root1));
+
+    {
+        let rtxn = db.read_txn().unwrap();
+        let trie = db.inner.trie.as_ref().expect("trie");
+        assert!(!trie.account_nodes.is_empty(&rtxn).unwrap());
+        assert!(!trie.storage_nodes.is_empty(&rtxn).unwrap());
+    }
+
+    // update balances, update and delete slots of a few accounts
+    let mut accounts = Vec::new();
+    for i in (5..=200u64).step_by(15) {
+        let address = Address::left_padding_from(&i.to_be_bytes());
+        let (balance, slots) = state.get_mut(&address).expect("account");
+
+        let mut changed = Vec::new();
+        for (slot, value) in slots.iter_mut().filter(|(slot, _)| *slot % 3 == 0) {
+            changed.push((*slot, *value, *value + 1));
+            *value += 1;
+        }
+        for slot in slots
+            .keys()
+            .filter(|slot| *slot % 4 == 1)
+            .copied()
+            .collect::<Vec<_>>()
+        {
+            changed.push((slot, slots.remove(&slot).expect("slot"), 0));
+        }
+        changed.push((100, 0, 1));
+        slots.insert(100, 1);
+
+        *balance += 1;
+        accounts.push((address, *balance, changed));
+    }
+
+    let mut commit = pending_commit(2, accounts.clone());
+    let calculated = state_root::calculate(&mut db, &mut commit, B256::ZERO).expect("ok");
+    crate::state_commit::commit_to_db(&mut db, commit, None).expect("ok");
+
+    let root2 = expected_root(&state);
+    assert_eq!(calculated, root2);
+    assert_eq!(db.get_state_root(2).unwrap(), Some(root2));
+
+    // the stored nodes match the committed state
+    let rtxn = db.read_txn().unwrap();
+    let trie = db.inner.trie.as_ref().expect("trie");
+    assert_eq!(
+        trie.calculate(&rtxn, TrieChanges::default())
+            .unwrap()
+            .state_root,
+        root2
+    );
+    drop(rtxn);
+
+    // unwinding restores the previous nodes, committing again the same root
+    db.unwind_to(1).expect("unwind");
+
+    let rtxn = db.read_txn().unwrap();
+    let trie = db.inner.trie.as_ref().expect("trie");
+    assert_eq!(
+        trie.calculate(&rtxn, TrieChanges::default())
+            .unwrap()
+            .state_root,
+        root1
+    );
+    drop(rtxn);
+
+    crate::state_commit::commit_to_db(&mut db, pending_commit(2, accounts), None).expect("ok");
+    assert_eq!(db.get_state_root(2).unwrap(), Some(root2));
+}
+
+#[test]
+fn test_state_root_mode_mismatch() {
+    use crate::{
+        db::{CommitKey, PendingCommit, PersistentDB, PersistentDBOptions},
+        state_root::StateRootMode,
+    };
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db =
+        PersistentDB::new(PersistentDBOptions::new(path.path().to_path_buf())).expect("database");
+
+    crate::state_commit::commit_to_db(
+        &mut db,
+        PendingCommit {
+            key: CommitKey(1, 0, B256::ZERO),
+            ..Default::default()
+        },
+        None,
+    )
+    .expect("ok");
+
+    let result = PersistentDB::new(
+        PersistentDBOptions::new(path.path().to_path_buf())
+            .with_state_root_mode(StateRootMode::MerklePatriciaTrie),
+    );
+
+    assert!(matches!(
+        result,
+        Err(Error::StateRootModeMismatch(StateRootMode::Legacy))
+    ));
+}
+
+#[test]
+fn test_proof() {
+    use alloy_trie::proof::verify_proof;
+    use revm::primitives::address;
+
+    use crate::{
+        db::{PersistentDB, PersistentDBOptions},
+        state_root::StateRootMode,
+    };
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db = PersistentDB::new(
+        PersistentDBOptions::new(path.path().to_path_buf())
+            .with_state_root_mode(StateRootMode::MerklePatriciaTrie),
+    )
+    .expect("database");
+
+    let address1 = address!("bd6f65c58a46427af4b257cbe231d0ed69ed5508");
+    let address2 = address!("0000000000000000000000000000000000000002");
+    let missing = address!("0000000000000000000000000000000000000003");
+
+    // enough accounts and slots for the proofs to pass stored branch nodes
+    let mut accounts = (10..200u64)
+        .map(|i| (Address::left_padding_from(&i.to_be_bytes()), i, vec![]))
+        .collect::<Vec<_>>();
+    accounts.push((
+        address1,
+        100,
+        (10..100).map(|slot| (slot, 0, slot)).collect(),
+    ));
+    crate::state_commit::commit_to_db(&mut db, pending_commit(1, accounts), None).expect("ok");
+
+    crate::state_commit::commit_to_db(
+        &mut db,
+        pending_commit(
+            2,
+            vec![
+                (address1, 200, vec![(1, 0, 10), (2, 0, 20)]),
+                (address2, 5, vec![]),
+            ],
+        ),
+        None,
+    )
+    .expect("ok");
+
+    {
+        let rtxn = db.read_txn().unwrap();
+        let trie = db.inner.trie.as_ref().expect("trie");
+        assert!(!trie.account_nodes.is_empty(&rtxn).unwrap());
+        assert!(!trie.storage_nodes.is_empty(&rtxn).unwrap());
+    }
+
+    let state_root = db.get_state_root(2).unwrap().expect("state root");
+
+    // Only the latest committed block can be proven
+    assert!(matches!(
+        db.get_proof(address1, &[U256::from(1)], 1),
+        Err(Error::HistoryUnavailable(1))
+    ));
+
+    let proof = db
+        .get_proof(address1, &[U256::from(1), U256::from(3)], 2)
+        .expect("proof");
+
+    assert_eq!(proof.balance, U256::from(200));
+    verify_proof(
+        state_root,
+        Nibbles::unpack(keccak256(address1)),
+        Some(alloy_rlp::encode(TrieAccount {
+            nonce: proof.nonce,
+            balance: proof.balance,
+            storage_root: proof.storage_root,
+            code_hash: proof.code_hash,
+        })),
+        &proof.account_proof,
+    )
+    .expect("valid account proof");
+
+    // existing slot
+    assert_eq!(proof.storage_proofs[0].value, U256::from(10));
+    verify_proof(
+        proof.storage_root,
+        Nibbles::unpack(hash_slot(U256::from(1))),
+        Some(alloy_rlp::encode(U256::from(10))),
+        &proof.storage_proofs[0].proof,
+    )
+    .expect("valid storage proof");
+
+    // missing slot
+    assert_eq!(proof.storage_proofs[1].value, U256::ZERO);
+    verify_proof(
+        proof.storage_root,
+        Nibbles::unpack(hash_slot(U256::from(3))),
+        None,
+        &proof.storage_proofs[1].proof,
+    )
+    .expect("valid storage exclusion proof");
+
+    // missing account
+    let proof = db.get_proof(missing, &[], 2).expect("proof");
+    assert_eq!(proof.storage_root, EMPTY_ROOT_HASH);
+    verify_proof(
+        state_root,
+        Nibbles::unpack(keccak256(missing)),
+        None,
+        &proof.account_proof,
+    )
+    .expect("valid account exclusion proof");
+}
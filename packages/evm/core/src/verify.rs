@@ -0,0 +1,548 @@
+use std::fmt;
+
+use alloy_trie::{
+    TrieAccount,
+    root::{state_root_unsorted, storage_root_unsorted},
+};
+use heed::types::DecodeIgnore;
+use revm::primitives::{Address, B256, U256, keccak256};
+
+use crate::{
+    db::{Error, HashWrapper, PersistentDB},
+    trie::hash_slot,
+};
+
+// An inconsistency between the tables of the database.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Inconsistency {
+    // A block without an entry in `blocks_number_hash`.
+    BlockHashMissing {
+        block_number: u64,
+    },
+    // `blocks_number_hash` and `blocks_hash_number` do not refer to each other.
+    BlockHashMismatch {
+        block_number: u64,
+        block_hash: B256,
+        indexed_block_number: Option<u64>,
+    },
+    // A block without commit, thus without receipts.
+    CommitMissing {
+        block_number: u64,
+    },
+    // A transaction without an entry in `transactions_hash_key`.
+    TransactionHashMissing {
+        key: String,
+    },
+    // An entry in `transactions_hash_key` referring to a missing transaction.
+    TransactionMissing {
+        key: String,
+        transaction_hash: B256,
+    },
+    // A transaction key which is malformed or refers to a block that has not been committed.
+    OrphanedTransaction {
+        key: String,
+    },
+    ReceiptMissing {
+        block_number: u64,
+        transaction_hash: B256,
+    },
+    // Zero values are never written to the storage, instead the slot gets removed.
+    ZeroStorageValue {
+        address: Address,
+        slot: U256,
+    },
+    StateRootMissing {
+        block_number: u64,
+    },
+    // The state root recomputed from the plain state differs from the stored one.
+    StateRootMismatch {
+        block_number: u64,
+        stored: B256,
+        calculated: B256,
+    },
+}
+
+impl Inconsistency {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Inconsistency::BlockHashMissing { .. } => "BlockHashMissing",
+            Inconsistency::BlockHashMismatch { .. } => "BlockHashMismatch",
+            Inconsistency::CommitMissing { .. } => "CommitMissing",
+            Inconsistency::TransactionHashMissing { .. } => "TransactionHashMissing",
+            Inconsistency::TransactionMissing { .. } => "TransactionMissing",
+            Inconsistency::OrphanedTransaction { .. } => "OrphanedTransaction",
+            Inconsistency::ReceiptMissing { .. } => "ReceiptMissing",
+            Inconsistency::ZeroStorageValue { .. } => "ZeroStorageValue",
+            Inconsistency::StateRootMissing { .. } => "StateRootMissing",
+            Inconsistency::StateRootMismatch { .. } => "StateRootMismatch",
+        }
+    }
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inconsistency::BlockHashMissing { block_number } => {
+                write!(f, "block {} has no block hash", block_number)
+            }
+            Inconsistency::BlockHashMismatch {
+                block_number,
+                block_hash,
+                indexed_block_number,
+            } => write!(
+                f,
+                "block {} with hash {} is indexed as block {:?}",
+                block_number, block_hash, indexed_block_number
+            ),
+            Inconsistency::CommitMissing { block_number } => {
+                write!(f, "block {} has no commit", block_number)
+            }
+            Inconsistency::TransactionHashMissing { key } => {
+                write!(f, "transaction {} has no transaction hash", key)
+            }
+            Inconsistency::TransactionMissing {
+                key,
+                transaction_hash,
+            } => write!(
+                f,
+                "transaction {} referred to by hash {} is missing",
+                key, transaction_hash
+            ),
+            Inconsistency::OrphanedTransaction { key } => {
+                write!(
+                    f,
+                    "transaction {} does not belong to a committed block",
+                    key
+                )
+            }
+            Inconsistency::ReceiptMissing {
+                block_number,
+                transaction_hash,
+            } => write!(
+                f,
+                "block {} has no receipt for transaction {}",
+                block_number, transaction_hash
+            ),
+            Inconsistency::ZeroStorageValue { address, slot } => {
+                write!(f, "storage slot {} of {} has a zero value", slot, address)
+            }
+            Inconsistency::StateRootMissing { block_number } => {
+                write!(f, "block {} has no state root", block_number)
+            }
+            Inconsistency::StateRootMismatch {
+                block_number,
+                stored,
+                calculated,
+            } => write!(
+                f,
+                "state root of block {} is {} but the state hashes to {}",
+                block_number, stored, calculated
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    pub blocks: u64,
+    pub transactions: u64,
+    pub storage_entries: u64,
+    pub inconsistencies: Vec<Inconsistency>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.inconsistencies.is_empty()
+    }
+}
+
+impl PersistentDB {
+    // Walks the entire database in a single read transaction and collects all inconsistencies
+    // between the tables. The accounts, contracts and storage hashes stored in the commits are
+    // calculated over the change set of a commit, which is not retained, thus they are not
+    // verified. Only the Merkle Patricia Trie state root of the last commit is recomputed from the
+    // plain state.
+    pub fn verify(&self) -> Result<VerifyReport, Error> {
+        let rtxn = self.read_txn()?;
+        let inner = &self.inner;
+
+        let mut report = VerifyReport::default();
+
+        // Blocks
+        let blocks = inner.blocks.remap_data_type::<DecodeIgnore>();
+        let commits = inner.commits.remap_data_type::<DecodeIgnore>();
+
+        for item in blocks.iter(&rtxn)? {
+            let (block_number, _) = item?;
+            report.blocks += 1;
+
+            match inner.blocks_number_hash.get(&rtxn, &block_number)? {
+                Some(block_hash) => {
+                    let indexed_block_number = inner.blocks_hash_number.get(&rtxn, &block_hash)?;
+                    if indexed_block_number != Some(block_number) {
+                        report
+                            .inconsistencies
+                            .push(Inconsistency::BlockHashMismatch {
+                                block_number,
+                                block_hash: block_hash.0,
+                                indexed_block_number,
+                            });
+                    }
+                }
+                None => report
+                    .inconsistencies
+                    .push(Inconsistency::BlockHashMissing { block_number }),
+            }
+
+            if commits.get(&rtxn, &block_number)?.is_none() {
+                report
+                    .inconsistencies
+                    .push(Inconsistency::CommitMissing { block_number });
+            }
+        }
+
+        // Block hashes are kept for blocks which are not stored, e.g. after a snapshot import
+        for item in inner.blocks_hash_number.iter(&rtxn)? {
+            let (block_hash, block_number) = item?;
+
+            if inner
+                .blocks_number_hash
+                .get(&rtxn, &block_number)?
+                .is_none_or(|indexed_hash| indexed_hash.0 != block_hash.0)
+            {
+                report
+                    .inconsistencies
+                    .push(Inconsistency::BlockHashMismatch {
+                        block_number,
+                        block_hash: block_hash.0,
+                        indexed_block_number: Some(block_number),
+                    });
+            }
+        }
+
+        // Transactions and receipts. Both tables are walked in their own order with lookups into
+        // the other one, the hash of a transaction is taken from the receipt with its index.
+        let transactions = inner.transactions.remap_key_type::<heed::types::Str>();
+
+        for item in inner.transactions_hash_key.iter(&rtxn)? {
+            let (transaction_hash, key) = item?;
+
+            if transactions
+                .remap_data_type::<DecodeIgnore>()
+                .get(&rtxn, &key)?
+                .is_none()
+            {
+                report
+                    .inconsistencies
+                    .push(Inconsistency::TransactionMissing {
+                        key,
+                        transaction_hash: transaction_hash.0,
+                    });
+                continue;
+            }
+
+            // transactions of blocks without commit are reported below
+            if let Some((block_number, _)) = parse_transaction_key(&key)
+                && let Some(receipts) = inner.commits.get(&rtxn, &block_number)?
+                && !receipts.tx_hashes().any(|hash| *hash == transaction_hash.0)
+            {
+                report.inconsistencies.push(Inconsistency::ReceiptMissing {
+                    block_number,
+                    transaction_hash: transaction_hash.0,
+                });
+            }
+        }
+
+        let mut commit = None;
+        for item in transactions.remap_data_type::<DecodeIgnore>().iter(&rtxn)? {
+            let (key, _) = item?;
+            report.transactions += 1;
+
+            let Some((block_number, sequence)) = parse_transaction_key(key) else {
+                report
+                    .inconsistencies
+                    .push(Inconsistency::OrphanedTransaction {
+                        key: key.to_owned(),
+                    });
+                continue;
+            };
+
+            // the keys of a block are adjacent
+            if commit
+                .as_ref()
+                .is_none_or(|(number, _)| *number != block_number)
+            {
+                commit = Some((block_number, inner.commits.get(&rtxn, &block_number)?));
+            }
+
+            let Some((_, Some(receipts))) = &commit else {
+                report
+                    .inconsistencies
+                    .push(Inconsistency::OrphanedTransaction {
+                        key: key.to_owned(),
+                    });
+                continue;
+            };
+
+            // without receipt the hash is unknown, a missing receipt is reported above
+            let Some((transaction_hash, _)) = receipts
+                .tx_receipts
+                .iter()
+                .find(|(_, receipt)| receipt.transaction_index == sequence)
+            else {
+                continue;
+            };
+
+            if inner
+                .transactions_hash_key
+                .get(&rtxn, &HashWrapper(*transaction_hash))?
+                .is_none_or(|indexed_key| indexed_key != key)
+            {
+                report
+                    .inconsistencies
+                    .push(Inconsistency::TransactionHashMissing {
+                        key: key.to_owned(),
+                    });
+            }
+        }
+
+        // Storage
+        for item in inner.storage.iter(&rtxn)? {
+            let (address, entry) = item?;
+            report.storage_entries += 1;
+
+            if entry.1.is_zero() {
+                report
+                    .inconsistencies
+                    .push(Inconsistency::ZeroStorageValue {
+                        address: address.0,
+                        slot: entry.0,
+                    });
+            }
+        }
+
+        // State root
+        if let Some(trie) = &inner.trie
+            && let Some((block_number, _)) = commits.last(&rtxn)?
+        {
+            match trie.state_roots.get(&rtxn, &block_number)? {
+                Some(HashWrapper(stored)) => {
+                    let mut accounts = Vec::new();
+                    for item in inner.accounts.iter(&rtxn)? {
+                        let (address, info) = item?;
+
+                        let mut slots = Vec::new();
+                        if let Some(iter) = inner.storage.get_duplicates(&rtxn, &address)? {
+                            for item in iter {
+                                let (_, entry) = item?;
+                                if !entry.1.is_zero() {
+                                    slots.push((hash_slot(entry.0), entry.1));
+                                }
+                            }
+                        }
+
+                        accounts.push((
+                            keccak256(address.0),
+                            TrieAccount {
+                                nonce: info.nonce,
+                                balance: info.balance,
+                                storage_root: storage_root_unsorted(slots),
+                                code_hash: info.code_hash,
+                            },
+                        ));
+                    }
+
+                    let calculated = state_root_unsorted(accounts);
+                    if calculated != stored {
+                        report
+                            .inconsistencies
+                            .push(Inconsistency::StateRootMismatch {
+                                block_number,
+                                stored,
+                                calculated,
+                            });
+                    }
+                }
+                None => report
+                    .inconsistencies
+                    .push(Inconsistency::StateRootMissing { block_number }),
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+// Splits a `<block number>-<sequence>` transaction key.
+fn parse_transaction_key(key: &str) -> Option<(u64, u64)> {
+    let (block_number, sequence) = key.split_once('-')?;
+
+    Some((block_number.parse().ok()?, sequence.parse().ok()?))
+}
+
+#[test]
+fn test_verify() {
+    use revm::{
+        context::result::{ExecutionResult, Output, SuccessReason},
+        primitives::{Bytes, address},
+    };
+
+    use crate::{
+        db::{AddressWrapper, CommitData, PersistentDBOptions, StorageEntryWrapper},
+        receipt::executed_transaction,
+        state_root::StateRootMode,
+        trie::pending_commit,
+    };
+
+    let path = tempfile::Builder::new()
+        .prefix("evm.mdb")
+        .tempdir()
+        .unwrap();
+
+    let mut db = PersistentDB::new(
+        PersistentDBOptions::new(path.path().to_path_buf())
+            .with_state_root_mode(StateRootMode::MerklePatriciaTrie),
+    )
+    .expect("database");
+
+    let address = address!("bd6f65c58a46427af4b257cbe231d0ed69ed5508");
+    let transaction_hash =
+        |block_number: u64, sequence: u64| B256::from(U256::from(block_number * 1000 + sequence));
+
+    for block_number in 1..=3 {
+        let mut commit = pending_commit(
+            block_number,
+            vec![(address, block_number, vec![(block_number, 0, 1)])],
+        );
+
+        let transaction_hashes = vec![
+            transaction_hash(block_number, 0),
+            transaction_hash(block_number, 1),
+        ];
+
+        for hash in transaction_hashes.iter() {
+            commit.results.insert(
+                *hash,
+                executed_transaction(
+                    commit.results.len() as u64,
+                    ExecutionResult::Success {
+                        reason: SuccessReason::Stop,
+                        gas_used: 21000,
+                        gas_refunded: 0,
+                        logs: vec![],
+                        output: Output::Call(Bytes::new()),
+                    },
+                ),
+            );
+        }
+
+        crate::state_commit::commit_to_db(
+            &mut db,
+            commit,
+            Some(CommitData {
+                block_hash: B256::from(U256::from(block_number)),
+                block: Bytes::from_static(&[1]),
+                transactions: vec![Bytes::from_static(&[2]); 2],
+                transaction_hashes,
+                ..Default::default()
+            }),
+        )
+        .expect("ok");
+    }
+
+    let report = db.verify().expect("verify");
+    assert!(report.is_ok(), "{:?}", report.inconsistencies);
+    assert_eq!(report.blocks, 3);
+    assert_eq!(report.transactions, 6);
+    assert_eq!(report.storage_entries, 3);
+
+    // Corrupt the database
+    {
+        let mut wtxn = db.env.write_txn().unwrap();
+        let inner = &db.inner;
+
+        inner
+            .blocks_hash_number
+            .delete(&mut wtxn, &HashWrapper(B256::from(U256::from(2))))
+            .unwrap();
+        inner.commits.delete(&mut wtxn, &1).unwrap();
+        inner
+            .transactions_hash_key
+            .delete(&mut wtxn, &HashWrapper(transaction_hash(3, 0)))
+            .unwrap();
+        inner
+            .transactions
+            .remap_key_type::<heed::types::Str>()
+            .delete(&mut wtxn, "3-1")
+            .unwrap();
+        inner
+            .storage
+            .put(
+                &mut wtxn,
+                &AddressWrapper(address),
+                &StorageEntryWrapper(U256::from(100), U256::ZERO),
+            )
+            .unwrap();
+
+        wtxn.commit().unwrap();
+    }
+
+    let report = db.verify().expect("verify");
+    assert!(!report.is_ok());
+    assert_eq!(
+        report.inconsistencies,
+        vec![
+            Inconsistency::CommitMissing { block_number: 1 },
+            Inconsistency::BlockHashMismatch {
+                block_number: 2,
+                block_hash: B256::from(U256::from(2)),
+                indexed_block_number: None,
+            },
+            Inconsistency::TransactionMissing {
+                key: "3-1".to_owned(),
+                transaction_hash: transaction_hash(3, 1),
+            },
+            Inconsistency::OrphanedTransaction {
+                key: "1-0".to_owned()
+            },
+            Inconsistency::OrphanedTransaction {
+                key: "1-1".to_owned()
+            },
+            Inconsistency::TransactionHashMissing {
+                key: "3-0".to_owned()
+            },
+            Inconsistency::ZeroStorageValue {
+                address,
+                slot: U256::from(100),
+            },
+        ]
+    );
+
+    // Recomputing the state root detects changes which bypass the trie
+    {
+        let mut wtxn = db.env.write_txn().unwrap();
+        let inner = &db.inner;
+
+        let mut info = inner
+            .accounts
+            .get(&wtxn, &AddressWrapper(address))
+            .unwrap()
+            .unwrap();
+        info.balance = U256::from(1);
+        inner
+            .accounts
+            .put(&mut wtxn, &AddressWrapper(address), &info)
+            .unwrap();
+
+        wtxn.commit().unwrap();
+    }
+
+    let report = db.verify().expect("verify");
+    assert!(matches!(
+        report.inconsistencies.last(),
+        Some(Inconsistency::StateRootMismatch {
+            block_number: 3,
+            ..
+        })
+    ));
+}